@@ -43,8 +43,80 @@ pub struct PhysicalAction<T: Sync>(pub(crate) Action<Physical, T>);
 pub(crate) struct Logical;
 pub(crate) struct Physical;
 
+/// The minimum spacing between two events of an action,
+/// like the `minSpacing` parameter of LF actions. This
+/// is the minimum logical time between the tags of two
+/// successive events. An event that does not respect it
+/// is handled according to the [SpacingPolicy].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MinSpacing {
+    /// Minimum time between two events. If zero, two events
+    /// only need to be at different tags.
+    pub spacing: Duration,
+    /// What to do with events that are too close to the previous one.
+    pub policy: SpacingPolicy,
+}
+
+/// What to do when an action is scheduled too close to its
+/// previous event, see [MinSpacing].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum SpacingPolicy {
+    /// The new event is postponed to the earliest tag that
+    /// respects the minimum spacing.
+    #[default]
+    Defer,
+    /// The new event is ignored.
+    Drop,
+    /// If the previous event has not been processed yet, its
+    /// value is replaced by the new one. Otherwise the new
+    /// event is deferred.
+    Replace,
+}
+
+/// The result of scheduling an action, see
+/// [ReactionCtx::schedule_with_v](crate::ReactionCtx::schedule_with_v).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScheduleOutcome {
+    /// A new event was scheduled at the requested tag.
+    Scheduled(EventTag),
+    /// A new event was scheduled, but later than requested,
+    /// to respect the minimum spacing of the action.
+    Deferred(EventTag),
+    /// No new event was scheduled, instead the value of a pending
+    /// event at the given tag was replaced. This happens when
+    /// the action is scheduled several times for the same tag,
+    /// or with [SpacingPolicy::Replace].
+    Replaced(EventTag),
+    /// The event was dropped, because of [SpacingPolicy::Drop].
+    Dropped,
+}
+
+impl ScheduleOutcome {
+    /// Returns the tag at which the action will be present
+    /// with the given value, or None if the event was dropped.
+    pub fn tag(&self) -> Option<EventTag> {
+        match *self {
+            ScheduleOutcome::Scheduled(tag) | ScheduleOutcome::Deferred(tag) | ScheduleOutcome::Replaced(tag) => Some(tag),
+            ScheduleOutcome::Dropped => None,
+        }
+    }
+
+    /// Whether a new event must be pushed to the event queue.
+    pub(crate) fn is_new_event(&self) -> bool {
+        matches!(self, ScheduleOutcome::Scheduled(_) | ScheduleOutcome::Deferred(_))
+    }
+}
+
 pub(crate) struct Action<Kind, T: Sync> {
     pub(crate) min_delay: Duration,
+    min_spacing: Option<MinSpacing>,
+    /// Tag of the latest event of this action, used to
+    /// enforce the min spacing.
+    last_event: Option<EventTag>,
     id: TriggerId,
     // is_logical: bool,
     _logical: PhantomData<Kind>,
@@ -61,17 +133,77 @@ impl<K, T: Sync> Action<K, T> {
     /// Note that we don't check that the given time is in the future. If it's
     /// in the past, the value will never be reclaimed.
     ///
-    ///
-    #[inline]
-    pub(crate) fn schedule_future_value(&mut self, time: EventTag, value: Option<T>) {
+    /// The min spacing of the action is enforced here, so the
+    /// value may be recorded for another tag, or not at all.
+    /// The caller must push an event to the queue if the outcome
+    /// is a [new event](ScheduleOutcome::is_new_event). The
+    /// current tag `now` is used to know whether the previous
+    /// event has been processed already.
+    pub(crate) fn schedule_future_value(&mut self, now: EventTag, time: EventTag, value: Option<T>) -> ScheduleOutcome {
+        let mut outcome = ScheduleOutcome::Scheduled(time);
+
+        if let (Some(MinSpacing { spacing, policy }), Some(last)) = (self.min_spacing, self.last_event) {
+            let earliest = if spacing.is_zero() {
+                last.next_microstep()
+            } else {
+                EventTag::offset(last.duration_since_start() + spacing, 0)
+            };
+            if time < earliest {
+                let last_is_pending = last > now && self.map.contains_key(&Reverse(last));
+                outcome = match policy {
+                    SpacingPolicy::Drop => ScheduleOutcome::Dropped,
+                    SpacingPolicy::Replace if last_is_pending => ScheduleOutcome::Replaced(last),
+                    SpacingPolicy::Defer | SpacingPolicy::Replace => ScheduleOutcome::Deferred(earliest),
+                };
+                trace!("Action scheduled at {} within min spacing of {}: {:?}", time, last, outcome);
+            }
+        }
+
+        let time = match outcome {
+            ScheduleOutcome::Dropped => return outcome,
+            other => other.tag().unwrap(),
+        };
+
         match self.map.entry(Reverse(time)) {
             Entry::Vacant(e) => e.insert(value),
             Entry::Occupied(e) => {
                 trace!("Value overwritten in an action for tag {}", time);
                 trace!("This means an action was scheduled several times for the same tag.");
+                outcome = ScheduleOutcome::Replaced(time);
                 e.replace(value)
             }
         }
+        if outcome.is_new_event() {
+            self.last_event = Some(time);
+        }
+        outcome
+    }
+
+    /// Replace the value of the latest event of this action,
+    /// provided the scheduler has not started processing its
+    /// tag yet. Returns the tag of that event, or gives back
+    /// the value if there is no such event.
+    pub(crate) fn replace_pending_value(&mut self, latest_started: EventTag, value: Option<T>) -> Result<EventTag, Option<T>> {
+        match self.last_event {
+            Some(last) if latest_started < last => match self.map.entry(Reverse(last)) {
+                Entry::Occupied(e) => {
+                    e.replace(value);
+                    Ok(last)
+                }
+                Entry::Vacant(_) => Err(value),
+            },
+            _ => Err(value),
+        }
+    }
+
+    /// Returns the given tag, or the first later microstep at
+    /// which this action has no pending event, so that a new
+    /// event does not overwrite the value of another.
+    pub(crate) fn first_free_tag(&self, mut tag: EventTag) -> EventTag {
+        while self.map.contains_key(&Reverse(tag)) {
+            tag = tag.next_microstep();
+        }
+        tag
     }
 
     #[inline]
@@ -79,9 +211,11 @@ impl<K, T: Sync> Action<K, T> {
         self.map.remove(&Reverse(*time)).flatten()
     }
 
-    fn new_impl(id: TriggerId, min_delay: Option<Duration>, _is_logical: bool) -> Self {
+    fn new_impl(id: TriggerId, min_delay: Option<Duration>, min_spacing: Option<MinSpacing>, _is_logical: bool) -> Self {
         Action {
             min_delay: min_delay.unwrap_or(Duration::ZERO),
+            min_spacing,
+            last_event: None,
             // is_logical,
             id,
             _logical: PhantomData,
@@ -133,14 +267,14 @@ impl<T: Sync> ReactionTrigger<T> for LogicalAction<T> {
 }
 
 impl<T: Sync> LogicalAction<T> {
-    pub(crate) fn new(id: TriggerId, min_delay: Option<Duration>) -> Self {
-        Self(Action::new_impl(id, min_delay, true))
+    pub(crate) fn new(id: TriggerId, min_delay: Option<Duration>, min_spacing: Option<MinSpacing>) -> Self {
+        Self(Action::new_impl(id, min_delay, min_spacing, true))
     }
 }
 
 impl<T: Sync> PhysicalAction<T> {
-    fn new(id: TriggerId, min_delay: Option<Duration>) -> Self {
-        Self(Action::new_impl(id, min_delay, false))
+    fn new(id: TriggerId, min_delay: Option<Duration>, min_spacing: Option<MinSpacing>) -> Self {
+        Self(Action::new_impl(id, min_delay, min_spacing, false))
     }
 }
 
@@ -213,12 +347,18 @@ mod test {
 /// on the action are
 ///
 /// See [crate::ReactionCtx::spawn_physical_thread].
-#[derive(Clone)]
 pub struct PhysicalActionRef<T: Sync>(Arc<Mutex<PhysicalAction<T>>>);
 
+// not derived, which would require T: Clone
+impl<T: Sync> Clone for PhysicalActionRef<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
 impl<T: Sync> PhysicalActionRef<T> {
-    pub(crate) fn new(id: TriggerId, min_delay: Option<Duration>) -> Self {
-        Self(Arc::new(Mutex::new(PhysicalAction::new(id, min_delay))))
+    pub(crate) fn new(id: TriggerId, min_delay: Option<Duration>, min_spacing: Option<MinSpacing>) -> Self {
+        Self(Arc::new(Mutex::new(PhysicalAction::new(id, min_delay, min_spacing))))
     }
 
     pub(crate) fn use_mut<O>(&self, f: impl FnOnce(&mut PhysicalAction<T>) -> O) -> Result<O, ()> {
@@ -1,6 +1,8 @@
 //! Module containing the API to initialize a reactor program.
 
-use AssemblyErrorImpl::*;
+use std::fmt::{self, Display, Formatter};
+
+use AssemblyDiagnostic::*;
 
 pub use crate::ids::GlobalReactionId;
 // this is where most of the stuff is implemented
@@ -38,40 +40,280 @@ pub type AssemblyResult<T = ()> = Result<T, AssemblyError>;
 /// An error occurring during initialization of the reactor program.
 /// Should never occur unless the graph is built by hand, and not
 /// by a Lingua Franca compiler.
-pub struct AssemblyError(pub(crate) AssemblyErrorImpl);
+///
+/// Assembly does not stop at the first problem it finds. This
+/// error contains every [diagnostic](AssemblyDiagnostic) that
+/// was reported during assembly, so that a single run reports
+/// all of them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AssemblyError {
+    diagnostics: Vec<AssemblyDiagnostic>,
+}
 
 impl AssemblyError {
+    /// Create an error with a single user-defined diagnostic.
+    /// This can be used by user-defined validations within
+    /// [ReactorInitializer::assemble].
+    pub fn custom(message: impl Into<String>) -> Self {
+        AssemblyDiagnostic::Custom { reactor: None, message: message.into() }.into()
+    }
+
+    /// Returns the diagnostics contained in this error,
+    /// in the order they were reported. This is never empty.
+    pub fn diagnostics(&self) -> &[AssemblyDiagnostic] {
+        &self.diagnostics
+    }
+
+    /// Returns the diagnostics contained in this error.
+    pub fn into_diagnostics(self) -> Vec<AssemblyDiagnostic> {
+        self.diagnostics
+    }
+
+    /// Build an error from the given diagnostics, returns
+    /// None if there are none.
+    pub(crate) fn from_diagnostics(diagnostics: Vec<AssemblyDiagnostic>) -> Option<Self> {
+        if diagnostics.is_empty() {
+            None
+        } else {
+            Some(AssemblyError { diagnostics })
+        }
+    }
+
+    /// Append the diagnostics of the other error to this one.
+    pub(crate) fn merge(&mut self, other: AssemblyError) {
+        self.diagnostics.extend(other.diagnostics)
+    }
+
+    /// Resolve the paths of all components mentioned by
+    /// the diagnostics.
+    pub(crate) fn with_paths(mut self, debug: &DebugInfoRegistry) -> Self {
+        for diagnostic in &mut self.diagnostics {
+            diagnostic.resolve_paths(debug)
+        }
+        self
+    }
+
     pub(crate) fn lift(self, debug: &DebugInfoRegistry) -> String {
-        self.display(debug)
+        self.with_paths(debug).to_string()
+    }
+}
+
+impl From<AssemblyDiagnostic> for AssemblyError {
+    fn from(diagnostic: AssemblyDiagnostic) -> Self {
+        AssemblyError { diagnostics: vec![diagnostic] }
+    }
+}
+
+impl Display for AssemblyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.diagnostics.as_slice() {
+            [single] => write!(f, "{}", single),
+            all => {
+                write!(f, "{} errors during assembly:", all.len())?;
+                for diagnostic in all {
+                    write!(f, "\n - {}", diagnostic)?;
+                }
+                Ok(())
+            }
+        }
     }
 }
 
-pub(crate) enum AssemblyErrorImpl {
-    CyclicDependency(PortId, PortId),
-    CyclicDependencyGraph,
-    CannotBind(PortId, PortId),
+impl std::error::Error for AssemblyError {}
+
+/// A single problem found during assembly.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum AssemblyDiagnostic {
+    /// Binding the upstream port to the downstream port would
+    /// create a cycle, because the upstream port is already
+    /// in the downstream of the other.
+    CyclicDependency {
+        upstream: ComponentRef,
+        downstream: ComponentRef,
+        /// The existing chain of bindings, from the downstream
+        /// port to the upstream port.
+        chain: Vec<ComponentRef>,
+    },
+    /// The dependency graph contains a cycle. The cycle lists
+    /// each element along it, with the kind of dependency that
+    /// leads to the next element. The last element depends on
+    /// the first.
+    CyclicDependencyGraph { cycle: Vec<(GraphElement, DependencyKind)> },
+    /// The downstream port is already bound to another port.
+    CannotBind {
+        upstream: ComponentRef,
+        downstream: ComponentRef,
+    },
+    /// Too many components were allocated.
     IdOverflow,
+    /// Two port sequences of different widths were bound together.
+    /// Extra ports on the wider side are left unconnected.
+    WidthMismatch {
+        /// Path of the reactor that declared the connection.
+        reactor: String,
+        upstream_width: usize,
+        downstream_width: usize,
+    },
+    /// A diagnostic reported by user code.
+    Custom {
+        /// Path of the reactor that reported the diagnostic,
+        /// if known.
+        reactor: Option<String>,
+        message: String,
+    },
 }
 
-impl AssemblyError {
-    fn display(&self, debug: &DebugInfoRegistry) -> String {
-        match self.0 {
-            CyclicDependency(upstream, downstream) => format!(
-                "Port {} is already in the downstream of port {}",
-                debug.fmt_component(upstream),
-                debug.fmt_component(downstream)
-            ),
-            CyclicDependencyGraph => "Cyclic dependency graph".to_string(),
-            CannotBind(upstream, downstream) => format!(
-                "Cannot bind {} to {}, downstream is already bound",
-                debug.fmt_component(upstream),
-                debug.fmt_component(downstream)
+impl AssemblyDiagnostic {
+    fn resolve_paths(&mut self, debug: &DebugInfoRegistry) {
+        match self {
+            CyclicDependency { upstream, downstream, chain } => {
+                upstream.resolve_path(debug);
+                downstream.resolve_path(debug);
+                for port in chain {
+                    port.resolve_path(debug);
+                }
+            }
+            CannotBind { upstream, downstream } => {
+                upstream.resolve_path(debug);
+                downstream.resolve_path(debug);
+            }
+            CyclicDependencyGraph { cycle } => {
+                for (element, _) in cycle {
+                    element.resolve_path(debug);
+                }
+            }
+            IdOverflow | WidthMismatch { .. } | Custom { .. } => {}
+        }
+    }
+}
+
+impl Display for AssemblyDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CyclicDependency { upstream, downstream, chain } => {
+                write!(f, "Cannot bind {} to {}, this would create a cycle:", upstream, downstream)?;
+                for port in chain {
+                    write!(f, " {} ->", port)?;
+                }
+                write!(f, " {}", downstream)
+            }
+            CyclicDependencyGraph { cycle } => {
+                write!(f, "Cyclic dependency graph:")?;
+                for (element, kind) in cycle {
+                    write!(f, " {} --({})-->", element, kind)?;
+                }
+                match cycle.first() {
+                    Some((first, _)) => write!(f, " {}", first),
+                    None => Ok(()),
+                }
+            }
+            CannotBind { upstream, downstream } => {
+                write!(f, "Cannot bind {} to {}, downstream is already bound", upstream, downstream)
+            }
+            IdOverflow => write!(f, "Overflow when allocating component ID"),
+            WidthMismatch { reactor, upstream_width, downstream_width } => write!(
+                f,
+                "In {}: cannot bind {} upstream port(s) to {} downstream port(s)",
+                reactor, upstream_width, downstream_width
             ),
-            IdOverflow => "Overflow when allocating component ID".to_string(),
+            Custom { reactor: Some(reactor), message } => write!(f, "In {}: {}", reactor, message),
+            Custom { reactor: None, message } => write!(f, "{}", message),
         }
     }
 }
 
+/// Designates a component of the program in a diagnostic.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ComponentRef {
+    /// Id of the component.
+    pub id: TriggerId,
+    /// Path of the component, if it could be determined.
+    pub path: Option<String>,
+}
+
+impl ComponentRef {
+    pub(crate) fn new(id: TriggerId) -> Self {
+        Self { id, path: None }
+    }
+
+    fn resolve_path(&mut self, debug: &DebugInfoRegistry) {
+        if self.path.is_none() {
+            self.path = debug.try_fmt_component(self.id);
+        }
+    }
+}
+
+impl Display for ComponentRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}", path),
+            None => write!(f, "#{:?}", self.id),
+        }
+    }
+}
+
+/// Designates a node of the dependency graph in a diagnostic.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum GraphElement {
+    Reaction {
+        id: GlobalReactionId,
+        /// Path of the reaction, if it could be determined.
+        path: Option<String>,
+    },
+    Component(ComponentRef),
+}
+
+impl GraphElement {
+    fn resolve_path(&mut self, debug: &DebugInfoRegistry) {
+        match self {
+            GraphElement::Reaction { id, path: path @ None } => *path = debug.try_fmt_reaction(*id),
+            GraphElement::Reaction { .. } => {}
+            GraphElement::Component(component) => component.resolve_path(debug),
+        }
+    }
+}
+
+impl Display for GraphElement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphElement::Reaction { path: Some(path), .. } => write!(f, "reaction {}", path),
+            GraphElement::Reaction { id, path: None } => write!(f, "reaction {}", id),
+            GraphElement::Component(component) => write!(f, "{}", component),
+        }
+    }
+}
+
+/// Kind of an edge of the dependency graph.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum DependencyKind {
+    /// A port or action triggers a reaction.
+    Trigger,
+    /// A reaction uses a port without being triggered by it.
+    Use,
+    /// A reaction effects a port.
+    Effect,
+    /// A port is bound to another, or a multiport
+    /// contains a channel.
+    Binding,
+    /// A reaction has priority over another reaction
+    /// of the same reactor.
+    Priority,
+}
+
+impl Display for DependencyKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DependencyKind::Trigger => "trigger",
+            DependencyKind::Use => "use",
+            DependencyKind::Effect => "effect",
+            DependencyKind::Binding => "binding",
+            DependencyKind::Priority => "priority",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 /// Kind of a port.
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
 pub enum PortKind {
@@ -0,0 +1,414 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+//! Loading of options and parameters from a configuration
+//! file and from environment variables. This is only
+//! available with the `config` feature.
+
+use std::path::Path;
+
+use super::*;
+
+/// Environment variable that contains the path to a
+/// configuration file, if `--config` is not passed.
+const CONFIG_VAR: &str = "LF_CONFIG";
+/// Prefix of environment variables that set runtime options.
+const OPTION_PREFIX: &str = "LF_";
+/// Prefix of environment variables that set main reactor parameters.
+const PARAM_PREFIX: &str = "LF_PARAM_";
+
+impl Cli {
+    /// Parse the given arguments like [Self::parse], but also
+    /// read options and parameters from a configuration file
+    /// and from environment variables. Sources have increasing
+    /// precedence in this order:
+    /// - the `defaults`, and the default values of parameters,
+    /// - the configuration file, given by `--config <path>`, or
+    ///   else by the `LF_CONFIG` environment variable,
+    /// - environment variables: `LF_<OPTION>` for runtime options
+    ///   (eg `LF_TIMEOUT`, `LF_DUMP_GRAPH`), and `LF_PARAM_<NAME>`
+    ///   for main reactor parameters (eg `LF_PARAM_COUNT`),
+    /// - command-line arguments.
+    ///
+    /// The configuration file is a TOML or JSON document, depending
+    /// on its extension. Runtime options are top-level keys, named
+    /// like their command-line counterpart, and parameters are
+    /// set in a `params` table:
+    /// ```toml
+    /// timeout = "2 s"
+    /// dump-graph = true
+    ///
+    /// [params]
+    /// count = 12
+    /// period = "100 ms"
+    /// ```
+    ///
+    /// Durations are written as strings like on the command line.
+    /// Repeatable options take an array, eg `break = ["T0 + 1 s", "child/on_tick"]`.
+    /// Unknown keys are an error, unknown `LF_*` variables are ignored.
+    pub fn parse_with_config<I, S, E>(&self, defaults: SchedulerOptions, args: I, env: E) -> Result<CliArgs, CliError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+        E: IntoIterator<Item = (String, String)>,
+    {
+        let mut args: Vec<String> = args.into_iter().map(|s| s.as_ref().to_owned()).collect();
+        if args.iter().any(|a| a == "-h" || a == "--help") {
+            return Err(CliError::Help(self.help(&defaults)));
+        }
+        let env: Vec<(String, String)> = env.into_iter().filter(|(k, _)| k.starts_with(OPTION_PREFIX)).collect();
+
+        let config_file =
+            take_config_arg(&mut args)?.or_else(|| env.iter().find(|(k, _)| k == CONFIG_VAR).map(|(_, v)| v.clone()));
+
+        let mut state = ParseState::new(self, defaults);
+        if let Some(path) = config_file {
+            self.apply_config_file(&mut state, Path::new(&path))?;
+        }
+        self.apply_env(&mut state, &env)?;
+        self.apply_args(&mut state, args)?;
+        Ok(state.finish(self))
+    }
+
+    fn apply_config_file(&self, state: &mut ParseState, path: &Path) -> Result<(), CliError> {
+        let file = path.to_string_lossy();
+        let error = |message: String| CliError::Invalid(format!("{}: {}", file, message));
+
+        let text = std::fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+        let entries = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => from_toml(text.parse::<toml::Value>().map_err(|e| error(e.to_string()))?),
+            Some("json") => from_json(serde_json::from_str(&text).map_err(|e| error(e.to_string()))?),
+            _ => return Err(error("unknown configuration format, expected a .toml or .json file".into())),
+        };
+        let entries = match entries {
+            ConfigValue::Table(entries) => entries,
+            _ => return Err(error("expected a table at the top level".into())),
+        };
+
+        for (key, value) in entries {
+            if key == "params" {
+                let params = match value {
+                    ConfigValue::Table(params) => params,
+                    _ => return Err(error("expected a table for key 'params'".into())),
+                };
+                for (name, value) in params {
+                    let key = format!("params.{}", name);
+                    let value = value.into_scalar().map_err(|e| error(format!("{} for key '{}'", e, key)))?;
+                    let i = self
+                        .param_index(&name)
+                        .ok_or_else(|| error(format!("unknown key '{}'", key)))?;
+                    state
+                        .set_param(self, i, value)
+                        .map_err(|(value, e)| error(format!("invalid value '{}' for key '{}': {}", value, key, e)))?;
+                }
+            } else {
+                let opt = runtime_option(&key.replace('_', "-")).ok_or_else(|| error(format!("unknown key '{}'", key)))?;
+                let values = match value {
+                    ConfigValue::Array(values) if opt.is_repeatable => values.into_iter().map(ConfigValue::into_scalar).collect(),
+                    value => value.into_scalar().map(|value| vec![value]),
+                };
+                for value in values.map_err(|e| error(format!("{} for key '{}'", e, key)))? {
+                    (opt.apply)(&mut state.options, &value)
+                        .map_err(|e| error(format!("invalid value '{}' for key '{}': {}", value, key, e)))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_env(&self, state: &mut ParseState, env: &[(String, String)]) -> Result<(), CliError> {
+        let invalid = |var: &str, value: &str, e: String| {
+            CliError::Invalid(format!("invalid value '{}' for environment variable {}: {}", value, var, e))
+        };
+
+        for (var, value) in env {
+            if let Some(name) = var.strip_prefix(PARAM_PREFIX) {
+                match self.params.iter().position(|p| env_name(&p.name) == name) {
+                    Some(i) => state
+                        .set_param(self, i, value.clone())
+                        .map_err(|(v, e)| invalid(var, &v, e))?,
+                    None => warn!("Ignoring environment variable {}, there is no parameter {}", var, name),
+                }
+            } else if let Some(opt) = RUNTIME_OPTIONS
+                .iter()
+                .find(|o| format!("{}{}", OPTION_PREFIX, env_name(o.name)) == *var)
+            {
+                (opt.apply)(&mut state.options, value).map_err(|e| invalid(var, value, e))?;
+            } else if var != CONFIG_VAR {
+                warn!("Ignoring unknown environment variable {}", var);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the `LF_*` environment variables of this process.
+/// Other variables are skipped without looking at their value,
+/// which may not be valid unicode.
+pub(super) fn lf_env_vars() -> Vec<(String, String)> {
+    std::env::vars_os()
+        .filter_map(|(var, value)| {
+            let var = var.into_string().ok().filter(|var| var.starts_with(OPTION_PREFIX))?;
+            match value.into_string() {
+                Ok(value) => Some((var, value)),
+                Err(value) => {
+                    warn!(
+                        "Ignoring environment variable {}, its value {:?} is not valid unicode",
+                        var, value
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Name of an option in an environment variable, eg `DUMP_GRAPH`.
+fn env_name(name: &str) -> String {
+    name.replace('-', "_").to_uppercase()
+}
+
+/// Remove the `--config` option from the arguments, and
+/// return its value.
+fn take_config_arg(args: &mut Vec<String>) -> Result<Option<String>, CliError> {
+    let mut config = None;
+    let mut i = 0;
+    while i < args.len() {
+        if let Some(path) = args[i].strip_prefix("--config=") {
+            config = Some(path.to_owned());
+            args.remove(i);
+        } else if args[i] == "--config" {
+            if i + 1 >= args.len() {
+                return Err(missing_value("config", "<path>"));
+            }
+            config = Some(args.remove(i + 1));
+            args.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+    Ok(config)
+}
+
+/// A value of the configuration file, independent of its format.
+enum ConfigValue {
+    /// A value formatted like a command-line argument.
+    Scalar(String),
+    Table(Vec<(String, ConfigValue)>),
+    /// Only accepted by repeatable options.
+    Array(Vec<ConfigValue>),
+    /// Another kind of value, which is not supported. Contains
+    /// a description of the kind.
+    Unsupported(&'static str),
+}
+
+impl ConfigValue {
+    fn into_scalar(self) -> Result<String, String> {
+        match self {
+            ConfigValue::Scalar(value) => Ok(value),
+            ConfigValue::Table(_) => Err("unexpected table".into()),
+            ConfigValue::Array(_) => Err("unexpected array".into()),
+            ConfigValue::Unsupported(kind) => Err(format!("unexpected {}", kind)),
+        }
+    }
+}
+
+fn from_toml(value: toml::Value) -> ConfigValue {
+    use toml::Value;
+    match value {
+        Value::String(s) => ConfigValue::Scalar(s),
+        Value::Integer(i) => ConfigValue::Scalar(i.to_string()),
+        Value::Float(f) => ConfigValue::Scalar(f.to_string()),
+        Value::Boolean(b) => ConfigValue::Scalar(b.to_string()),
+        Value::Table(table) => ConfigValue::Table(table.into_iter().map(|(k, v)| (k, from_toml(v))).collect()),
+        Value::Datetime(_) => ConfigValue::Unsupported("datetime"),
+        Value::Array(values) => ConfigValue::Array(values.into_iter().map(from_toml).collect()),
+    }
+}
+
+fn from_json(value: serde_json::Value) -> ConfigValue {
+    use serde_json::Value;
+    match value {
+        Value::String(s) => ConfigValue::Scalar(s),
+        Value::Number(n) => ConfigValue::Scalar(n.to_string()),
+        Value::Bool(b) => ConfigValue::Scalar(b.to_string()),
+        Value::Object(map) => ConfigValue::Table(map.into_iter().map(|(k, v)| (k, from_json(v))).collect()),
+        Value::Null => ConfigValue::Unsupported("null"),
+        Value::Array(values) => ConfigValue::Array(values.into_iter().map(from_json).collect()),
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::cli::test::cli;
+
+    /// A config file in a fresh temp directory, which is
+    /// deleted when this is dropped.
+    struct ConfigFile {
+        dir: PathBuf,
+        path: String,
+    }
+
+    impl ConfigFile {
+        fn path(&self) -> &str {
+            &self.path
+        }
+    }
+
+    impl Drop for ConfigFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// Write a config file in a fresh temp directory.
+    fn config_file(name: &str, contents: &str) -> ConfigFile {
+        let dir = std::env::temp_dir().join(format!("reactor_rt_config_{}_{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::File::create(&path).unwrap().write_all(contents.as_bytes()).unwrap();
+        let path = path.to_string_lossy().into_owned();
+        ConfigFile { dir, path }
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_config_precedence() {
+        let toml = config_file(
+            "prec.toml",
+            r#"
+timeout = "2 s"
+workers = 2
+dump_graph = true
+
+[params]
+count = 12
+period = "1 ms"
+"#,
+        );
+        let args = cli()
+            .parse_with_config(
+                SchedulerOptions::default(),
+                ["--config", toml.path(), "--count", "14"],
+                env(&[
+                    ("LF_WORKERS", "3"),
+                    ("LF_PARAM_PERIOD", "5 ms"),
+                    ("LF_UNRELATED", "x"),
+                    ("HOME", "/"),
+                ]),
+            )
+            .unwrap();
+
+        assert_eq!(args.options.timeout, Some(Duration::from_secs(2)));
+        assert!(args.options.dump_graph);
+        // env overrides the file, the command line overrides both
+        assert_eq!(args.options.threads, 3);
+        assert_eq!(args.get::<Duration>("period"), Duration::from_millis(5));
+        assert_eq!(args.get::<u32>("count"), 14);
+    }
+
+    #[test]
+    fn test_json_config_from_env() {
+        let json = config_file("env.json", r#"{ "fast": true, "params": { "count": 7 } }"#);
+        let args = cli()
+            .parse_with_config(
+                SchedulerOptions::default(),
+                Vec::<String>::new(),
+                env(&[("LF_CONFIG", json.path())]),
+            )
+            .unwrap();
+        assert!(args.options.fast);
+        assert_eq!(args.get::<u32>("count"), 7);
+    }
+
+    #[test]
+    fn test_repeatable_options_from_config() {
+        let toml = config_file("repeat.toml", "break = [\"T0 + 1 s\", \"child/on_tick\"]\n");
+        let args = cli()
+            .parse_with_config(
+                SchedulerOptions::default(),
+                ["--config", toml.path(), "--break", "T0"],
+                Vec::new(),
+            )
+            .unwrap();
+        let breakpoints: Vec<String> = args.options.breakpoints.iter().map(|b| b.to_string()).collect();
+        assert_eq!(breakpoints, ["(T0 + 1 s, 0)", "child/on_tick", "(T0, 0)"]);
+
+        let json = config_file("repeat.json", r#"{ "break": "T0" }"#);
+        let args = cli()
+            .parse_with_config(SchedulerOptions::default(), ["--config", json.path()], Vec::new())
+            .unwrap();
+        assert_eq!(args.options.breakpoints.len(), 1);
+    }
+
+    #[test]
+    fn test_config_errors() {
+        let parse = |file: &str, vars: &[(&str, &str)]| match cli().parse_with_config(
+            SchedulerOptions::default(),
+            ["--config", file],
+            env(vars),
+        ) {
+            Err(CliError::Invalid(message)) => message.replace(file, "<file>"),
+            _ => panic!("expected an error"),
+        };
+
+        let toml = config_file("err1.toml", "timeout = 30\n");
+        assert_eq!(
+            parse(toml.path(), &[]),
+            "<file>: invalid value '30' for key 'timeout': time unit required"
+        );
+
+        let toml = config_file("err2.toml", "[params]\ncount = -1\n");
+        assert_eq!(
+            parse(toml.path(), &[]),
+            "<file>: invalid value '-1' for key 'params.count': invalid digit found in string"
+        );
+
+        let toml = config_file("err3.toml", "timeot = \"1 s\"\n");
+        assert_eq!(parse(toml.path(), &[]), "<file>: unknown key 'timeot'");
+
+        let json = config_file("err4.json", r#"{ "params": { "count": [1] } }"#);
+        assert_eq!(parse(json.path(), &[]), "<file>: unexpected array for key 'params.count'");
+
+        let toml = config_file("err5.toml", "timeout = [\"1 s\"]\n");
+        assert_eq!(parse(toml.path(), &[]), "<file>: unexpected array for key 'timeout'");
+
+        let toml = config_file("err6.toml", "break = [[\"T0\"]]\n");
+        assert_eq!(parse(toml.path(), &[]), "<file>: unexpected array for key 'break'");
+
+        let toml = config_file("ok.toml", "");
+        assert_eq!(
+            parse(toml.path(), &[("LF_FAST", "yes")]),
+            "invalid value 'yes' for environment variable LF_FAST: provided string was not `true` or `false`"
+        );
+    }
+}
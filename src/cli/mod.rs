@@ -0,0 +1,798 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+//! Command-line parsing for programs generated by LFC.
+//! This is only available with the `cli` feature.
+
+use std::fmt::{Display, Formatter};
+use std::net::ToSocketAddrs;
+use std::time::Duration;
+
+use crate::{format_duration, try_parse_duration, SchedulerOptions};
+
+#[cfg(feature = "config")]
+mod config;
+
+/// Parses the command line of a reactor program, that is,
+/// overrides for [SchedulerOptions] and values for the
+/// parameters of the main reactor. Parameters must be
+/// declared with [Cli::param], the `--help` message is
+/// derived from these declarations.
+///
+/// ### Examples
+///
+/// ```no_run
+/// use reactor_rt::*;
+///
+/// let cli = Cli::new("counter")
+///     .about("Counts to some number.")
+///     .param("count", 10u32, "Number to count to")
+///     .param("period", Duration::from_millis(100), "Time between two increments");
+///
+/// // defaults are usually taken from target properties
+/// let defaults = SchedulerOptions { timeout: Some(Duration::from_secs(3)), ..Default::default() };
+///
+/// // exits the process if arguments are invalid or `--help` is passed
+/// let args = cli.parse_env(defaults);
+/// let count: u32 = args.get("count");
+/// let period: Duration = args.get("period");
+/// // SyncScheduler::run_main::<CounterAdapter>(args.options, CounterParams { count, period });
+/// ```
+pub struct Cli {
+    program: String,
+    about: Option<String>,
+    params: Vec<ParamDecl>,
+}
+
+/// Declaration of a main reactor parameter.
+struct ParamDecl {
+    name: String,
+    help: String,
+    /// Placeholder for the value in the help message.
+    hint: &'static str,
+    /// Name of the rust type, to check calls to [CliArgs::get].
+    type_name: &'static str,
+    default: String,
+    /// Checks that a value can be parsed into the declared type.
+    check: fn(&str) -> Result<(), String>,
+}
+
+impl Cli {
+    /// Create a parser with no declared parameter.
+    /// The name of the program is used in the help message.
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            about: None,
+            params: Vec::new(),
+        }
+    }
+
+    /// Set a description of the program, printed in the help message.
+    pub fn about(mut self, about: impl Into<String>) -> Self {
+        self.about = Some(about.into());
+        self
+    }
+
+    /// Declare a parameter of the main reactor, which may
+    /// be overridden with `--<name> <value>`.
+    ///
+    /// Panics if an option with the same name already exists,
+    /// including the runtime options like `--timeout`.
+    pub fn param<T: CliValue>(mut self, name: &str, default: T, help: &str) -> Self {
+        assert!(
+            name != "config" && runtime_option(name).is_none() && self.param_index(name).is_none(),
+            "Option --{} is declared twice",
+            name
+        );
+        self.params.push(ParamDecl {
+            name: name.to_owned(),
+            help: help.to_owned(),
+            hint: T::HINT,
+            type_name: std::any::type_name::<T>(),
+            default: default.to_arg(),
+            check: |arg| T::parse_arg(arg).map(drop),
+        });
+        self
+    }
+
+    /// Parse the arguments of this process, see [Self::parse].
+    /// With the `config` feature, this also reads the configuration
+    /// file and environment variables, see `Cli::parse_with_config`.
+    /// If `--help` is passed, prints the help message and
+    /// exits the process. If the arguments are invalid,
+    /// prints an error and exits the process with status 2.
+    pub fn parse_env(&self, defaults: SchedulerOptions) -> CliArgs {
+        #[cfg(feature = "config")]
+        let result = self.parse_with_config(defaults, std::env::args().skip(1), config::lf_env_vars());
+        #[cfg(not(feature = "config"))]
+        let result = self.parse(defaults, std::env::args().skip(1));
+
+        match result {
+            Ok(args) => args,
+            Err(CliError::Help(help)) => {
+                print!("{}", help);
+                std::process::exit(0)
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2)
+            }
+        }
+    }
+
+    /// Parse the given arguments, which should not include
+    /// the name of the program. Options that are not
+    /// mentioned keep the value they have in `defaults`,
+    /// or the default value of their declaration.
+    ///
+    /// Values may be given as `--name value` or `--name=value`.
+    /// Boolean runtime options like `--fast` don't need
+    /// a value, but accept one with the second syntax.
+    pub fn parse<I, S>(&self, defaults: SchedulerOptions, args: I) -> Result<CliArgs, CliError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let args: Vec<String> = args.into_iter().map(|s| s.as_ref().to_owned()).collect();
+        if args.iter().any(|a| a == "-h" || a == "--help") {
+            return Err(CliError::Help(self.help(&defaults)));
+        }
+
+        let mut state = ParseState::new(self, defaults);
+        self.apply_args(&mut state, args)?;
+        Ok(state.finish(self))
+    }
+
+    /// Apply command-line arguments on top of the given state.
+    fn apply_args(&self, state: &mut ParseState, args: Vec<String>) -> Result<(), CliError> {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let name = arg
+                .strip_prefix("--")
+                .ok_or_else(|| CliError::Invalid(format!("unexpected argument '{}'", arg)))?;
+            let (name, inline_value) = match name.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_owned())),
+                None => (name, None),
+            };
+
+            if let Some(opt) = runtime_option(name) {
+                let value = match inline_value {
+                    None if opt.is_flag => "true".to_owned(),
+                    value => value.or_else(|| args.next()).ok_or_else(|| missing_value(name, opt.hint))?,
+                };
+                (opt.apply)(&mut state.options, &value).map_err(|e| invalid_value(name, &value, e))?;
+            } else if let Some(i) = self.param_index(name) {
+                let value = inline_value
+                    .or_else(|| args.next())
+                    .ok_or_else(|| missing_value(name, self.params[i].hint))?;
+                state
+                    .set_param(self, i, value)
+                    .map_err(|(value, e)| invalid_value(name, &value, e))?;
+            } else {
+                return Err(CliError::Invalid(format!("unknown option '--{}'", name)));
+            }
+        }
+        Ok(())
+    }
+
+    fn param_index(&self, name: &str) -> Option<usize> {
+        self.params.iter().position(|p| p.name == name)
+    }
+
+    /// Returns the help message, which lists the declared
+    /// parameters and the runtime options, with their default
+    /// values.
+    pub fn help(&self, defaults: &SchedulerOptions) -> String {
+        let mut param_lines = Vec::new();
+        for p in &self.params {
+            param_lines.push((format!("--{} {}", p.name, p.hint), p.help.clone(), Some(p.default.clone())));
+        }
+        let mut option_lines = Vec::new();
+        for o in RUNTIME_OPTIONS {
+            let hint = if o.is_flag {
+                format!("[={}]", o.hint)
+            } else {
+                format!(" {}", o.hint)
+            };
+            option_lines.push((format!("--{}{}", o.name, hint), o.help.to_owned(), (o.default)(defaults)));
+        }
+        #[cfg(feature = "config")]
+        option_lines.push((
+            "--config <path>".to_owned(),
+            "Read options from a TOML or JSON file, or else from the file named by $LF_CONFIG".to_owned(),
+            None,
+        ));
+        option_lines.push(("-h, --help".to_owned(), "Print this help message".to_owned(), None));
+
+        let width = param_lines
+            .iter()
+            .chain(&option_lines)
+            .map(|(left, ..)| left.len())
+            .max()
+            .unwrap_or(0)
+            + 2;
+        let format_section = |str: &mut String, title: &str, lines: &[(String, String, Option<String>)]| {
+            *str += &format!("\n{}:\n", title);
+            for (left, help, default) in lines {
+                *str += &format!("  {:width$}{}", left, help, width = width);
+                if let Some(default) = default {
+                    *str += &format!(" [default: {}]", default);
+                }
+                *str += "\n";
+            }
+        };
+
+        let mut str = format!("Usage: {} [OPTIONS]\n", self.program);
+        if let Some(about) = &self.about {
+            str += &format!("\n{}\n", about);
+        }
+        if !param_lines.is_empty() {
+            format_section(&mut str, "Main reactor parameters", &param_lines);
+        }
+        format_section(&mut str, "Runtime options", &option_lines);
+        str
+    }
+}
+
+/// Values of the options and parameters, which are
+/// overwritten by each source of configuration in turn.
+struct ParseState {
+    options: SchedulerOptions,
+    /// Values of the parameters, in declaration order.
+    values: Vec<String>,
+}
+
+impl ParseState {
+    fn new(cli: &Cli, defaults: SchedulerOptions) -> Self {
+        Self {
+            options: defaults,
+            values: cli.params.iter().map(|p| p.default.clone()).collect(),
+        }
+    }
+
+    /// Set the value of the parameter at the given index. If
+    /// the value is invalid, returns it with the parse error.
+    fn set_param(&mut self, cli: &Cli, index: usize, value: String) -> Result<(), (String, String)> {
+        match (cli.params[index].check)(&value) {
+            Ok(()) => {
+                self.values[index] = value;
+                Ok(())
+            }
+            Err(e) => Err((value, e)),
+        }
+    }
+
+    fn finish(self, cli: &Cli) -> CliArgs {
+        let params = cli
+            .params
+            .iter()
+            .zip(self.values)
+            .map(|(p, v)| (p.name.clone(), p.type_name, v))
+            .collect();
+        CliArgs { options: self.options, params }
+    }
+}
+
+fn runtime_option(name: &str) -> Option<&'static RuntimeOption> {
+    RUNTIME_OPTIONS.iter().find(|o| o.name == name)
+}
+
+fn missing_value(name: &str, hint: &str) -> CliError {
+    CliError::Invalid(format!("option '--{}' requires a value {}", name, hint))
+}
+
+fn invalid_value(name: &str, value: &str, error: String) -> CliError {
+    CliError::Invalid(format!("invalid value '{}' for '--{}': {}", value, name, error))
+}
+
+/// The result of parsing the command line, see [Cli::parse].
+pub struct CliArgs {
+    /// Options of the scheduler.
+    pub options: SchedulerOptions,
+    /// Name, rust type and value of each parameter.
+    params: Vec<(String, &'static str, String)>,
+}
+
+impl CliArgs {
+    /// Returns the value of a main reactor parameter.
+    ///
+    /// Panics if the parameter was not declared with
+    /// [Cli::param], or with a different type.
+    pub fn get<T: CliValue>(&self, name: &str) -> T {
+        let (_, type_name, value) = self
+            .params
+            .iter()
+            .find(|(n, ..)| n == name)
+            .unwrap_or_else(|| panic!("Parameter {} was not declared", name));
+        assert_eq!(
+            *type_name,
+            std::any::type_name::<T>(),
+            "Parameter {} was declared with another type",
+            name
+        );
+        // the value has been checked already
+        T::parse_arg(value).unwrap()
+    }
+}
+
+/// An error while parsing the command line.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CliError {
+    /// The help message was requested with `--help`.
+    /// This contains the help message.
+    Help(String),
+    /// Some argument is invalid.
+    Invalid(String),
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Help(help) => write!(f, "{}", help),
+            CliError::Invalid(message) => write!(f, "error: {}\n\nFor more information, try '--help'.", message),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// A type that main reactor parameters may have.
+pub trait CliValue: Sized + 'static {
+    /// Placeholder for the value in the help message, eg `<int>`.
+    const HINT: &'static str;
+
+    /// Parse a value from a command-line argument.
+    fn parse_arg(arg: &str) -> Result<Self, String>;
+
+    /// Format a value so that [Self::parse_arg] can read it back.
+    fn to_arg(&self) -> String;
+}
+
+macro_rules! impl_cli_value_from_str {
+    ($hint:literal: $($t:ty),*) => {$(
+        impl CliValue for $t {
+            const HINT: &'static str = $hint;
+
+            fn parse_arg(arg: &str) -> Result<Self, String> {
+                arg.parse::<$t>().map_err(|e| e.to_string())
+            }
+
+            fn to_arg(&self) -> String {
+                self.to_string()
+            }
+        }
+    )*};
+}
+
+impl_cli_value_from_str!("<int>": i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_cli_value_from_str!("<float>": f32, f64);
+impl_cli_value_from_str!("<bool>": bool);
+impl_cli_value_from_str!("<string>": String);
+
+impl CliValue for Duration {
+    const HINT: &'static str = "<duration>";
+
+    fn parse_arg(arg: &str) -> Result<Self, String> {
+        try_parse_duration(arg)
+    }
+
+    fn to_arg(&self) -> String {
+        format_duration(*self)
+    }
+}
+
+/// A command-line option that sets a field of [SchedulerOptions].
+struct RuntimeOption {
+    name: &'static str,
+    /// Placeholder for the value in the help message.
+    hint: &'static str,
+    help: &'static str,
+    /// Flags don't need a value, they're set to true if no value is given.
+    is_flag: bool,
+    /// Repeatable options add their value to a list every time
+    /// they're given, they take an array in a configuration file.
+    is_repeatable: bool,
+    default: fn(&SchedulerOptions) -> Option<String>,
+    apply: fn(&mut SchedulerOptions, &str) -> Result<(), String>,
+}
+
+const RUNTIME_OPTIONS: &[RuntimeOption] = &[
+    RuntimeOption {
+        name: "timeout",
+        hint: "<duration>",
+        help: "Stop execution at the latest at this logical time",
+        is_flag: false,
+        is_repeatable: false,
+        default: |o| o.timeout.map(format_duration),
+        apply: |o, v| {
+            o.timeout = Some(try_parse_duration(v)?);
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "workers",
+        hint: "<int>",
+        help: "Max number of worker threads, zero means one per core",
+        is_flag: false,
+        is_repeatable: false,
+        default: |o| Some(o.threads.to_string()),
+        apply: |o, v| {
+            o.threads = usize::parse_arg(v)?;
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "fast",
+        hint: "<bool>",
+        help: "Execute as fast as possible, without waiting for physical time",
+        is_flag: true,
+        is_repeatable: false,
+        default: |o| Some(o.fast.to_string()),
+        apply: |o, v| {
+            o.fast = bool::parse_arg(v)?;
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "keepalive",
+        hint: "<bool>",
+        help: "Keep the program alive when the event queue is empty",
+        is_flag: true,
+        is_repeatable: false,
+        default: |o| Some(o.keep_alive.to_string()),
+        apply: |o, v| {
+            o.keep_alive = bool::parse_arg(v)?;
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "async-capacity",
+        hint: "<int|unbounded>",
+        help: "Capacity of the channel through which physical actions send events",
+        is_flag: false,
+        is_repeatable: false,
+        default: |o| Some(o.async_capacity.map_or_else(|| "unbounded".to_owned(), |c| c.to_string())),
+        apply: |o, v| {
+            o.async_capacity = if v == "unbounded" { None } else { Some(usize::parse_arg(v)?) };
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "async-overflow",
+        hint: "<block|fail|coalesce>",
+        help: "What to do when physical actions are scheduled while the channel is full",
+        is_flag: false,
+        is_repeatable: false,
+        default: |o| Some(format!("{:?}", o.async_overflow).to_lowercase()),
+        apply: |o, v| {
+            o.async_overflow = v.parse()?;
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "blocking-threads",
+        hint: "<int>",
+        help: "Max number of threads running blocking jobs, zero means one per core",
+        is_flag: false,
+        is_repeatable: false,
+        default: |o| Some(o.blocking_threads.to_string()),
+        apply: |o, v| {
+            o.blocking_threads = usize::parse_arg(v)?;
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "blocking-shutdown",
+        hint: "<await|cancel>",
+        help: "What to do with pending blocking jobs at shutdown",
+        is_flag: false,
+        is_repeatable: false,
+        default: |o| Some(format!("{:?}", o.blocking_shutdown).to_lowercase()),
+        apply: |o, v| {
+            o.blocking_shutdown = v.parse()?;
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "reaction-log-format",
+        hint: "<log|text|json>",
+        help: "Format of the messages logged by reactions",
+        is_flag: false,
+        is_repeatable: false,
+        default: |o| Some(format!("{:?}", o.reaction_log_format).to_lowercase()),
+        apply: |o, v| {
+            o.reaction_log_format = v.parse()?;
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "metrics-addr",
+        hint: "<host:port>",
+        help: "Serve metrics in the Prometheus format on this address",
+        is_flag: false,
+        is_repeatable: false,
+        default: |o| o.metrics_addr.map(|a| a.to_string()),
+        apply: |o, v| {
+            let mut addrs = v.to_socket_addrs().map_err(|e| e.to_string())?;
+            o.metrics_addr = Some(addrs.next().ok_or_else(|| format!("cannot resolve '{}'", v))?);
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "debug",
+        hint: "<stdin|host:port>",
+        help: "Run under an interactive debugger, which reads commands from stdin or a socket",
+        is_flag: false,
+        is_repeatable: false,
+        default: |o| o.debugger.map(|d| d.to_string()),
+        apply: |o, v| {
+            o.debugger = Some(v.parse()?);
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "break",
+        hint: "<tag|reaction>",
+        help: "Add a breakpoint for the debugger, eg 'T0 + 1 s' or 'child/on_tick'",
+        is_flag: false,
+        is_repeatable: true,
+        default: |_| None,
+        apply: |o, v| {
+            o.breakpoints.push(v.parse()?);
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "max-microsteps",
+        hint: "<int|unbounded>",
+        help: "Max microstep of a tag, above which the program is considered to exhibit Zeno behavior",
+        is_flag: false,
+        is_repeatable: false,
+        default: |o| Some(o.max_microsteps.map_or_else(|| "unbounded".to_owned(), |m| m.to_string())),
+        apply: |o, v| {
+            o.max_microsteps = if v == "unbounded" { None } else { Some(u32::parse_arg(v)?) };
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "on-zeno",
+        hint: "<stop|panic>",
+        help: "What to do when the max microstep is exceeded",
+        is_flag: false,
+        is_repeatable: false,
+        default: |o| Some(format!("{:?}", o.on_zeno).to_lowercase()),
+        apply: |o, v| {
+            o.on_zeno = v.parse()?;
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "dump-graph",
+        hint: "<bool>",
+        help: "Dump the dependency graph to a file before execution",
+        is_flag: true,
+        is_repeatable: false,
+        default: |o| Some(o.dump_graph.to_string()),
+        apply: |o, v| {
+            o.dump_graph = bool::parse_arg(v)?;
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "dump-graph-format",
+        hint: "<dot|json|mermaid>",
+        help: "Format of the dependency graph dump",
+        is_flag: false,
+        is_repeatable: false,
+        default: |o| Some(format!("{:?}", o.dump_graph_format).to_lowercase()),
+        apply: |o, v| {
+            o.dump_graph_format = v.parse()?;
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "dump-graph-path",
+        hint: "<path>",
+        help: "File the dependency graph is dumped to",
+        is_flag: false,
+        is_repeatable: false,
+        default: |o| o.dump_graph_path.as_ref().map(|p| p.to_string_lossy().into_owned()),
+        apply: |o, v| {
+            o.dump_graph_path = Some(v.into());
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "log-level",
+        hint: "<off|error|warn|info|debug|trace>",
+        help: "Maximum level of log messages",
+        is_flag: false,
+        is_repeatable: false,
+        default: |o| o.log_level.map(|l| l.to_string().to_lowercase()),
+        apply: |o, v| {
+            o.log_level = Some(v.parse().map_err(|_| format!("unknown log level '{}'", v))?);
+            Ok(())
+        },
+    },
+    RuntimeOption {
+        name: "trace",
+        hint: "<bool>",
+        help: "Log all messages, same as --log-level trace",
+        is_flag: true,
+        is_repeatable: false,
+        default: |_| None,
+        apply: |o, v| {
+            if bool::parse_arg(v)? {
+                o.log_level = Some(log::LevelFilter::Trace);
+            }
+            Ok(())
+        },
+    },
+];
+
+#[cfg(test)]
+pub mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        BlockingShutdown, Breakpoint, DebuggerInput, EventTag, GraphFormat, OverflowPolicy, ReactionLogFormat, ZenoPolicy,
+    };
+
+    pub(super) fn cli() -> Cli {
+        Cli::new("prog")
+            .about("A test program.")
+            .param("count", 3u32, "Number of iterations")
+            .param("period", Duration::from_millis(100), "Period of the timer")
+    }
+
+    #[test]
+    fn test_parse_options_and_params() {
+        let defaults = SchedulerOptions {
+            timeout: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        let args = cli()
+            .parse(
+                defaults,
+                [
+                    "--fast",
+                    "--workers",
+                    "4",
+                    "--count=12",
+                    "--dump-graph-format",
+                    "json",
+                    "--trace",
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(args.get::<u32>("count"), 12);
+        assert_eq!(args.get::<Duration>("period"), Duration::from_millis(100));
+        assert!(args.options.fast);
+        assert!(!args.options.keep_alive);
+        assert_eq!(args.options.threads, 4);
+        assert_eq!(args.options.timeout, Some(Duration::from_secs(1)));
+        assert_eq!(args.options.dump_graph_format, GraphFormat::Json);
+        assert_eq!(args.options.log_level, Some(log::LevelFilter::Trace));
+        assert_eq!(args.options.async_capacity, None);
+
+        let args = cli()
+            .parse(Default::default(), ["--async-capacity", "64", "--async-overflow", "coalesce"])
+            .unwrap();
+        assert_eq!(args.options.async_capacity, Some(64));
+        assert_eq!(args.options.async_overflow, OverflowPolicy::Coalesce);
+
+        let args = cli()
+            .parse(Default::default(), ["--blocking-threads", "8", "--blocking-shutdown=cancel"])
+            .unwrap();
+        assert_eq!(args.options.blocking_threads, 8);
+        assert_eq!(args.options.blocking_shutdown, BlockingShutdown::Cancel);
+
+        let args = cli()
+            .parse(Default::default(), ["--max-microsteps", "1000", "--on-zeno=panic"])
+            .unwrap();
+        assert_eq!(args.options.max_microsteps, Some(1000));
+        assert_eq!(args.options.on_zeno, ZenoPolicy::Panic);
+
+        let args = cli().parse(Default::default(), ["--reaction-log-format=json"]).unwrap();
+        assert_eq!(args.options.reaction_log_format, ReactionLogFormat::Json);
+
+        let args = cli().parse(Default::default(), ["--metrics-addr=127.0.0.1:9464"]).unwrap();
+        assert_eq!(args.options.metrics_addr, Some("127.0.0.1:9464".parse().unwrap()));
+
+        let args = cli()
+            .parse(
+                Default::default(),
+                ["--debug=stdin", "--break", "T0 + 1 s", "--break", "child/on_tick"],
+            )
+            .unwrap();
+        assert_eq!(args.options.debugger, Some(DebuggerInput::Stdin));
+        assert_eq!(
+            args.options.breakpoints,
+            [
+                Breakpoint::Tag(EventTag::offset(Duration::from_secs(1), 0)),
+                Breakpoint::Reaction("child/on_tick".into())
+            ]
+        );
+
+        let args = cli()
+            .parse(Default::default(), ["--timeout", "20 ms", "--period=1s", "--fast=false"])
+            .unwrap();
+        assert_eq!(args.options.timeout, Some(Duration::from_millis(20)));
+        assert_eq!(args.get::<Duration>("period"), Duration::from_secs(1));
+        assert!(!args.options.fast);
+
+        let args = cli().parse(Default::default(), ["--timeout", "forever"]).unwrap();
+        assert_eq!(args.options.timeout, Some(Duration::MAX));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let parse = |args: &[&str]| match cli().parse(Default::default(), args) {
+            Err(CliError::Invalid(message)) => message,
+            _ => panic!("expected an error for {:?}", args),
+        };
+
+        assert_eq!(
+            parse(&["--count", "x"]),
+            "invalid value 'x' for '--count': invalid digit found in string"
+        );
+        assert_eq!(
+            parse(&["--timeout", "30"]),
+            "invalid value '30' for '--timeout': time unit required"
+        );
+        assert_eq!(parse(&["--period"]), "option '--period' requires a value <duration>");
+        assert_eq!(parse(&["--frobnicate"]), "unknown option '--frobnicate'");
+        assert_eq!(parse(&["count"]), "unexpected argument 'count'");
+    }
+
+    #[test]
+    fn test_help() {
+        let defaults = SchedulerOptions {
+            timeout: Some(Duration::from_secs(2)),
+            ..Default::default()
+        };
+        let help = match cli().parse(defaults, ["--count", "x", "--help"]) {
+            Err(CliError::Help(help)) => help,
+            _ => panic!("expected help"),
+        };
+        assert!(help.starts_with("Usage: prog [OPTIONS]\n\nA test program.\n\nMain reactor parameters:\n"));
+        assert!(help.contains("\n  --count <int>  "));
+        assert!(help.contains("Number of iterations [default: 3]\n"));
+        assert!(help.contains("Period of the timer [default: 100 ms]\n"));
+        assert!(help.contains("\nRuntime options:\n"));
+        assert!(help.contains("\n  --timeout <duration>  "));
+        assert!(help.contains("logical time [default: 2 s]\n"));
+        assert!(help.contains("\n  --fast[=<bool>]  "));
+        assert!(help.contains("\n  -h, --help  "));
+    }
+
+    #[test]
+    #[should_panic(expected = "Option --timeout is declared twice")]
+    fn test_param_clash() {
+        cli().param("timeout", 0u32, "");
+    }
+}
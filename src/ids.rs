@@ -35,6 +35,8 @@ macro_rules! simple_idx_type {
 
 $(#[$($attrs)*])*
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 #[repr(transparent)]
 pub struct $id($impl_t);
 
@@ -98,6 +100,8 @@ macro_rules! global_id_newtype {
     {$(#[$m:meta])* $id:ident} => {
         $(#[$m])*
         #[derive(Eq, Ord, PartialOrd, PartialEq, Hash, Copy, Clone)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(feature = "serde", serde(transparent))]
         pub struct $id(pub(crate) GlobalId);
 
         impl $id {
@@ -166,6 +170,23 @@ impl FromStr for GlobalId {
     }
 }
 
+// Global ids are serialized in the format of their Display
+// impl, eg "3/1", which FromStr parses back.
+#[cfg(feature = "serde")]
+impl serde::Serialize for GlobalId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for GlobalId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 // Hashing global ids is a very hot operation in the framework,
 // therefore we give it an optimal implementation.
 // The implementation was verified to be faster than the default
@@ -50,6 +50,22 @@
 //! This is a default feature.
 //! - `no-unsafe`: disable optimisations that use unsafe code in this runtime.
 //! Just provided for comparison, should probably be removed (unsafe code is fine).
+//! - `cli`: provide `Cli`, which parses the command-line of a
+//!   generated program into [SchedulerOptions] and main reactor
+//!   parameters, and prints its help.
+//! - `config`: implies `cli`. Options are also read from a TOML
+//!   or JSON configuration file, passed with `--config` or named
+//!   by `$LF_CONFIG`, and from `LF_*` environment variables, see
+//!   `Cli::parse_with_config`. The command-line takes precedence
+//!   over the environment, which takes precedence over the file.
+//! - `serde`: implement `Serialize` and `Deserialize` for tags,
+//!   ids, time values and the other values exchanged with the
+//!   runtime, so that they can be persisted or transmitted. Global
+//!   reaction ids are serialized as strings like `"3/1"`.
+//! - `tracing`: open a [tracing](https://docs.rs/tracing) span
+//!   named `tag` for each processed tag, and a child span named
+//!   `reaction` for each executed reaction, with fields for the
+//!   tag, level, reactor path and reaction id and label.
 
 // #![deny(unused_crate_dependencies)]
 #![deny(unused_extern_crates)]
@@ -79,9 +95,12 @@ extern crate cfg_if;
 
 pub use std::time::{Duration, Instant};
 
+pub use log::Level as LogLevel;
 pub(crate) use scheduler::debug::*;
 
 pub use self::actions::*;
+#[cfg(feature = "cli")]
+pub use self::cli::*;
 pub use self::ids::*;
 pub use self::ports::*;
 pub use self::scheduler::*;
@@ -94,6 +113,8 @@ pub use self::util::*;
 pub mod test;
 
 mod actions;
+#[cfg(feature = "cli")]
+mod cli;
 pub(self) mod ids;
 mod ports;
 mod scheduler;
@@ -109,8 +130,9 @@ pub mod assembly;
 pub mod prelude {
     pub use crate::Offset::*;
     pub use crate::{
-        after, assert_tag_is, delay, tag, AsyncCtx, Duration, EventTag, Instant, LogicalAction, PhysicalActionRef, ReactionCtx,
-        ReadablePort, ReadablePortBank, Timer, WritablePort, WritablePortBank,
+        after, assert_tag_is, ctx_debug, ctx_error, ctx_info, ctx_trace, ctx_warn, delay, tag, AsyncCtx, Duration, EventTag,
+        Instant, LogicalAction, PhysicalActionRef, ReactionCtx, ReadablePort, ReadablePortBank, Timer, WritablePort,
+        WritablePortBank,
     };
 
     /// Alias for the unit type, so that it can be written without quotes in LF.
@@ -144,6 +166,22 @@ pub trait ReactorBehavior {
     /// Acknowledge that the given tag is done executing and
     /// free resources if need be.
     fn cleanup_tag(&mut self, ctx: &CleanupCtx);
+
+    /// Report the state of the ports, actions and timers of this
+    /// reactor to the [debugger](SchedulerOptions::debugger), by
+    /// passing each of them to the inspector, for instance
+    /// `inspector.value(&self.out).presence(&self.action)`.
+    ///
+    /// This is a no-op by default. The runtime then inspects
+    /// ports, physical actions and timers on its own: the
+    /// debugger tells whether they are present, and prints
+    /// the values of those that were created with a `new_debug_*`
+    /// method of [ComponentCreator](assembly::ComponentCreator).
+    /// Logical actions are stored in the reactor, so they can
+    /// only be inspected if this is implemented. Otherwise,
+    /// the debugger answers that the reactor does not support
+    /// inspection.
+    fn inspect(&self, _inspector: &mut TriggerInspector) {}
 }
 assert_obj_safe!(ReactorBehavior);
 
@@ -25,18 +25,22 @@
 use std::borrow::Borrow;
 #[cfg(not(feature = "no-unsafe"))]
 use std::cell::UnsafeCell;
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::fmt::Debug;
 #[cfg(feature = "no-unsafe")]
 use std::ops::Deref;
 use std::ops::{DerefMut, Index, IndexMut};
 use std::rc::Rc;
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use atomic_refcell::AtomicRefCell;
-use AssemblyErrorImpl::{CannotBind, CyclicDependency};
+use AssemblyDiagnostic::{CannotBind, CyclicDependency};
 
-use crate::assembly::{AssemblyError, AssemblyErrorImpl, PortId, PortKind, TriggerId, TriggerLike};
+use crate::assembly::{AssemblyDiagnostic, AssemblyError, ComponentRef, PortId, PortKind, TriggerId, TriggerLike};
+use crate::vecmap::VecMap;
 use crate::{EventTag, ReactionTrigger};
 
 /// A read-only reference to a port.
@@ -52,16 +56,16 @@ impl<'a, T: Sync> ReadablePort<'a, T> {
 
 impl<T: Sync> ReactionTrigger<T> for ReadablePort<'_, T> {
     #[inline]
-    fn get_value(&self, _now: &EventTag, _start: &Instant) -> Option<T>
+    fn get_value(&self, now: &EventTag, _start: &Instant) -> Option<T>
     where
         T: Copy,
     {
-        self.0.get()
+        self.0.get_at(now)
     }
 
     #[inline]
-    fn use_value_ref<O>(&self, _now: &EventTag, _start: &Instant, action: impl FnOnce(Option<&T>) -> O) -> O {
-        self.0.use_ref(|opt| action(opt.as_ref()))
+    fn use_value_ref<O>(&self, now: &EventTag, _start: &Instant, action: impl FnOnce(Option<&T>) -> O) -> O {
+        self.0.use_ref_at(now, action)
     }
 }
 
@@ -82,6 +86,17 @@ impl<'a, T: Sync> WritablePort<'a, T> {
         self.0.set_impl(Some(v))
     }
 
+    /// Send the current value on the delayed connections
+    /// going out of this port, see [Port::send_delayed].
+    #[inline]
+    pub(crate) fn send_delayed(
+        &self,
+        now: EventTag,
+        schedule: impl FnMut(PortId, ConnectionKind, Duration, Option<EventTag>) -> EventTag,
+    ) {
+        self.0.send_delayed(now, schedule)
+    }
+
     pub(crate) fn get_id(&self) -> TriggerId {
         self.0.get_id()
     }
@@ -227,6 +242,13 @@ impl<'a, T: Sync> IntoIterator for WritablePortBank<'a, T> {
 /// (logically instantaneously). A port may have only one
 /// upstream binding.
 ///
+/// A binding may also have a logical delay (`a -> b after 10 ms`
+/// in LF). In that case the values of the upstream port are
+/// kept in flight, and become present on the downstream
+/// port only at the tag that is the given delay later.
+/// Physical connections (`a ~> b`) work the same way, except
+/// the tag of the downstream value is taken from physical time.
+///
 /// Output ports may also be explicitly [set](super::ReactionCtx::set)
 /// within a reaction, in which case they may not have an
 /// upstream port binding.
@@ -278,6 +300,9 @@ impl<T: Sync> Port<T> {
         }
     }
 
+    /// Returns the value that was set on this port, ignoring
+    /// values sent through a delayed connection.
+    #[cfg(test)]
     #[inline]
     pub(crate) fn get(&self) -> Option<T>
     where
@@ -286,8 +311,33 @@ impl<T: Sync> Port<T> {
         self.use_ref(Option::<T>::clone)
     }
 
+    /// Returns the value of the port at the given tag. Unlike
+    /// [Self::get], this takes into account values that were
+    /// sent through a delayed connection.
+    #[inline]
+    pub(crate) fn get_at(&self, now: &EventTag) -> Option<T>
+    where
+        T: Copy,
+    {
+        self.use_ref_at(now, |opt| opt.cloned())
+    }
+
+    /// Executes the closure on the value of the port at the
+    /// given tag, see [Self::get_at].
+    #[inline]
+    pub(crate) fn use_ref_at<R>(&self, now: &EventTag, f: impl FnOnce(Option<&T>) -> R) -> R {
+        self.use_class_cell(|cell| cell.use_value_at(now, f))
+    }
+
     cfg_if! {
         if #[cfg(feature = "no-unsafe")] {
+            fn use_class_cell<R>(&self, f: impl FnOnce(&Rc<PortCell<T>>) -> R) -> R {
+                use atomic_refcell::AtomicRef;
+                let cell_ref: AtomicRef<Rc<PortCell<T>>> = AtomicRefCell::borrow(&self.upstream_binding);
+                f(cell_ref.deref())
+            }
+
+            #[cfg(test)]
             pub(crate) fn use_ref<R>(&self, f: impl FnOnce(&Option<T>) -> R) -> R {
                 use atomic_refcell::AtomicRef;
                 let cell_ref: AtomicRef<Rc<PortCell<T>>> = AtomicRefCell::borrow(&self.upstream_binding);
@@ -304,7 +354,7 @@ impl<T: Sync> Port<T> {
             pub(crate) fn set_impl(&mut self, new_value: Option<T>) {
                 use atomic_refcell::AtomicRef;
 
-                debug_assert_ne!(self.bind_status, BindStatus::Bound, "Cannot set a bound port ({:?})", self.id);
+                debug_assert!(!self.bind_status.is_bound(), "Cannot set a bound port ({:?})", self.id);
 
                 let cell_ref: AtomicRef<Rc<PortCell<T>>> = AtomicRefCell::borrow(&self.upstream_binding);
                 let class_cell: &PortCell<T> = Rc::borrow(cell_ref.deref());
@@ -313,6 +363,14 @@ impl<T: Sync> Port<T> {
             }
 
         } else {
+             #[inline]
+             fn use_class_cell<R>(&self, f: impl FnOnce(&Rc<PortCell<T>>) -> R) -> R {
+                let binding: &UnsafeCell<Rc<PortCell<T>>> = Rc::borrow(&self.upstream_binding);
+                let cell: &Rc<PortCell<T>> = unsafe { &*binding.get() };
+                f(cell)
+            }
+
+             #[cfg(test)]
              #[inline]
              pub(crate) fn use_ref<R>(&self, f: impl FnOnce(&Option<T>) -> R) -> R {
                 let binding: &UnsafeCell<Rc<PortCell<T>>> = Rc::borrow(&self.upstream_binding);
@@ -325,7 +383,7 @@ impl<T: Sync> Port<T> {
 
              #[inline]
              pub(crate) fn set_impl(&mut self, new_value: Option<T>) {
-                debug_assert_ne!(self.bind_status, BindStatus::Bound, "Cannot set a bound port");
+                debug_assert!(!self.bind_status.is_bound(), "Cannot set a bound port");
 
                 let binding: &UnsafeCell<Rc<PortCell<T>>> = Rc::borrow(&self.upstream_binding);
 
@@ -338,17 +396,104 @@ impl<T: Sync> Port<T> {
         }
     }
 
+    /// Returns a probe on the value of this port, for the debugger.
+    pub(crate) fn probe(&self) -> PortProbe<T> {
+        PortProbe {
+            id: self.id,
+            binding: Rc::as_ptr(&self.upstream_binding),
+        }
+    }
+
     /// Called at the end of a tag.
     #[inline]
-    pub(crate) fn clear_value(&mut self) {
-        // If this port is bound, then some other port has
-        // a reference to the same cell but is not bound.
-        if self.bind_status != BindStatus::Bound {
-            self.set_impl(None)
+    pub(crate) fn clear_value(&mut self, tag: &EventTag) {
+        match self.bind_status {
+            BindStatus::Free => self.set_impl(None),
+            // If this port is bound, then some other port has
+            // a reference to the same cell but is not bound.
+            BindStatus::Bound => {}
+            // This port is the head of its class, but it's
+            // only ever written through the delayed connection.
+            BindStatus::DelayedBound => self.use_class_cell(|cell| cell.forget_in_flight(tag)),
         }
     }
 
+    /// Sends the current value of this port on all delayed
+    /// connections going out of its equivalence class. The value is
+    /// cloned for every connection and kept in flight until its
+    /// delivery tag. The delivery tag is chosen by the callback,
+    /// which is called for each connection with the id of the
+    /// downstream port, the kind of connection, its delay, and
+    /// the latest tag at which a value is in flight on it.
+    pub(crate) fn send_delayed(
+        &self,
+        now: EventTag,
+        mut schedule: impl FnMut(PortId, ConnectionKind, Duration, Option<EventTag>) -> EventTag,
+    ) {
+        self.use_class_cell(|cell| {
+            let delayed = cell.delayed.borrow();
+            if delayed.is_empty() {
+                return;
+            }
+            cell.use_value_at(&now, |value| {
+                if let Some(value) = value {
+                    for binding in delayed.iter() {
+                        let latest = binding.target.latest_in_flight();
+                        let eta = schedule(binding.downstream, binding.kind, binding.delay, latest);
+                        binding.target.put_in_flight(eta, (binding.clone)(value));
+                    }
+                }
+            })
+        })
+    }
+
+    /// Bind this port to the downstream port with a delay.
+    /// Values set on this port are made present on the
+    /// downstream port at a later tag, which is determined
+    /// by the [ConnectionKind] and the delay.
+    /// Unlike with [Self::forward_to], both ports keep
+    /// distinct equivalence classes.
+    pub(crate) fn forward_delayed_to(
+        &mut self,
+        downstream: &mut Port<T>,
+        kind: ConnectionKind,
+        delay: Duration,
+    ) -> Result<(), AssemblyError>
+    where
+        T: Clone,
+    {
+        if downstream.bind_status.is_bound() {
+            return Err(CannotBind {
+                upstream: ComponentRef::new(self.id),
+                downstream: ComponentRef::new(downstream.id),
+            }
+            .into());
+        }
+        downstream.bind_status = BindStatus::DelayedBound;
+
+        let target = downstream.use_class_cell(Rc::clone);
+        target.receives_delayed.store(true, Ordering::Relaxed);
+        self.use_class_cell(|my_class| {
+            my_class.delayed.borrow_mut().push(DelayedBinding {
+                downstream: downstream.id,
+                kind,
+                delay,
+                target,
+                clone: T::clone,
+            })
+        });
+        Ok(())
+    }
+
     pub(crate) fn forward_to(&mut self, downstream: &mut Port<T>) -> Result<(), AssemblyError> {
+        if downstream.bind_status.is_bound() {
+            return Err(CannotBind {
+                upstream: ComponentRef::new(self.id),
+                downstream: ComponentRef::new(downstream.id),
+            }
+            .into());
+        }
+
         let mut mut_downstream_cell = {
             cfg_if! {
                 if #[cfg(feature = "no-unsafe")] {
@@ -359,12 +504,6 @@ impl<T: Sync> Port<T> {
             }
         };
 
-        if downstream.bind_status == BindStatus::Bound {
-            return Err(AssemblyError(CannotBind(self.id, downstream.id)));
-        }
-
-        downstream.bind_status = BindStatus::Bound;
-
         let my_class = {
             cfg_if! {
                 if #[cfg(feature = "no-unsafe")] {
@@ -375,17 +514,37 @@ impl<T: Sync> Port<T> {
             }
         };
 
+        // check before modifying anything, so that a failed
+        // binding leaves both classes untouched
+        my_class.check_cycle(&mut_downstream_cell, &self.id, &downstream.id)?;
+
+        downstream.bind_status = BindStatus::Bound;
+
         my_class
             .downstreams
             .borrow_mut()
             .insert(downstream.id, Rc::clone(&downstream.upstream_binding));
+        my_class.upstreams.borrow_mut().insert(downstream.id, self.id);
 
-        let new_binding = Rc::clone(&my_class);
-
-        mut_downstream_cell.check_cycle(&self.id, &downstream.id)?;
-
+        // The downstream class is merged into ours: all its ports
+        // now point to our class, and we record its bindings, so that
+        // cycles and later rebindings see the whole class.
         mut_downstream_cell.set_upstream(&my_class);
-        *mut_downstream_cell.deref_mut() = new_binding;
+        my_class
+            .downstreams
+            .borrow_mut()
+            .extend(mut_downstream_cell.downstreams.borrow_mut().drain());
+        my_class
+            .upstreams
+            .borrow_mut()
+            .extend(mut_downstream_cell.upstreams.borrow_mut().drain());
+        // delayed connections going out of the downstream class now go out of ours
+        my_class
+            .delayed
+            .borrow_mut()
+            .append(&mut mut_downstream_cell.delayed.borrow_mut());
+
+        *mut_downstream_cell.deref_mut() = Rc::clone(&my_class);
         Ok(())
     }
 }
@@ -396,6 +555,72 @@ impl<T: Sync> TriggerLike for Port<T> {
     }
 }
 
+impl<T: Sync> ReactionTrigger<T> for Port<T> {
+    #[inline]
+    fn get_value(&self, now: &EventTag, _start: &Instant) -> Option<T>
+    where
+        T: Copy,
+    {
+        self.get_at(now)
+    }
+
+    #[inline]
+    fn use_value_ref<O>(&self, now: &EventTag, _start: &Instant, action: impl FnOnce(Option<&T>) -> O) -> O {
+        self.use_ref_at(now, action)
+    }
+}
+
+/// Reads the value of a port without borrowing it, so that
+/// the debugger can inspect ports of reactors that do not
+/// implement [ReactorBehavior::inspect](crate::ReactorBehavior::inspect).
+///
+/// The probe points to the binding of the port, which is
+/// allocated once and follows the port when it is bound.
+/// It does not keep the port alive, so it must only be read
+/// while the reactors of the program exist, which is the
+/// case when the debugger pauses the program.
+pub(crate) struct PortProbe<T: Sync> {
+    id: TriggerId,
+    #[cfg(feature = "no-unsafe")]
+    binding: *const AtomicRefCell<Rc<PortCell<T>>>,
+    #[cfg(not(feature = "no-unsafe"))]
+    binding: *const UnsafeCell<Rc<PortCell<T>>>,
+}
+
+// The probe only reads the port, like the reactions that are
+// executed in parallel with the same (unsynchronized) ports,
+// and it never touches the reference counts of the binding.
+unsafe impl<T: Sync> Send for PortProbe<T> {}
+
+unsafe impl<T: Sync> Sync for PortProbe<T> {}
+
+impl<T: Sync> TriggerLike for PortProbe<T> {
+    fn get_id(&self) -> TriggerId {
+        self.id
+    }
+}
+
+impl<T: Sync> ReactionTrigger<T> for PortProbe<T> {
+    fn get_value(&self, now: &EventTag, start: &Instant) -> Option<T>
+    where
+        T: Copy,
+    {
+        self.use_value_ref(now, start, |opt| opt.cloned())
+    }
+
+    fn use_value_ref<O>(&self, now: &EventTag, _start: &Instant, action: impl FnOnce(Option<&T>) -> O) -> O {
+        // the port is alive, see the doc of the struct
+        let binding = unsafe { &*self.binding };
+        cfg_if! {
+            if #[cfg(feature = "no-unsafe")] {
+                AtomicRefCell::borrow(binding).use_value_at(now, action)
+            } else {
+                unsafe { &*binding.get() }.use_value_at(now, action)
+            }
+        }
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 enum BindStatus {
     /// A bindable port is also writable explicitly (with set)
@@ -408,6 +633,19 @@ enum BindStatus {
     /// and a single writable port through which values are
     /// communicated ([Self::Upstream]).
     Bound,
+
+    /// The port is the downstream of a delayed connection.
+    /// It cannot be written to explicitly, but it is still
+    /// the head of its own equivalence class, whose values
+    /// are the in-flight values of the connection.
+    DelayedBound,
+}
+
+impl BindStatus {
+    #[inline]
+    fn is_bound(self) -> bool {
+        self != BindStatus::Free
+    }
 }
 
 #[cfg(feature = "no-unsafe")]
@@ -433,25 +671,138 @@ struct PortCell<T: Sync> {
     /// then both the equiv class of A and B (the downstream of A)
     /// need to be updated to point to the equiv class of U
     ///
-    /// Coincidentally, this means we can track transitive
-    /// cyclic port dependencies:
-    /// - say you have bound A -> B, then B -> C
-    /// - so all three refer to the equiv class of A, whose downstream is now {B, C}
-    /// - if you then try binding C -> A, then we can know
-    /// that C is in the downstream of A, indicating that there is a cycle.
+    /// When the class of A is merged into that of U, the downstreams
+    /// of A are merged into those of U too, so that this always
+    /// contains every bound port of the class.
     #[cfg(feature = "no-unsafe")]
     downstreams: DownstreamsSafe<T>,
     #[cfg(not(feature = "no-unsafe"))]
     downstreams: DownstreamsUnsafe<T>,
+
+    /// Maps each bound port of the class to the port it is
+    /// directly bound to. Following this from any port leads
+    /// to the head of the class, ie the only port that may be set.
+    ///
+    /// This means we can track transitive cyclic port dependencies:
+    /// - say you have bound A -> B, then B -> C
+    /// - so all three refer to the equiv class of A, which maps C to B, and B to A
+    /// - if you then try binding C -> A, then we can know
+    ///   that A and C are in the same class, indicating that there
+    ///   is a cycle, and the chain is A -> B -> C.
+    upstreams: AtomicRefCell<HashMap<PortId, PortId>>,
+
+    /// Delayed connections going out of this equivalence class.
+    delayed: AtomicRefCell<Vec<DelayedBinding<T>>>,
+
+    /// Values sent to this class by a delayed connection, that
+    /// are not yet delivered (or are being delivered at the current tag).
+    /// The upstream of the connection may write to this map
+    /// while reactions read this port at the same level,
+    /// hence the lock.
+    in_flight: Mutex<VecMap<Reverse<EventTag>, T>>,
+
+    /// Whether a delayed connection goes into this class. This
+    /// is only set during assembly, and spares reading the
+    /// [Self::in_flight] map for classes that never receive values.
+    receives_delayed: AtomicBool,
+}
+
+/// The kind of a connection between two ports that is not
+/// instantaneous.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum ConnectionKind {
+    /// A connection with a logical delay (`a -> b after 10 ms`
+    /// in LF). The downstream value is tagged with the tag
+    /// of the upstream value plus the delay.
+    Logical,
+    /// A physical connection (`a ~> b` in LF). The downstream
+    /// value is tagged with the physical time at which it was
+    /// sent, plus the delay, like a physical action.
+    Physical,
+}
+
+/// A connection with a delay between two equivalence
+/// classes of ports.
+struct DelayedBinding<T: Sync> {
+    /// Id of the downstream port.
+    downstream: PortId,
+    /// How the tag of the downstream value is determined.
+    kind: ConnectionKind,
+    /// Delay of the connection.
+    delay: Duration,
+    /// Equivalence class of the downstream port.
+    target: Rc<PortCell<T>>,
+    /// We need to clone values that are sent on the connection,
+    /// but ports don't require `T: Clone` in general. This is
+    /// captured when the connection is made.
+    clone: fn(&T) -> T,
 }
 
 impl<T: Sync> PortCell<T> {
-    fn check_cycle(&self, upstream_id: &PortId, downstream_id: &PortId) -> Result<(), AssemblyError> {
-        if (&*self.downstreams.borrow()).contains_key(upstream_id) {
-            Err(AssemblyError(CyclicDependency(*upstream_id, *downstream_id)))
+    /// Executes the closure on the value of this class at the given tag.
+    /// If the value has not been set, it may be delivered by a delayed connection.
+    #[inline]
+    fn use_value_at<R>(&self, now: &EventTag, f: impl FnOnce(Option<&T>) -> R) -> R {
+        cfg_if! {
+            if #[cfg(feature = "no-unsafe")] {
+                let value = self.value.borrow();
+                let value: &Option<T> = value.deref();
+            } else {
+                let value: &Option<T> = unsafe { &*self.value.get() };
+            }
+        }
+        if value.is_some() || !self.receives_delayed.load(Ordering::Relaxed) {
+            f(value.as_ref())
         } else {
-            Ok(())
+            let in_flight = self.in_flight.lock().unwrap();
+            f(in_flight.get(&Reverse(*now)))
+        }
+    }
+
+    fn put_in_flight(&self, eta: EventTag, value: T) {
+        if self.in_flight.lock().unwrap().insert(Reverse(eta), value).is_some() {
+            trace!("Value overwritten in a delayed connection for tag {}", eta);
+        }
+    }
+
+    /// Returns the latest tag at which a value is in flight.
+    fn latest_in_flight(&self) -> Option<EventTag> {
+        // keys are sorted in reverse order
+        self.in_flight.lock().unwrap().iter().next().map(|(Reverse(tag), _)| *tag)
+    }
+
+    fn forget_in_flight(&self, tag: &EventTag) {
+        self.in_flight.lock().unwrap().remove(&Reverse(*tag));
+    }
+
+    /// Checks that binding the upstream port, which is in this class,
+    /// to the downstream port, which is the head of the given
+    /// class, would not create a cycle.
+    fn check_cycle(
+        &self,
+        downstream_class: &Rc<PortCell<T>>,
+        upstream_id: &PortId,
+        downstream_id: &PortId,
+    ) -> Result<(), AssemblyError> {
+        if !std::ptr::eq(self, Rc::as_ptr(downstream_class)) {
+            return Ok(());
+        }
+        // The downstream is the head of our class, so the upstream
+        // is already in its downstream. Walk the bindings back up.
+        let upstreams = self.upstreams.borrow();
+        let mut chain = vec![ComponentRef::new(*upstream_id)];
+        let mut cur = *upstream_id;
+        while cur != *downstream_id {
+            cur = upstreams[&cur];
+            chain.push(ComponentRef::new(cur));
+        }
+        chain.reverse();
+        Err(CyclicDependency {
+            upstream: ComponentRef::new(*upstream_id),
+            downstream: ComponentRef::new(*downstream_id),
+            chain,
         }
+        .into())
     }
 
     /// This updates all downstreams to point to the given equiv class instead of `self`
@@ -476,6 +827,10 @@ impl<T: Sync> Default for PortCell<T> {
         PortCell {
             value: Default::default(),
             downstreams: Default::default(),
+            upstreams: Default::default(),
+            delayed: Default::default(),
+            in_flight: Default::default(),
+            receives_delayed: Default::default(),
         }
     }
 }
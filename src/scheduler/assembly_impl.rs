@@ -23,6 +23,7 @@
  */
 
 use std::borrow::Cow;
+use std::fmt::Debug;
 use std::marker::PhantomData;
 
 use index_vec::{Idx, IndexVec};
@@ -40,6 +41,8 @@ pub(super) struct RootAssembler {
     pub(super) graph: DepGraph,
     /// Debug infos
     pub(super) debug_info: DebugInfoRegistry,
+    /// Problems reported so far, which did not abort assembly.
+    diagnostics: Vec<AssemblyDiagnostic>,
 
     /// Next reactor ID to assign
     reactor_id: ReactorId,
@@ -65,24 +68,47 @@ impl RootAssembler {
         }
     }
 
-    /// Top level fun that assembles the main reactor
+    /// Top level fun that assembles the main reactor.
+    /// The last component of the result contains the problems
+    /// that were reported without aborting assembly. If assembly
+    /// was aborted, this panics with all diagnostics found so far.
     pub fn assemble_tree<R: ReactorInitializer + 'static>(
         main_args: R::Params,
-    ) -> (ReactorVec<'static>, DepGraph, DebugInfoRegistry) {
+    ) -> (ReactorVec<'static>, DepGraph, DebugInfoRegistry, AssemblyResult<()>) {
         let mut root = RootAssembler::default();
         let assembler = AssemblyCtx::new(&mut root, ReactorDebugInfo::root::<R::Wrapped>());
 
         let main_reactor = match R::assemble(main_args, assembler) {
             Ok(main) => main.finish(),
-            Err(e) => std::panic::panic_any(e.lift(&root.debug_info)),
+            Err(fatal) => {
+                // also report the problems found before assembly was aborted
+                root.report(fatal);
+                let errors = AssemblyError::from_diagnostics(std::mem::take(&mut root.diagnostics));
+                std::panic::panic_any(errors.unwrap().lift(&root.debug_info))
+            }
         };
         root.debug_info.record_main_reactor(main_reactor.id());
         root.register_reactor(main_reactor);
 
-        let RootAssembler { graph, reactors, debug_info: id_registry, .. } = root;
+        let RootAssembler {
+            graph,
+            reactors,
+            debug_info: id_registry,
+            diagnostics,
+            ..
+        } = root;
 
         let reactors = reactors.into_iter().map(|r| r.expect("Uninitialized reactor!")).collect();
-        (reactors, graph, id_registry)
+        let result = match AssemblyError::from_diagnostics(diagnostics) {
+            Some(errors) => Err(errors.with_paths(&id_registry)),
+            None => Ok(()),
+        };
+        (reactors, graph, id_registry, result)
+    }
+
+    /// Record a problem without aborting assembly.
+    fn report(&mut self, error: AssemblyError) {
+        self.diagnostics.extend(error.into_diagnostics())
     }
 }
 
@@ -92,6 +118,7 @@ impl Default for RootAssembler {
             reactor_id: ReactorId::new(0),
             graph: DepGraph::new(),
             debug_info: DebugInfoRegistry::new(),
+            diagnostics: Vec::new(),
             reactors: Default::default(),
             cur_trigger: TriggerId::FIRST_REGULAR,
         }
@@ -191,7 +218,7 @@ where
 
         // declare dependencies
         let reactions = self.new_reactions(id, num_non_synthetic_reactions, reaction_names);
-        declare_dependencies(&mut DependencyDeclarator { assembler: &mut self }, &mut ich, reactions)?;
+        declare_dependencies(&mut DependencyDeclarator { assembler: &mut self, id }, &mut ich, reactions)?;
         Ok(AssemblyIntermediate(self, ich))
     }
 
@@ -209,6 +236,7 @@ where
         assert!(num_non_synthetic <= N);
 
         let result = array![i => GlobalReactionId::new(my_id, LocalReactionId::from_usize(i)); N];
+        self.globals.debug_info.record_num_reactions(my_id, N);
 
         let mut prev: Option<GlobalReactionId> = None;
         for (i, r) in result.iter().cloned().enumerate() {
@@ -315,8 +343,15 @@ where
 }
 
 /// Declares dependencies between components and reactions.
+///
+/// Problems with the declared dependencies, like conflicting
+/// bindings, are recorded and reported all at once at the end
+/// of assembly. Methods of this struct then return `Ok`, so
+/// that assembly can carry on and find other problems.
 pub struct DependencyDeclarator<'a, 'x, S: ReactorInitializer> {
     assembler: &'a mut AssemblyCtx<'x, S>,
+    /// Id of the reactor being assembled.
+    id: ReactorId,
 }
 
 impl<S: ReactorInitializer> DependencyDeclarator<'_, '_, S> {
@@ -348,28 +383,92 @@ impl<S: ReactorInitializer> DependencyDeclarator<'_, '_, S> {
         Ok(())
     }
 
+    /// Declare that the reaction may call [ReactionCtx::spawn_physical_thread],
+    /// which is required to call it. [SyncScheduler::run_main_async]
+    /// rejects programs that contain such a reaction before they
+    /// start, as threads cannot be spawned there.
+    pub fn declare_spawns_threads(&mut self, reaction: GlobalReactionId) -> AssemblyResult<()> {
+        self.graph().reaction_spawns_threads(reaction);
+        Ok(())
+    }
+
     /// Bind two ports together.
     #[inline]
     pub fn bind_ports<T: Sync>(&mut self, upstream: &mut Port<T>, downstream: &mut Port<T>) -> AssemblyResult<()> {
-        upstream.forward_to(downstream)?;
-        self.graph().port_bind(upstream, downstream);
+        match upstream.forward_to(downstream) {
+            Ok(()) => self.graph().port_bind(upstream, downstream),
+            Err(e) => self.report(e),
+        }
+        Ok(())
+    }
+
+    /// Bind two ports together with a logical delay. Values
+    /// set on the upstream port become present on the downstream
+    /// port at the tag that is `delay` later (or one microstep
+    /// later if the delay is zero), like with a logical action.
+    ///
+    /// This is not an instantaneous dependency, so it is not
+    /// recorded in the dependency graph.
+    #[inline]
+    pub fn bind_ports_delayed<T: Sync + Clone>(
+        &mut self,
+        upstream: &mut Port<T>,
+        downstream: &mut Port<T>,
+        delay: Duration,
+    ) -> AssemblyResult<()> {
+        if let Err(e) = upstream.forward_delayed_to(downstream, ConnectionKind::Logical, delay) {
+            self.report(e)
+        }
+        Ok(())
+    }
+
+    /// Bind two ports together with a physical connection.
+    /// Values set on the upstream port are sent to the scheduler
+    /// asynchronously, like for a [physical action](PhysicalAction),
+    /// and become present on the downstream port at a tag that
+    /// is determined by the physical time at which they were
+    /// sent, plus the optional minimum delay.
+    ///
+    /// This is not an instantaneous dependency, so it is not
+    /// recorded in the dependency graph.
+    #[inline]
+    pub fn bind_ports_physical<T: Sync + Clone>(
+        &mut self,
+        upstream: &mut Port<T>,
+        downstream: &mut Port<T>,
+        min_delay: Option<Duration>,
+    ) -> AssemblyResult<()> {
+        if let Err(e) = upstream.forward_delayed_to(downstream, ConnectionKind::Physical, min_delay.unwrap_or(Duration::ZERO)) {
+            self.report(e)
+        }
         Ok(())
     }
 
     /// Bind the ports of the upstream to those of the downstream,
-    /// as if zipping both iterators.
-    /// todo this will just throw away bindings if both iterators are not of the same size
-    ///  normally this should be reported by LFC as a warning, maybe we should implement the same thing here
+    /// as if zipping both iterators. If both iterators are not
+    /// of the same size, a [WidthMismatch](AssemblyDiagnostic::WidthMismatch)
+    /// is reported, and the extra ports are left unbound.
     #[inline]
     pub fn bind_ports_zip<'a, T: Sync + 'a>(
         &mut self,
-        upstream: impl Iterator<Item = &'a mut Port<T>>,
-        downstream: impl Iterator<Item = &'a mut Port<T>>,
+        mut upstream: impl Iterator<Item = &'a mut Port<T>>,
+        mut downstream: impl Iterator<Item = &'a mut Port<T>>,
     ) -> AssemblyResult<()> {
-        for (upstream, downstream) in upstream.zip(downstream) {
-            self.bind_ports(upstream, downstream)?;
+        let mut bound = 0;
+        loop {
+            match (upstream.next(), downstream.next()) {
+                (Some(up), Some(down)) => self.bind_ports(up, down)?,
+                (None, None) => return Ok(()),
+                (up, down) => {
+                    // count the extra ports to report the widths
+                    let up_width = bound + up.map_or(0, |_| 1 + upstream.count());
+                    let down_width = bound + down.map_or(0, |_| 1 + downstream.count());
+                    self.report_width_mismatch(up_width, down_width);
+                    return Ok(());
+                }
+            }
+            bound += 1;
         }
-        Ok(())
     }
 
     #[inline]
@@ -379,8 +478,14 @@ impl<S: ReactorInitializer> DependencyDeclarator<'_, '_, S> {
         mut downstream: impl Iterator<Item = &'a mut Port<T>>,
     ) -> AssemblyResult<()> {
         let mut upstream = upstream.collect::<Vec<_>>();
-        assert!(!upstream.is_empty(), "Empty upstream!");
         let up_len = upstream.len();
+        if up_len == 0 {
+            let down_len = downstream.count();
+            if down_len > 0 {
+                self.report_width_mismatch(0, down_len);
+            }
+            return Ok(());
+        }
         // we have to implement this loop manually instead of with an iterator
         // because we can't clone mutable references in the upstream iterator
         for i in 0.. {
@@ -394,6 +499,27 @@ impl<S: ReactorInitializer> DependencyDeclarator<'_, '_, S> {
         Ok(())
     }
 
+    /// Report a problem found by a user-defined validation.
+    /// Assembly carries on, and fails at the end with all
+    /// reported diagnostics.
+    pub fn report_error(&mut self, message: impl Into<String>) {
+        let reactor = Some(self.reactor_path());
+        self.report(AssemblyDiagnostic::Custom { reactor, message: message.into() }.into())
+    }
+
+    fn report_width_mismatch(&mut self, upstream_width: usize, downstream_width: usize) {
+        let reactor = self.reactor_path();
+        self.report(AssemblyDiagnostic::WidthMismatch { reactor, upstream_width, downstream_width }.into())
+    }
+
+    fn report(&mut self, error: AssemblyError) {
+        self.assembler.globals.report(error)
+    }
+
+    fn reactor_path(&self) -> String {
+        self.assembler.globals.debug_info.get_debug_info(self.id).to_string()
+    }
+
     #[inline]
     fn graph(&mut self) -> &mut DepGraph {
         &mut self.assembler.globals.graph
@@ -406,17 +532,29 @@ pub struct ComponentCreator<'a, 'x, S: ReactorInitializer> {
 }
 
 impl<S: ReactorInitializer> ComponentCreator<'_, '_, S> {
-    pub fn new_port<T: Sync>(&mut self, lf_name: &'static str, kind: PortKind) -> Port<T> {
+    /// Create a port. The debugger can tell whether it is
+    /// present, but cannot print its values, see [Self::new_debug_port].
+    pub fn new_port<T: Sync + 'static>(&mut self, lf_name: &'static str, kind: PortKind) -> Port<T> {
         self.new_port_impl(Cow::Borrowed(lf_name), kind)
     }
 
-    fn new_port_impl<T: Sync>(&mut self, lf_name: Cow<'static, str>, kind: PortKind) -> Port<T> {
+    /// Create a port whose values can be printed by the debugger,
+    /// even if the reactor does not implement [ReactorBehavior::inspect].
+    pub fn new_debug_port<T: Sync + Debug + 'static>(&mut self, lf_name: &'static str, kind: PortKind) -> Port<T> {
+        let port = self.new_port(lf_name, kind);
+        self.probe_values(port.probe());
+        port
+    }
+
+    fn new_port_impl<T: Sync + 'static>(&mut self, lf_name: Cow<'static, str>, kind: PortKind) -> Port<T> {
         let id = self.next_comp_id(lf_name);
         self.graph().record_port(id);
-        Port::new(id, kind)
+        let port = Port::new(id, kind);
+        self.probe_presence(port.probe());
+        port
     }
 
-    pub fn new_port_bank<T: Sync>(
+    pub fn new_port_bank<T: Sync + 'static>(
         &mut self,
         lf_name: &'static str,
         kind: PortKind,
@@ -433,7 +571,22 @@ impl<S: ReactorInitializer> ComponentCreator<'_, '_, S> {
         ))
     }
 
-    fn new_port_bank_component<T: Sync>(
+    /// Create a port bank whose values can be printed by the
+    /// debugger, see [Self::new_debug_port].
+    pub fn new_debug_port_bank<T: Sync + Debug + 'static>(
+        &mut self,
+        lf_name: &'static str,
+        kind: PortKind,
+        len: usize,
+    ) -> Result<PortBank<T>, AssemblyError> {
+        let bank = self.new_port_bank(lf_name, kind, len)?;
+        for i in 0..bank.len() {
+            self.probe_values(bank[i].probe());
+        }
+        Ok(bank)
+    }
+
+    fn new_port_bank_component<T: Sync + 'static>(
         &mut self,
         lf_name: &'static str,
         kind: PortKind,
@@ -442,25 +595,105 @@ impl<S: ReactorInitializer> ComponentCreator<'_, '_, S> {
     ) -> Port<T> {
         let channel_id = self.next_comp_id(Cow::Owned(format!("{}[{}]", lf_name, index)));
         self.graph().record_port_bank_component(bank_id, channel_id);
-        Port::new(channel_id, kind)
+        let port = Port::new(channel_id, kind);
+        self.probe_presence(port.probe());
+        port
     }
 
+    /// Create a logical action. The debugger can only inspect
+    /// it if the reactor implements [ReactorBehavior::inspect],
+    /// as its values are stored in the reactor.
     pub fn new_logical_action<T: Sync>(&mut self, lf_name: &'static str, min_delay: Option<Duration>) -> LogicalAction<T> {
+        self.new_logical_action_with_spacing(lf_name, min_delay, None)
+    }
+
+    /// Create a logical action with a minimum spacing
+    /// between its events, see [MinSpacing].
+    pub fn new_logical_action_with_spacing<T: Sync>(
+        &mut self,
+        lf_name: &'static str,
+        min_delay: Option<Duration>,
+        min_spacing: Option<MinSpacing>,
+    ) -> LogicalAction<T> {
         let id = self.next_comp_id(Cow::Borrowed(lf_name));
         self.graph().record_laction(id);
-        LogicalAction::new(id, min_delay)
+        LogicalAction::new(id, min_delay, min_spacing)
+    }
+
+    /// Create a physical action. It can then also be found by
+    /// code outside of the program, see [SchedulerHandle::physical_action].
+    /// The debugger can tell whether it is present, but cannot
+    /// print its values, see [Self::new_debug_physical_action].
+    pub fn new_physical_action<T: Sync + Send + 'static>(
+        &mut self,
+        lf_name: &'static str,
+        min_delay: Option<Duration>,
+    ) -> PhysicalActionRef<T> {
+        self.new_physical_action_with_spacing(lf_name, min_delay, None)
     }
 
-    pub fn new_physical_action<T: Sync>(&mut self, lf_name: &'static str, min_delay: Option<Duration>) -> PhysicalActionRef<T> {
+    /// Create a physical action with a minimum spacing
+    /// between its events, see [MinSpacing].
+    pub fn new_physical_action_with_spacing<T: Sync + Send + 'static>(
+        &mut self,
+        lf_name: &'static str,
+        min_delay: Option<Duration>,
+        min_spacing: Option<MinSpacing>,
+    ) -> PhysicalActionRef<T> {
         let id = self.next_comp_id(Cow::Borrowed(lf_name));
         self.graph().record_paction(id);
-        PhysicalActionRef::new(id, min_delay)
+        let action = PhysicalActionRef::new(id, min_delay, min_spacing);
+        self.assembler.globals.debug_info.record_physical_action(id, action.clone());
+        self.probe_presence(action.clone());
+        action
+    }
+
+    /// Create a physical action whose values can be printed by
+    /// the debugger, even if the reactor does not implement
+    /// [ReactorBehavior::inspect].
+    pub fn new_debug_physical_action<T: Sync + Send + Debug + 'static>(
+        &mut self,
+        lf_name: &'static str,
+        min_delay: Option<Duration>,
+    ) -> PhysicalActionRef<T> {
+        self.new_debug_physical_action_with_spacing(lf_name, min_delay, None)
+    }
+
+    /// Create a physical action with a minimum spacing between
+    /// its events, whose values can be printed by the debugger.
+    pub fn new_debug_physical_action_with_spacing<T: Sync + Send + Debug + 'static>(
+        &mut self,
+        lf_name: &'static str,
+        min_delay: Option<Duration>,
+        min_spacing: Option<MinSpacing>,
+    ) -> PhysicalActionRef<T> {
+        let action = self.new_physical_action_with_spacing(lf_name, min_delay, min_spacing);
+        self.probe_values(action.clone());
+        action
     }
 
     pub fn new_timer(&mut self, lf_name: &'static str, offset: Duration, period: Duration) -> Timer {
         let id = self.next_comp_id(Cow::Borrowed(lf_name));
         self.graph().record_timer(id);
-        Timer::new(id, offset, period)
+        let timer = Timer::new(id, offset, period);
+        self.probe_presence(timer.probe());
+        timer
+    }
+
+    /// Let the debugger tell whether the component is present.
+    fn probe_presence<T>(&mut self, component: impl ReactionTrigger<T> + TriggerLike + Send + Sync + 'static) {
+        let id = component.get_id();
+        self.assembler.globals.debug_info.record_probe(id, move |inspector| {
+            inspector.presence(&component);
+        });
+    }
+
+    /// Let the debugger print the values of the component.
+    fn probe_values<T: Debug>(&mut self, component: impl ReactionTrigger<T> + TriggerLike + Send + Sync + 'static) {
+        let id = component.get_id();
+        self.assembler.globals.debug_info.record_probe(id, move |inspector| {
+            inspector.value(&component);
+        });
     }
 
     /// Create and return a new id for a trigger component.
@@ -545,3 +778,69 @@ macro_rules! unsafe_iter_bank {
         iter.map(move |(i, j)| unsafe { &mut (*__ptr.add(i)).$field_name[j] })
     }};
 }
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    /// A reactor with several invalid connections.
+    struct BadBindings {
+        id: ReactorId,
+        ports: [Port<u32>; 5],
+    }
+
+    impl ReactorBehavior for BadBindings {
+        fn id(&self) -> ReactorId {
+            self.id
+        }
+
+        fn react(&mut self, _ctx: &mut ReactionCtx, _rid: LocalReactionId) {}
+
+        fn cleanup_tag(&mut self, _ctx: &CleanupCtx) {}
+    }
+
+    impl ReactorInitializer for BadBindings {
+        type Wrapped = ();
+        type Params = ();
+        const MAX_REACTION_ID: LocalReactionId = LocalReactionId::new(0);
+
+        fn assemble(_args: (), ctx: AssemblyCtx<Self>) -> AssemblyResult<FinishedReactor<Self>> {
+            ctx.assemble(|ctx| {
+                ctx.assemble_self(
+                    |cc, id| {
+                        let ports = ["a", "b", "c", "d", "e"].map(|name| cc.new_port(name, PortKind::Output));
+                        Ok(BadBindings { id, ports })
+                    },
+                    0,
+                    [],
+                    |decl, me, []| {
+                        let [a, b, c, d, e] = &mut me.ports;
+                        decl.bind_ports(a, b)?;
+                        // b is already bound
+                        decl.bind_ports(c, b)?;
+                        // d is left unbound
+                        decl.bind_ports_zip([c, d].into_iter(), std::iter::once(e))?;
+                        Ok(())
+                    },
+                )
+            })
+        }
+    }
+
+    #[test]
+    fn test_assembly_collects_diagnostics() {
+        let (_, _, _, result) = RootAssembler::assemble_tree::<BadBindings>(());
+        let error = result.unwrap_err();
+        assert_matches!(
+            error.diagnostics(),
+            [
+                AssemblyDiagnostic::CannotBind { .. },
+                AssemblyDiagnostic::WidthMismatch { upstream_width: 2, downstream_width: 1, .. }
+            ]
+        );
+        assert_eq!(
+            "2 errors during assembly:\n - Cannot bind /c to /b, downstream is already bound\n - In /: cannot bind 2 upstream port(s) to 1 downstream port(s)",
+            error.to_string()
+        );
+    }
+}
@@ -0,0 +1,771 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+//! The channel through which asynchronous events, eg those
+//! of physical actions, are sent to the scheduler.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::reconnectable::{unbounded, Receiver, RecvError, RecvTimeoutError, SendError, Sender};
+
+use super::blocking_pool::BlockingPool;
+use super::dependencies::DataflowInfo;
+use super::events::QueueMonitorState;
+use super::{Event, SchedulerHandle};
+use crate::assembly::TriggerId;
+use crate::{DebugInfoRegistry, EventTag};
+
+/// What to do when a physical action is scheduled while
+/// the asynchronous event channel is full.
+/// See [SchedulerOptions::async_capacity](crate::SchedulerOptions::async_capacity).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum OverflowPolicy {
+    /// Block the sending thread until the scheduler has
+    /// received some events.
+    #[default]
+    Block,
+    /// Fail with [TrySendError::Full](crossbeam_channel::TrySendError::Full).
+    Fail,
+    /// If the scheduler has not started processing the latest
+    /// event of the same action, replace the value of that event
+    /// instead of sending a new one. Otherwise, block.
+    Coalesce,
+}
+
+impl std::str::FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "block" => Ok(OverflowPolicy::Block),
+            "fail" => Ok(OverflowPolicy::Fail),
+            "coalesce" => Ok(OverflowPolicy::Coalesce),
+            _ => Err(format!(
+                "unknown overflow policy '{}', expected one of block, fail, coalesce",
+                s
+            )),
+        }
+    }
+}
+
+/// Statistics about the asynchronous event channel, which
+/// make an overload visible. See [ReactionCtx::async_channel_stats](crate::ReactionCtx::async_channel_stats).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AsyncChannelStats {
+    /// Number of events sent but not received yet by the scheduler.
+    pub depth: usize,
+    /// The largest depth observed so far.
+    pub max_depth: usize,
+    /// The capacity of the channel, None if it is unbounded.
+    pub capacity: Option<usize>,
+    /// Total number of events sent.
+    pub sent: u64,
+    /// Number of events whose sender had to wait because
+    /// the channel was full.
+    pub blocked: u64,
+    /// Number of events rejected because the channel was full.
+    pub rejected: u64,
+    /// Number of events merged into a pending event of the same action.
+    pub coalesced: u64,
+}
+
+/// An event sent by a [SchedulerHandle](crate::SchedulerHandle).
+/// Handles don't borrow the dataflow graph, so the reactions
+/// to execute are only resolved when the scheduler receives it.
+pub(super) struct ExternalEvent {
+    tag: EventTag,
+    trigger: Option<TriggerId>,
+    terminate: bool,
+}
+
+impl ExternalEvent {
+    pub(super) fn execute(tag: EventTag, trigger: TriggerId) -> Self {
+        Self { tag, trigger: Some(trigger), terminate: false }
+    }
+
+    pub(super) fn terminate_at(tag: EventTag) -> Self {
+        Self { tag, trigger: None, terminate: true }
+    }
+}
+
+/// State shared by the receiver and all senders.
+pub(super) struct ChannelState {
+    capacity: Option<usize>,
+    policy: OverflowPolicy,
+    /// Number of events sent or about to be sent, and not
+    /// received yet. This is what the capacity limits.
+    depth: Mutex<usize>,
+    not_full: Condvar,
+    /// Whether we already warned that the channel is full.
+    warned_full: AtomicBool,
+    /// The latest tag the scheduler has started to process.
+    /// Asynchronous events must be tagged strictly after it.
+    latest_started: Mutex<EventTag>,
+    /// Start time of the program, set when the scheduler starts.
+    initial_time: OnceLock<Instant>,
+
+    /// Events sent by scheduler handles.
+    external: Mutex<VecDeque<ExternalEvent>>,
+    /// Number of live [AsyncSender]s.
+    senders: AtomicUsize,
+    /// Number of live scheduler handles.
+    external_senders: AtomicUsize,
+    /// Incremented when an event is sent or a sender hangs up,
+    /// to wake up the scheduler if it waits for that.
+    signal: Mutex<u64>,
+    signaled: Condvar,
+    /// Woken up on a signal, if the scheduler waits for one
+    /// asynchronously.
+    waker: Mutex<Option<Waker>>,
+    /// Snapshots of the event queue of the scheduler, which
+    /// scheduler handles may observe.
+    queue_monitor: Arc<QueueMonitorState>,
+
+    max_depth: AtomicUsize,
+    sent: AtomicU64,
+    blocked: AtomicU64,
+    rejected: AtomicU64,
+    coalesced: AtomicU64,
+}
+
+impl ChannelState {
+    pub(super) fn new(capacity: Option<usize>, policy: OverflowPolicy) -> Arc<Self> {
+        Arc::new(ChannelState {
+            capacity,
+            policy,
+            depth: Mutex::new(0),
+            not_full: Condvar::new(),
+            warned_full: AtomicBool::new(false),
+            latest_started: Mutex::new(EventTag::ORIGIN),
+            initial_time: OnceLock::new(),
+            external: Default::default(),
+            senders: Default::default(),
+            external_senders: Default::default(),
+            signal: Mutex::new(0),
+            signaled: Condvar::new(),
+            waker: Mutex::new(None),
+            queue_monitor: Default::default(),
+            max_depth: Default::default(),
+            sent: Default::default(),
+            blocked: Default::default(),
+            rejected: Default::default(),
+            coalesced: Default::default(),
+        })
+    }
+
+    pub(super) fn policy(&self) -> OverflowPolicy {
+        self.policy
+    }
+
+    pub(super) fn stats(&self) -> AsyncChannelStats {
+        AsyncChannelStats {
+            depth: *self.depth.lock().unwrap(),
+            max_depth: self.max_depth.load(Ordering::Relaxed),
+            capacity: self.capacity,
+            sent: self.sent.load(Ordering::Relaxed),
+            blocked: self.blocked.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The start time of the program, None if the scheduler
+    /// has not started yet.
+    pub(super) fn initial_time(&self) -> Option<Instant> {
+        self.initial_time.get().copied()
+    }
+
+    /// Snapshots of the event queue, shared with the queue of the scheduler.
+    pub(super) fn queue_monitor(&self) -> &Arc<QueueMonitorState> {
+        &self.queue_monitor
+    }
+
+    /// Fix the start time of the program to the current time.
+    pub(super) fn start_clock(&self) -> Instant {
+        *self.initial_time.get_or_init(Instant::now)
+    }
+
+    /// The latest tag the scheduler has started to process.
+    pub(super) fn latest_started(&self) -> EventTag {
+        *self.latest_started.lock().unwrap()
+    }
+
+    /// Run the given function with the latest tag the scheduler
+    /// has started to process. The scheduler cannot start another
+    /// tag until the function returns, so events it sends with a
+    /// later tag than that will be processed in order.
+    pub(super) fn with_latest_started<R>(&self, f: impl FnOnce(EventTag) -> R) -> R {
+        let latest_started = self.latest_started.lock().unwrap();
+        f(*latest_started)
+    }
+
+    /// Reserve room for an event if the channel is not full.
+    pub(super) fn try_reserve(&self) -> bool {
+        let mut depth = self.depth.lock().unwrap();
+        if self.is_full(*depth) {
+            self.warn_full();
+            return false;
+        }
+        self.incr_depth(&mut depth);
+        true
+    }
+
+    /// Wait until there is room in the channel and reserve it.
+    /// Returns false if the scheduler has been terminated in
+    /// the meantime.
+    pub(super) fn reserve_blocking(&self, was_terminated: &AtomicBool) -> bool {
+        self.blocked.fetch_add(1, Ordering::Relaxed);
+        let mut depth = self.depth.lock().unwrap();
+        while self.is_full(*depth) {
+            if was_terminated.load(Ordering::SeqCst) {
+                return false;
+            }
+            depth = self.not_full.wait(depth).unwrap();
+        }
+        self.incr_depth(&mut depth);
+        true
+    }
+
+    /// Wake up the senders that wait for room in the channel,
+    /// after the scheduler has been terminated.
+    pub(super) fn notify_terminated(&self) {
+        // taking the lock ensures no sender is between its
+        // check of the termination flag and its wait
+        let _depth = self.depth.lock().unwrap();
+        self.not_full.notify_all();
+    }
+
+    pub(super) fn cancel_reservation(&self) {
+        self.release()
+    }
+
+    pub(super) fn record_rejected(&self) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_coalesced(&self) {
+        self.coalesced.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Send an event from a scheduler handle, for which room
+    /// has been reserved.
+    pub(super) fn send_external_reserved(&self, evt: ExternalEvent) {
+        self.external.lock().unwrap().push_back(evt);
+        self.record_sent();
+    }
+
+    /// Send an event from a scheduler handle regardless of the
+    /// capacity of the channel.
+    pub(super) fn send_external_unchecked(&self, evt: ExternalEvent) {
+        self.incr_depth(&mut self.depth.lock().unwrap());
+        self.send_external_reserved(evt)
+    }
+
+    pub(super) fn add_external_sender(&self) {
+        self.external_senders.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(super) fn remove_external_sender(&self) {
+        if self.external_senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            // the scheduler may be waiting for this
+            self.signal();
+        }
+    }
+
+    fn remove_sender(&self) {
+        if self.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.signal();
+        }
+    }
+
+    /// Whether no sender is left that could send an event.
+    fn is_disconnected(&self) -> bool {
+        self.senders.load(Ordering::SeqCst) == 0 && self.external_senders.load(Ordering::SeqCst) == 0
+    }
+
+    fn record_sent(&self) {
+        self.sent.fetch_add(1, Ordering::Relaxed);
+        self.signal();
+    }
+
+    fn signal(&self) {
+        *self.signal.lock().unwrap() += 1;
+        self.signaled.notify_all();
+        let waker = self.waker.lock().unwrap().take();
+        if let Some(waker) = waker {
+            waker.wake()
+        }
+    }
+
+    fn is_full(&self, depth: usize) -> bool {
+        matches!(self.capacity, Some(cap) if depth >= cap)
+    }
+
+    fn incr_depth(&self, depth: &mut usize) {
+        *depth += 1;
+        self.max_depth.fetch_max(*depth, Ordering::Relaxed);
+    }
+
+    fn release(&self) {
+        let mut depth = self.depth.lock().unwrap();
+        *depth -= 1;
+        self.not_full.notify_one();
+    }
+
+    fn warn_full(&self) {
+        if !self.warned_full.swap(true, Ordering::Relaxed) {
+            warn!(
+                "Asynchronous event channel is full (capacity {}), physical actions are scheduled faster than they are processed",
+                self.capacity.unwrap()
+            );
+        }
+    }
+}
+
+/// The receiving end of the channel, owned by the scheduler.
+pub(super) struct AsyncChannel<'x> {
+    rx: Receiver<Event<'x>>,
+    state: Arc<ChannelState>,
+    /// Used to resolve the reactions of external events.
+    dataflow: &'x DataflowInfo,
+    /// Executes blocking jobs, which send their result through this channel.
+    blocking_pool: BlockingPool,
+    /// Shared with the handles created by this channel.
+    debug: Arc<DebugInfoRegistry>,
+}
+
+impl<'x> AsyncChannel<'x> {
+    /// Create a channel that receives the events sent with the given state.
+    pub(super) fn new(
+        state: Arc<ChannelState>,
+        dataflow: &'x DataflowInfo,
+        blocking_pool: BlockingPool,
+        debug: Arc<DebugInfoRegistry>,
+    ) -> Self {
+        let (_, rx) = unbounded::<Event<'x>>();
+        Self { rx, state, dataflow, blocking_pool, debug }
+    }
+
+    pub(super) fn blocking_pool(&self) -> &BlockingPool {
+        &self.blocking_pool
+    }
+
+    /// Wake up the senders blocked on a full channel, see
+    /// [ChannelState::notify_terminated].
+    pub(super) fn notify_terminated(&self) {
+        self.state.notify_terminated()
+    }
+
+    /// Create a handle that sends events through this channel.
+    pub(super) fn new_handle(&self, was_terminated: Arc<AtomicBool>) -> SchedulerHandle {
+        SchedulerHandle::new(self.state.clone(), was_terminated, self.debug.clone())
+    }
+
+    pub(super) fn new_sender(&self) -> AsyncSender<'x> {
+        AsyncSender::new(self.rx.new_sender(), self.state.clone())
+    }
+
+    pub(super) fn stats(&self) -> AsyncChannelStats {
+        self.state.stats()
+    }
+
+    /// Record that the scheduler starts processing the given
+    /// tag, unless an asynchronous event with an earlier tag has
+    /// been sent in the meantime. In that case, the tag must not
+    /// be processed yet, and this returns false. Events received
+    /// here are appended to `received`.
+    pub(super) fn start_tag(&self, tag: EventTag, received: &mut Vec<Event<'x>>) -> bool {
+        // senders hold this lock while they tag and send their event
+        let mut latest_started = self.state.latest_started.lock().unwrap();
+        while let Some(evt) = self.try_recv() {
+            received.push(evt);
+        }
+        if received.iter().any(|evt| evt.tag < tag) {
+            return false;
+        }
+        *latest_started = tag;
+        true
+    }
+
+    /// Receive an event if one is available, without blocking.
+    pub(super) fn try_recv(&self) -> Option<Event<'x>> {
+        let evt = match self.rx.try_recv() {
+            Ok(evt) => evt,
+            Err(_) => {
+                let ExternalEvent { tag, trigger, terminate } = self.state.external.lock().unwrap().pop_front()?;
+                let reactions = trigger.map(|t| Cow::Borrowed(self.dataflow.reactions_triggered_by(&t)));
+                Event { tag, reactions, terminate, timers: Vec::new() }
+            }
+        };
+        self.state.release();
+        Some(evt)
+    }
+
+    /// Wait for an event. Fails if all senders have hung up.
+    pub(super) fn recv(&self) -> Result<Event<'x>, RecvError> {
+        // senders signal after sending, so holding the lock while
+        // we look at the channel ensures no event is missed
+        let mut signal = self.state.signal.lock().unwrap();
+        loop {
+            if let Some(evt) = self.try_recv() {
+                return Ok(evt);
+            } else if self.state.is_disconnected() {
+                return Err(RecvError);
+            }
+            signal = self.state.signaled.wait(signal).unwrap();
+        }
+    }
+
+    /// Wait for an event for at most the given duration.
+    pub(super) fn recv_timeout(&self, timeout: Duration) -> Result<Event<'x>, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        let mut signal = self.state.signal.lock().unwrap();
+        loop {
+            if let Some(evt) = self.try_recv() {
+                return Ok(evt);
+            } else if self.state.is_disconnected() {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            match deadline.checked_duration_since(Instant::now()) {
+                Some(timeout) if !timeout.is_zero() => {
+                    signal = self.state.signaled.wait_timeout(signal, timeout).unwrap().0;
+                }
+                _ => return Err(RecvTimeoutError::Timeout),
+            }
+        }
+    }
+
+    /// Wait asynchronously for an event, or until the given
+    /// sleep future completes. If `until_disconnected` is true,
+    /// this also completes when all senders have hung up.
+    pub(super) fn recv_async<'c, S: Future<Output = ()>>(
+        &'c self,
+        sleep: Option<S>,
+        until_disconnected: bool,
+    ) -> RecvAsync<'c, 'x, S> {
+        RecvAsync {
+            channel: self,
+            sleep: sleep.map(Box::pin),
+            until_disconnected,
+        }
+    }
+}
+
+/// Result of [AsyncChannel::recv_async].
+pub(super) enum Wakeup<'x> {
+    Event(Event<'x>),
+    Timeout,
+    Disconnected,
+}
+
+/// Future returned by [AsyncChannel::recv_async].
+pub(super) struct RecvAsync<'c, 'x, S> {
+    channel: &'c AsyncChannel<'x>,
+    sleep: Option<Pin<Box<S>>>,
+    until_disconnected: bool,
+}
+
+impl<'x, S: Future<Output = ()>> Future for RecvAsync<'_, 'x, S> {
+    type Output = Wakeup<'x>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let state = &self.channel.state;
+        // register before looking at the channel so that no event is missed
+        *state.waker.lock().unwrap() = Some(cx.waker().clone());
+
+        if let Some(evt) = self.channel.try_recv() {
+            return Poll::Ready(Wakeup::Event(evt));
+        }
+        if self.until_disconnected && state.is_disconnected() {
+            return Poll::Ready(Wakeup::Disconnected);
+        }
+        match &mut self.sleep {
+            Some(sleep) => sleep.as_mut().poll(cx).map(|_| Wakeup::Timeout),
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// The sending end of the channel. Sending an event is
+/// done in two steps: first reserve some room in the
+/// channel (see [ChannelState::try_reserve]), then send
+/// the event or cancel the reservation.
+pub(super) struct AsyncSender<'x> {
+    tx: Sender<Event<'x>>,
+    state: Arc<ChannelState>,
+}
+
+impl<'x> AsyncSender<'x> {
+    fn new(tx: Sender<Event<'x>>, state: Arc<ChannelState>) -> Self {
+        state.senders.fetch_add(1, Ordering::SeqCst);
+        Self { tx, state }
+    }
+
+    pub(super) fn state(&self) -> &ChannelState {
+        &self.state
+    }
+
+    /// Send an event for which room has been reserved.
+    pub(super) fn send_reserved(&self, evt: Event<'x>) -> Result<(), SendError<Event<'x>>> {
+        self.tx.send(evt).inspect_err(|_| self.state.cancel_reservation())?;
+        self.state.record_sent();
+        Ok(())
+    }
+
+    /// Send an event regardless of the capacity of the channel.
+    /// This is used for events that must not be lost, or that
+    /// are sent by the scheduler thread itself, which must not block.
+    pub(super) fn send_unchecked(&self, evt: Event<'x>) -> Result<(), SendError<Event<'x>>> {
+        self.state.incr_depth(&mut self.state.depth.lock().unwrap());
+        self.send_reserved(evt)
+    }
+}
+
+impl Clone for AsyncSender<'_> {
+    fn clone(&self) -> Self {
+        Self::new(self.tx.clone(), self.state.clone())
+    }
+}
+
+impl Drop for AsyncSender<'_> {
+    fn drop(&mut self) {
+        self.state.remove_sender()
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::scheduler::blocking_pool::BlockingShutdown;
+    use crate::scheduler::dependencies::DepGraph;
+    use crate::tag;
+
+    fn empty_dataflow() -> DataflowInfo {
+        DataflowInfo::new(DepGraph::new()).unwrap()
+    }
+
+    fn test_pool() -> BlockingPool {
+        BlockingPool::new(1, BlockingShutdown::Cancel)
+    }
+
+    fn test_debug() -> Arc<DebugInfoRegistry> {
+        Arc::new(DebugInfoRegistry::new())
+    }
+
+    #[test]
+    fn test_capacity_and_stats() {
+        let dataflow = empty_dataflow();
+        let channel = AsyncChannel::new(
+            ChannelState::new(Some(2), OverflowPolicy::Fail),
+            &dataflow,
+            test_pool(),
+            test_debug(),
+        );
+        let tx = channel.new_sender();
+
+        for i in 0..2 {
+            assert!(tx.state().try_reserve());
+            tx.send_reserved(Event::terminate_at(tag!(T0 + i ms))).unwrap();
+        }
+        assert!(!tx.state().try_reserve());
+        tx.state().record_rejected();
+        // some events bypass the capacity
+        tx.send_unchecked(Event::terminate_at(tag!(T0 + 5 ms))).unwrap();
+
+        let stats = channel.stats();
+        assert_eq!(stats.depth, 3);
+        assert_eq!(stats.max_depth, 3);
+        assert_eq!(stats.capacity, Some(2));
+        assert_eq!(stats.sent, 3);
+        assert_eq!(stats.rejected, 1);
+
+        assert!(channel.try_recv().is_some());
+        assert!(channel.try_recv().is_some());
+        assert!(tx.state().try_reserve());
+        tx.state().cancel_reservation();
+
+        let stats = tx.state().stats();
+        assert_eq!(stats.depth, 1);
+        assert_eq!(stats.max_depth, 3);
+    }
+
+    #[test]
+    fn test_blocked_sender_is_woken_up() {
+        let dataflow = empty_dataflow();
+        let channel = AsyncChannel::new(
+            ChannelState::new(Some(1), OverflowPolicy::Block),
+            &dataflow,
+            test_pool(),
+            test_debug(),
+        );
+        let tx = channel.new_sender();
+        let was_terminated = AtomicBool::new(false);
+        assert!(tx.state().try_reserve());
+        tx.send_reserved(Event::terminate_at(tag!(T0))).unwrap();
+
+        std::thread::scope(|s| {
+            let tx = tx.clone();
+            let was_terminated = &was_terminated;
+            let handle = s.spawn(move || tx.state().reserve_blocking(was_terminated));
+            std::thread::sleep(Duration::from_millis(20));
+            assert!(channel.try_recv().is_some());
+            assert!(handle.join().unwrap());
+        });
+        assert_eq!(channel.stats().blocked, 1);
+        assert_eq!(channel.stats().depth, 1);
+
+        // a terminated scheduler does not leave senders blocked
+        was_terminated.store(true, Ordering::SeqCst);
+        assert!(!tx.state().reserve_blocking(&was_terminated));
+    }
+
+    #[test]
+    fn test_blocked_sender_is_woken_up_on_termination() {
+        let dataflow = empty_dataflow();
+        let channel = AsyncChannel::new(
+            ChannelState::new(Some(1), OverflowPolicy::Block),
+            &dataflow,
+            test_pool(),
+            test_debug(),
+        );
+        let tx = channel.new_sender();
+        let was_terminated = AtomicBool::new(false);
+        assert!(tx.state().try_reserve());
+
+        std::thread::scope(|s| {
+            let tx = tx.clone();
+            let was_terminated = &was_terminated;
+            let handle = s.spawn(move || tx.state().reserve_blocking(was_terminated));
+            std::thread::sleep(Duration::from_millis(20));
+            // what the scheduler does when it shuts down
+            was_terminated.store(true, Ordering::SeqCst);
+            channel.notify_terminated();
+            assert!(!handle.join().unwrap());
+        });
+        assert_eq!(channel.stats().depth, 1);
+    }
+
+    #[test]
+    fn test_start_tag_waits_for_earlier_events() {
+        let dataflow = empty_dataflow();
+        let channel = AsyncChannel::new(
+            ChannelState::new(None, OverflowPolicy::Block),
+            &dataflow,
+            BlockingPool::new(1, BlockingShutdown::Cancel),
+            test_debug(),
+        );
+        let tx = channel.new_sender();
+        let mut received = Vec::new();
+        assert!(channel.start_tag(tag!(T0 + 1 ms), &mut received));
+        assert_eq!(tx.state().latest_started(), tag!(T0 + 1 ms));
+
+        // an event sent with an earlier tag than the one the scheduler wants to start
+        tx.send_unchecked(Event::terminate_at(tag!(T0 + 2 ms))).unwrap();
+        assert!(!channel.start_tag(tag!(T0 + 3 ms), &mut received));
+        assert_eq!(received.len(), 1);
+        assert_eq!(tx.state().latest_started(), tag!(T0 + 1 ms));
+
+        received.clear();
+        tx.state().with_latest_started(|latest| {
+            assert_eq!(latest, tag!(T0 + 1 ms));
+            tx.send_unchecked(Event::terminate_at(tag!(T0 + 3 ms))).unwrap();
+        });
+        assert!(channel.start_tag(tag!(T0 + 3 ms), &mut received));
+        assert_eq!(received.len(), 1);
+        assert_eq!(tx.state().latest_started(), tag!(T0 + 3 ms));
+    }
+
+    #[test]
+    fn test_external_events_and_recv_async() {
+        use std::future::{pending, ready, Pending};
+        use std::pin::pin;
+
+        let dataflow = empty_dataflow();
+        let state = ChannelState::new(None, OverflowPolicy::Block);
+        let channel = AsyncChannel::new(state.clone(), &dataflow, test_pool(), test_debug());
+        let mut cx = Context::from_waker(Waker::noop());
+
+        state.add_external_sender();
+        let mut recv = pin!(channel.recv_async(Some(pending()), true));
+        assert!(recv.as_mut().poll(&mut cx).is_pending());
+        state.send_external_unchecked(ExternalEvent::terminate_at(tag!(T0 + 1 ms)));
+        match recv.poll(&mut cx) {
+            Poll::Ready(Wakeup::Event(evt)) => {
+                assert!(evt.terminate);
+                assert_eq!(evt.tag, tag!(T0 + 1 ms));
+            }
+            _ => panic!("expected an event"),
+        }
+        assert_eq!(state.stats().depth, 0);
+
+        let recv = pin!(channel.recv_async(Some(ready(())), true));
+        assert!(matches!(recv.poll(&mut cx), Poll::Ready(Wakeup::Timeout)));
+
+        // no handle is left to send an event
+        state.remove_external_sender();
+        let recv = pin!(channel.recv_async(None::<Pending<()>>, true));
+        assert!(matches!(recv.poll(&mut cx), Poll::Ready(Wakeup::Disconnected)));
+    }
+
+    #[test]
+    fn test_recv_sees_all_senders() {
+        let dataflow = empty_dataflow();
+        let state = ChannelState::new(None, OverflowPolicy::Block);
+        let channel = AsyncChannel::new(state.clone(), &dataflow, test_pool(), test_debug());
+        assert!(matches!(channel.recv(), Err(RecvError)));
+
+        state.add_external_sender();
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(10));
+                state.send_external_unchecked(ExternalEvent::terminate_at(tag!(T0 + 1 ms)));
+                state.remove_external_sender();
+            });
+            assert_eq!(channel.recv().unwrap().tag, tag!(T0 + 1 ms));
+            // the last sender hung up
+            assert!(matches!(channel.recv(), Err(RecvError)));
+        });
+
+        let tx = channel.new_sender();
+        assert!(matches!(
+            channel.recv_timeout(Duration::from_millis(5)),
+            Err(RecvTimeoutError::Timeout)
+        ));
+        drop(tx);
+        assert!(matches!(
+            channel.recv_timeout(Duration::from_millis(5)),
+            Err(RecvTimeoutError::Disconnected)
+        ));
+    }
+}
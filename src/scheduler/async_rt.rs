@@ -0,0 +1,262 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+//! Support for running a reactor program from async code,
+//! see [SyncScheduler::run_main_async].
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use crossbeam_channel::reconnectable::SendError;
+use crossbeam_channel::TrySendError;
+
+use super::async_channel::{ChannelState, ExternalEvent};
+use super::context::{physical_tag, schedule_physical_impl};
+use crate::assembly::{TriggerId, TriggerLike};
+use crate::*;
+
+/// A timer with which [SyncScheduler::run_main_async] waits
+/// for physical time to catch up with logical time. This lets
+/// the program sleep using the timer of the async runtime.
+///
+/// It is implemented by functions that return a future. For
+/// instance with tokio:
+///
+/// ```ignore
+/// let timer = |deadline: Instant| tokio::time::sleep_until(deadline.into());
+/// ```
+pub trait AsyncTimer {
+    /// The future returned by [Self::sleep_until].
+    type Sleep: Future<Output = ()>;
+
+    /// Returns a future that completes at the given instant.
+    fn sleep_until(&self, deadline: Instant) -> Self::Sleep;
+}
+
+impl<F, S> AsyncTimer for F
+where
+    F: Fn(Instant) -> S,
+    S: Future<Output = ()>,
+{
+    type Sleep = S;
+
+    fn sleep_until(&self, deadline: Instant) -> S {
+        self(deadline)
+    }
+}
+
+/// A handle to a program run with [SyncScheduler::run_main_async],
+/// with which other tasks can schedule physical actions and
+/// request the program to stop. It can be cloned and sent to
+/// other threads.
+///
+/// Like the threads spawned by [ReactionCtx::spawn_physical_thread],
+/// live handles keep the program alive when its event queue is
+/// empty. The program terminates when the queue is empty and all
+/// handles have been dropped, or when it is stopped.
+pub struct SchedulerHandle {
+    channel: Arc<ChannelState>,
+    /// Whether the scheduler has been terminated.
+    was_terminated: Arc<AtomicBool>,
+    /// Debug information of the assembled program.
+    debug: Arc<DebugInfoRegistry>,
+}
+
+impl SchedulerHandle {
+    pub(super) fn new(channel: Arc<ChannelState>, was_terminated: Arc<AtomicBool>, debug: Arc<DebugInfoRegistry>) -> Self {
+        channel.add_external_sender();
+        Self { channel, was_terminated, debug }
+    }
+
+    /// Returns true if the scheduler has been shutdown. When
+    /// that's true, calls to other methods of this type will
+    /// fail.
+    pub fn was_terminated(&self) -> bool {
+        self.was_terminated.load(Ordering::SeqCst)
+    }
+
+    /// Returns statistics about the channel through which
+    /// this handle sends events to the scheduler.
+    /// See [ReactionCtx::async_channel_stats].
+    pub fn async_channel_stats(&self) -> AsyncChannelStats {
+        self.channel.stats()
+    }
+
+    /// Returns the ids of the reactors, triggers and reactions
+    /// whose path matches the given pattern.
+    /// See [ReactionCtx::lookup].
+    pub fn lookup(&self, pattern: &str) -> Vec<ElementId> {
+        self.debug.lookup(pattern)
+    }
+
+    /// Returns the physical action with the given id, eg found
+    /// with [Self::lookup], so that it can be scheduled from
+    /// outside of the program. Returns None if the trigger is
+    /// not a physical action, or if its values are not of type T.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use reactor_rt::*;
+    /// # let handle: SchedulerHandle = unimplemented!();
+    /// for id in handle.lookup("/sensor/reading") {
+    ///     if let ElementId::Trigger(id) = id {
+    ///         let action = handle.physical_action::<f64>(id).expect("not a physical action of f64");
+    ///         handle.schedule_physical_with_v(&action, Some(1.5), Offset::Asap).unwrap();
+    ///     }
+    /// }
+    /// ```
+    pub fn physical_action<T: Sync + Send + 'static>(&self, id: TriggerId) -> Option<PhysicalActionRef<T>> {
+        self.debug.get_physical_action(id)
+    }
+
+    /// Schedule the physical action with the given id with a
+    /// value, like [Self::schedule_physical_with_v].
+    ///
+    /// ### Panics
+    ///
+    /// If the trigger is not a physical action with values
+    /// of type T, see [Self::physical_action].
+    pub fn schedule_physical_by_id<T: Sync + Send + 'static>(
+        &self,
+        id: TriggerId,
+        value: Option<T>,
+        offset: Offset,
+    ) -> Result<ScheduleOutcome, TrySendError<Option<T>>> {
+        let action = self.physical_action(id).unwrap_or_else(|| {
+            panic!(
+                "{} is not a physical action with values of type {}",
+                self.debug.fmt_component(id),
+                std::any::type_name::<T>()
+            )
+        });
+        self.schedule_physical_with_v(&action, value, offset)
+    }
+
+    /// Returns the path of a reactor, trigger or reaction.
+    /// See [ReactionCtx::path_of].
+    pub fn path_of(&self, id: ElementId) -> String {
+        self.debug.fmt_element(id)
+    }
+
+    /// Returns a handle that can observe the event queue of
+    /// the scheduler, see [ReactionCtx::event_queue_monitor].
+    /// A monitor created before the program starts, or while
+    /// the scheduler waits, sees the pending events once the
+    /// scheduler next looks up the event to process.
+    pub fn event_queue_monitor(&self) -> EventQueueMonitor {
+        self.channel.queue_monitor().new_monitor()
+    }
+
+    /// Request that the application shutdown, possibly with
+    /// a particular offset from the current physical time.
+    /// See [AsyncCtx::request_stop].
+    pub fn request_stop(&self, offset: Offset) -> Result<(), SendError<()>> {
+        if self.was_terminated() {
+            return Err(SendError(()));
+        }
+        let now = Instant::now();
+        self.channel.with_latest_started(|latest_started| {
+            let tag = physical_tag(self.channel.initial_time(), now, offset, latest_started);
+            // this one is important enough to ignore the capacity of the channel
+            self.channel.send_external_unchecked(ExternalEvent::terminate_at(tag));
+        });
+        Ok(())
+    }
+
+    /// Schedule a physical action, without a value.
+    /// See [AsyncCtx::schedule_physical].
+    pub fn schedule_physical<T: Sync>(
+        &self,
+        action: &PhysicalActionRef<T>,
+        offset: Offset,
+    ) -> Result<ScheduleOutcome, TrySendError<Option<T>>> {
+        self.schedule_physical_with_v(action, None, offset)
+    }
+
+    /// Schedule a physical action with a value. The event is
+    /// tagged and sent like with [AsyncCtx::schedule_physical_with_v].
+    /// If this is called before the program has started, the
+    /// offset is taken from the start tag.
+    pub fn schedule_physical_with_v<T: Sync>(
+        &self,
+        action: &PhysicalActionRef<T>,
+        value: Option<T>,
+        offset: Offset,
+    ) -> Result<ScheduleOutcome, TrySendError<Option<T>>> {
+        if self.was_terminated() {
+            return Err(TrySendError::Disconnected(value));
+        }
+        // this locks the action, do it before schedule_physical_impl does
+        let trigger = action.get_id();
+        schedule_physical_impl(
+            &self.channel,
+            self.channel.initial_time(),
+            &self.was_terminated,
+            action,
+            value,
+            offset,
+            |tag| {
+                self.channel.send_external_reserved(ExternalEvent::execute(tag, trigger));
+                Ok(())
+            },
+        )
+    }
+}
+
+impl Clone for SchedulerHandle {
+    fn clone(&self) -> Self {
+        Self::new(self.channel.clone(), self.was_terminated.clone(), self.debug.clone())
+    }
+}
+
+impl Drop for SchedulerHandle {
+    fn drop(&mut self) {
+        self.channel.remove_external_sender()
+    }
+}
+
+/// Returns a future that lets other tasks run once, before
+/// completing.
+pub(super) fn yield_now() -> YieldNow {
+    YieldNow(false)
+}
+
+pub(super) struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
@@ -0,0 +1,257 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+//! A pool of threads to which reactions can offload
+//! blocking work, see [ReactionCtx::spawn_blocking](crate::ReactionCtx::spawn_blocking).
+
+use std::collections::VecDeque;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+/// What to do with blocking jobs that are still pending
+/// when the program shuts down. In any case, the results
+/// of jobs that complete after shutdown are discarded.
+/// See [ReactionCtx::spawn_blocking](crate::ReactionCtx::spawn_blocking).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum BlockingShutdown {
+    /// Wait for all submitted jobs to complete.
+    #[default]
+    Await,
+    /// Discard the jobs that have not started yet, and
+    /// wait for the running jobs to complete.
+    Cancel,
+}
+
+impl std::str::FromStr for BlockingShutdown {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "await" => Ok(BlockingShutdown::Await),
+            "cancel" => Ok(BlockingShutdown::Cancel),
+            _ => Err(format!(
+                "unknown blocking shutdown policy '{}', expected one of await, cancel",
+                s
+            )),
+        }
+    }
+}
+
+pub(super) type Job = Box<dyn FnOnce() + Send>;
+
+/// A pool of threads that execute jobs in submission order.
+/// Threads are only started when jobs are submitted and no
+/// thread is idle, up to a maximum.
+pub(super) struct BlockingPool {
+    max_threads: usize,
+    on_shutdown: BlockingShutdown,
+    shared: Arc<PoolShared>,
+    workers: Mutex<Vec<JoinHandle<()>>>,
+}
+
+struct PoolShared {
+    queue: Mutex<PoolQueue>,
+    job_available: Condvar,
+}
+
+#[derive(Default)]
+struct PoolQueue {
+    jobs: VecDeque<Job>,
+    /// Number of threads waiting for a job.
+    idle: usize,
+    shutting_down: bool,
+}
+
+impl BlockingPool {
+    /// Create a pool. If `max_threads` is zero, it uses one
+    /// thread per core.
+    pub(super) fn new(max_threads: usize, on_shutdown: BlockingShutdown) -> Self {
+        let max_threads = if max_threads == 0 {
+            std::thread::available_parallelism().map_or(1, |n| n.get())
+        } else {
+            max_threads
+        };
+        Self {
+            max_threads,
+            on_shutdown,
+            shared: Arc::new(PoolShared {
+                queue: Default::default(),
+                job_available: Condvar::new(),
+            }),
+            workers: Default::default(),
+        }
+    }
+
+    /// Submit a job. Jobs submitted after [Self::shutdown]
+    /// are dropped.
+    pub(super) fn submit(&self, job: Job) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        if queue.shutting_down {
+            warn!("Blocking job submitted after shutdown, ignoring it");
+            return;
+        }
+        queue.jobs.push_back(job);
+        if queue.idle > 0 {
+            self.shared.job_available.notify_one();
+        }
+        // Notified workers only leave the idle count once they
+        // have woken up, so compare with the jobs that are still
+        // pending to know whether one is left without a worker.
+        if queue.jobs.len() <= queue.idle {
+            return;
+        }
+        let mut workers = self.workers.lock().unwrap();
+        if workers.len() < self.max_threads {
+            let shared = self.shared.clone();
+            let worker = std::thread::Builder::new()
+                .name(format!("blocking-worker-{}", workers.len()))
+                .spawn(move || work(&shared))
+                .expect("Could not spawn blocking worker thread");
+            workers.push(worker);
+        }
+    }
+
+    /// Stop accepting jobs, cancel the pending ones if the
+    /// policy says so, and wait for the others to complete.
+    pub(super) fn shutdown(&self) {
+        let cancelled = {
+            let mut queue = self.shared.queue.lock().unwrap();
+            queue.shutting_down = true;
+            self.shared.job_available.notify_all();
+            match self.on_shutdown {
+                BlockingShutdown::Await => VecDeque::new(),
+                BlockingShutdown::Cancel => std::mem::take(&mut queue.jobs),
+            }
+        };
+        if !cancelled.is_empty() {
+            info!("Cancelled {} pending blocking jobs", cancelled.len());
+        }
+        // dropped outside of the lock
+        drop(cancelled);
+
+        let workers = std::mem::take(&mut *self.workers.lock().unwrap());
+        if !workers.is_empty() {
+            info!("Waiting for {} blocking worker threads...", workers.len());
+        }
+        for worker in workers {
+            worker.join().unwrap();
+        }
+    }
+}
+
+/// The loop of a worker thread.
+fn work(shared: &PoolShared) {
+    loop {
+        let job = {
+            let mut queue = shared.queue.lock().unwrap();
+            loop {
+                if let Some(job) = queue.jobs.pop_front() {
+                    break job;
+                } else if queue.shutting_down {
+                    return;
+                }
+                queue.idle += 1;
+                queue = shared.job_available.wait(queue).unwrap();
+                queue.idle -= 1;
+            }
+        };
+        // a panicking job must not take the thread down with it
+        if catch_unwind(AssertUnwindSafe(job)).is_err() {
+            warn!("A blocking job panicked");
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn counting_job(counter: &Arc<AtomicUsize>, sleep: Duration) -> Job {
+        let counter = counter.clone();
+        Box::new(move || {
+            std::thread::sleep(sleep);
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+    }
+
+    #[test]
+    fn test_shutdown_awaits_jobs() {
+        let pool = BlockingPool::new(2, BlockingShutdown::Await);
+        let done = Arc::new(AtomicUsize::new(0));
+        for _ in 0..5 {
+            pool.submit(counting_job(&done, Duration::from_millis(5)));
+        }
+        pool.submit(Box::new(|| panic!("this job panics")));
+        assert!(pool.workers.lock().unwrap().len() <= 2);
+
+        pool.shutdown();
+        assert_eq!(done.load(Ordering::SeqCst), 5);
+        // the pool is closed
+        pool.submit(counting_job(&done, Duration::ZERO));
+        assert_eq!(done.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_shutdown_cancels_pending_jobs() {
+        let pool = BlockingPool::new(1, BlockingShutdown::Cancel);
+        let done = Arc::new(AtomicUsize::new(0));
+        let (started_tx, started) = channel();
+        for _ in 0..5 {
+            let (started_tx, job) = (started_tx.clone(), counting_job(&done, Duration::from_millis(20)));
+            pool.submit(Box::new(move || {
+                let _ = started_tx.send(());
+                job()
+            }));
+        }
+        // shut down while the first job is running
+        started.recv().unwrap();
+        pool.shutdown();
+        // only the running job has completed
+        assert_eq!(done.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_spawns_worker_when_jobs_outnumber_idle_workers() {
+        let pool = BlockingPool::new(2, BlockingShutdown::Await);
+        let done = Arc::new(AtomicUsize::new(0));
+        // a worker that has been notified, but has not woken up yet
+        pool.shared.queue.lock().unwrap().idle = 1;
+
+        pool.submit(counting_job(&done, Duration::ZERO));
+        assert_eq!(pool.workers.lock().unwrap().len(), 0);
+        // the idle worker will only take one of the jobs
+        pool.submit(counting_job(&done, Duration::ZERO));
+        assert_eq!(pool.workers.lock().unwrap().len(), 1);
+
+        pool.shutdown();
+        assert_eq!(done.load(Ordering::SeqCst), 2);
+    }
+}
@@ -3,13 +3,19 @@ use std::hash::{Hash, Hasher};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use crossbeam_channel::reconnectable::{Receiver, SendError, Sender};
+use crossbeam_channel::reconnectable::SendError;
+use crossbeam_channel::TrySendError;
 use crossbeam_utils::thread::{Scope, ScopedJoinHandle};
 use smallvec::SmallVec;
 
+use super::async_channel::{AsyncChannel, AsyncSender, ChannelState};
+use super::debugger::{Debugger, PauseCtx, PausePoint};
+use super::metrics::Metrics;
+use super::reaction_log::{ReactionLogConfig, ReactionLogRecord};
 use super::*;
 use crate::assembly::*;
 use crate::scheduler::dependencies::{DataflowInfo, ExecutableReactions, LevelIx};
+use crate::timers::TimerFiring;
 use crate::*;
 
 /// The context in which a reaction executes. Its API
@@ -37,8 +43,12 @@ where
     /// ID of the reaction being executed.
     current_reaction: Option<GlobalReactionId>,
 
-    /// Sender to schedule events that should be executed later than this wave.
-    rx: &'a Receiver<Event<'x>>,
+    /// Channel to send events that should be executed later than this wave.
+    channel: &'a AsyncChannel<'x>,
+
+    /// The pending events of the scheduler, not including
+    /// the events produced by the current wave.
+    event_queue: &'a EventQueue<'x>,
 
     /// Start time of the program.
     initial_time: Instant,
@@ -46,13 +56,24 @@ where
     // globals, also they might be copied and passed to AsyncCtx
     dataflow: &'x DataflowInfo,
     debug_info: DebugInfoProvider<'a>,
-    thread_spawner: &'a Scope<'t>,
+    /// None if the scheduler runs asynchronously, see [SyncScheduler::run_main_async].
+    thread_spawner: Option<&'a Scope<'t>>,
     /// Whether the scheduler has been shut down.
     was_terminated_atomic: &'a Arc<AtomicBool>,
     /// In ReactionCtx, this will only be true if this is the shutdown tag.
     /// It duplicates [Self::was_terminated_atomic], to avoid an atomic
     /// operation within [Self::is_shutdown].
     was_terminated: bool,
+    /// Span of the tag being processed, which is the parent of
+    /// the spans of reactions, even in worker threads.
+    #[cfg(feature = "tracing")]
+    tag_span: tracing::Span,
+    /// Set by the scheduler if metrics are enabled.
+    pub(super) metrics: Option<&'a Metrics>,
+    /// Set by the scheduler if the program is being debugged.
+    pub(super) debugger: Option<&'a Debugger>,
+    /// Set by the scheduler, see [SchedulerOptions::reaction_log_format].
+    pub(super) reaction_log: ReactionLogConfig,
 }
 
 impl<'a, 'x, 't> ReactionCtx<'a, 'x, 't>
@@ -87,6 +108,75 @@ where
         self.tag.to_logical_time(self.get_start_time())
     }
 
+    /// Returns the current logical time as an absolute
+    /// [LogicalTime], which can be compared across processes.
+    ///
+    /// Repeated invocation of this method will always produce
+    /// the same value.
+    #[inline]
+    pub fn get_absolute_logical_time(&self) -> LogicalTime {
+        self.tag.to_absolute(self.get_start_time())
+    }
+
+    /// Returns true if the current physical time is later than
+    /// the current logical time plus the given deadline, that
+    /// is, if the current reaction missed its deadline. Misses
+    /// are counted in the metrics of the program, see
+    /// [SchedulerOptions::metrics_addr].
+    ///
+    /// ```no_run
+    /// # use reactor_rt::prelude::*;
+    /// # let ctx: &mut ReactionCtx = panic!();
+    /// if ctx.check_deadline(delay!(10 ms)) {
+    ///     // handle the violation
+    /// }
+    /// ```
+    pub fn check_deadline(&self, deadline: Duration) -> bool {
+        let missed = self.get_physical_time() > self.get_logical_time() + deadline;
+        if missed {
+            if let (Some(metrics), Some(reaction)) = (self.metrics, self.current_reaction) {
+                metrics.record_deadline_violation(reaction.0.container());
+            }
+        }
+        missed
+    }
+
+    /// Log a message from the current reaction. The message is
+    /// tagged with the current tag, the instance path of the
+    /// reactor and the label of the reaction, and written in
+    /// the format configured with [SchedulerOptions::reaction_log_format].
+    ///
+    /// The macros [ctx_info!](crate::ctx_info) and similar are
+    /// more convenient to call this:
+    ///
+    /// ```no_run
+    /// # use reactor_rt::prelude::*;
+    /// # use reactor_rt::LogLevel;
+    /// # let ctx: &mut ReactionCtx = panic!();
+    /// # let count = 0;
+    /// ctx_info!(ctx, "received {} messages", count);
+    /// // is equivalent to
+    /// ctx.log(LogLevel::Info, format_args!("received {} messages", count));
+    /// ```
+    pub fn log(&self, level: log::Level, message: std::fmt::Arguments<'_>) {
+        let Some(reaction) = self.current_reaction else {
+            return log!(level, "{} {}", self.tag, message);
+        };
+        if !self.reaction_log.enabled(level) {
+            return;
+        }
+        let registry = self.debug_info.id_registry;
+        self.reaction_log.emit(&ReactionLogRecord {
+            level,
+            tag: self.tag,
+            timestamp_ns: self.get_absolute_logical_time().timestamp.as_nanos(),
+            reactor: registry.get_debug_info(reaction.0.container()).to_string(),
+            reaction_index: reaction.0.local().index(),
+            reaction_label: registry.reaction_label(reaction),
+            message,
+        })
+    }
+
     /// Returns the tag at which the reaction executes.
     ///
     /// Repeated invocation of this method will always produce
@@ -145,6 +235,90 @@ where
         }
     }
 
+    /// Returns the ids of all reactors, triggers and reactions
+    /// whose path matches the given pattern. Paths are relative
+    /// to the main reactor, for instance `child[3]/port` or
+    /// `child[3].port`. In a pattern, `*` matches any sequence of
+    /// characters within a segment, and a `**` segment matches
+    /// any number of segments.
+    ///
+    /// ### Examples
+    ///
+    /// ```no_run
+    /// # use reactor_rt::{ElementId, ReactionCtx};
+    /// # let ctx: &mut ReactionCtx = unimplemented!();
+    /// // all output ports of the bank `workers`
+    /// let outputs = ctx.lookup("workers[*]/out");
+    /// // every reaction labeled `on_tick`
+    /// let on_tick = ctx.lookup("**/on_tick");
+    /// for id in on_tick {
+    ///     assert!(matches!(id, ElementId::Reaction(_)));
+    ///     println!("{}", ctx.path_of(id));
+    /// }
+    /// ```
+    pub fn lookup(&self, pattern: &str) -> Vec<ElementId> {
+        self.debug_info.id_registry.lookup(pattern)
+    }
+
+    /// Returns the path of a reactor, trigger or reaction.
+    /// See also [Self::lookup].
+    pub fn path_of(&self, id: ElementId) -> String {
+        self.debug_info.id_registry.fmt_element(id)
+    }
+
+    /// Returns a description of the events that are pending
+    /// in the event queue of the scheduler, earliest first.
+    /// The event being processed at the current tag is not
+    /// part of it, neither are the events scheduled by
+    /// reactions of the current tag: these are only pushed
+    /// to the queue once all reactions at this tag have
+    /// executed. Asynchronous events that the scheduler has
+    /// not received yet are not included either.
+    ///
+    /// ### Examples
+    ///
+    /// ```no_run
+    /// # use reactor_rt::ReactionCtx;
+    /// # let ctx: &mut ReactionCtx = unimplemented!();
+    /// let pending = ctx.pending_events();
+    /// if let Some(tag) = pending.next_tag() {
+    ///     println!("{} events pending, next one at {}", pending.len(), tag);
+    /// }
+    /// for evt in pending.events() {
+    ///     println!("{}", evt);
+    /// }
+    /// ```
+    pub fn pending_events(&self) -> EventQueueSnapshot {
+        self.event_queue.snapshot(&self.debug_info)
+    }
+
+    /// Returns a handle that can observe the event queue
+    /// from another thread, for instance for monitoring
+    /// purposes. See [EventQueueMonitor].
+    ///
+    /// ### Examples
+    ///
+    /// ```no_run
+    /// # use reactor_rt::ReactionCtx;
+    /// # let ctx: &mut ReactionCtx = unimplemented!();
+    /// let monitor = ctx.event_queue_monitor();
+    /// std::thread::spawn(move || loop {
+    ///     println!("{}", monitor.snapshot());
+    ///     std::thread::sleep(std::time::Duration::from_secs(1));
+    /// });
+    /// ```
+    pub fn event_queue_monitor(&self) -> EventQueueMonitor {
+        self.event_queue.new_monitor(&self.debug_info)
+    }
+
+    /// Returns statistics about the channel through which
+    /// physical actions send events to the scheduler, for
+    /// instance to detect an overload.
+    /// See [SchedulerOptions::async_capacity].
+    pub fn async_channel_stats(&self) -> AsyncChannelStats {
+        self.channel.stats()
+    }
+
     /// Returns the current value of a port or action at this
     /// logical time. If the value is absent, [Option::None] is
     /// returned.  This is the case if the action or port is
@@ -229,6 +403,47 @@ where
         }
         port.set_impl(value);
         self.enqueue_now(Cow::Borrowed(self.reactions_triggered_by(port.get_id())));
+        port.send_delayed(self.tag, |downstream, kind, delay, latest| {
+            self.schedule_connection(downstream, kind, delay, latest)
+        });
+    }
+
+    /// Schedule the delivery of a value sent on a connection that
+    /// is not instantaneous, and return the tag of the delivery.
+    /// The `latest` tag at which a value is in flight on the
+    /// connection is used to keep physical deliveries distinct.
+    fn schedule_connection(
+        &mut self,
+        downstream: PortId,
+        kind: ConnectionKind,
+        delay: Duration,
+        latest: Option<EventTag>,
+    ) -> EventTag {
+        let reactions = self.reactions_triggered_by(downstream);
+        match kind {
+            ConnectionKind::Logical => {
+                let eta = self.make_successor_tag(delay);
+                self.enqueue_later(reactions, eta);
+                eta
+            }
+            ConnectionKind::Physical => {
+                // Physical time is ahead of logical time, but the
+                // clock may not have ticked since the start of this tag.
+                let eta = EventTag::absolute_delayed(self.initial_time, Instant::now(), delay).max(self.tag.next_microstep());
+                // The tag must also be distinct from the previous value
+                // sent on the connection, even if the clock has not ticked since.
+                let eta = match latest {
+                    Some(latest) if eta <= latest => latest.next_microstep(),
+                    _ => eta,
+                };
+                // Route the event through the asynchronous channel, like for physical actions.
+                let evt = Event::execute(eta, Cow::Borrowed(reactions));
+                if let Err(e) = self.channel.new_sender().send_unchecked(evt) {
+                    warn!("Event could not be sent! {:?}", e);
+                }
+                eta
+            }
+        }
     }
 
     fn check_set_port_is_legal<T: Sync>(&self, port: &mut WritablePort<T>) {
@@ -307,6 +522,8 @@ where
     /// plus an optional additional time delay (see [Offset]).
     ///
     /// This is like [Self::schedule_with_v], where the value is [None].
+    /// The outcome tells whether an event was actually scheduled,
+    /// see [MinSpacing].
     ///
     /// ### Examples
     ///
@@ -320,7 +537,7 @@ where
     /// ctx.schedule(action, After(Duration::from_millis(2))); // equivalent to the previous
     /// ```
     #[inline]
-    pub fn schedule<T: Sync>(&mut self, action: &mut LogicalAction<T>, offset: Offset) {
+    pub fn schedule<T: Sync>(&mut self, action: &mut LogicalAction<T>, offset: Offset) -> ScheduleOutcome {
         self.schedule_with_v(action, None, offset)
     }
 
@@ -336,10 +553,16 @@ where
     /// The action will trigger after its own implicit time delay,
     /// plus an optional additional time delay (see [Offset]).
     ///
+    /// If the action has a [minimum spacing](MinSpacing), the
+    /// event may be deferred, dropped, or replace the value of
+    /// the previous event. The returned outcome tells which
+    /// one happened.
+    ///
     /// ### Examples
     ///
     /// ```no_run
     /// # use reactor_rt::prelude::*;
+    /// # use reactor_rt::ScheduleOutcome;
     /// # let ctx: &mut ReactionCtx = panic!();
     /// # let action: &mut LogicalAction<&'static str> = panic!();
     /// // will be executed 2 milliseconds (+ own delay) from now with that value.
@@ -348,13 +571,26 @@ where
     /// ctx.schedule_with_v(action, None, Asap);
     /// // that's equivalent to
     /// ctx.schedule(action, Asap);
+    ///
+    /// // with a min spacing, the event may not be scheduled as requested
+    /// if let ScheduleOutcome::Dropped = ctx.schedule(action, Asap) {
+    ///     println!("too many events");
+    /// }
     /// ```
     #[inline]
-    pub fn schedule_with_v<T: Sync>(&mut self, action: &mut LogicalAction<T>, value: Option<T>, offset: Offset) {
+    pub fn schedule_with_v<T: Sync>(
+        &mut self,
+        action: &mut LogicalAction<T>,
+        value: Option<T>,
+        offset: Offset,
+    ) -> ScheduleOutcome {
         let eta = self.make_successor_tag(action.0.min_delay + offset.to_duration());
-        action.0.schedule_future_value(eta, value);
-        let downstream = self.dataflow.reactions_triggered_by(&action.get_id());
-        self.enqueue_later(downstream, eta);
+        let outcome = action.0.schedule_future_value(self.get_tag(), eta, value);
+        if let ScheduleOutcome::Scheduled(tag) | ScheduleOutcome::Deferred(tag) = outcome {
+            let downstream = self.dataflow.reactions_triggered_by(&action.get_id());
+            self.enqueue_later(downstream, tag);
+        }
+        outcome
     }
 
     /// Add new reactions to execute later (at least 1 microstep later).
@@ -396,6 +632,13 @@ where
     /// least check that the scheduler has not been terminated by
     /// polling [AsyncCtx::was_terminated].
     ///
+    /// Reactions that call this must be declared with [DependencyDeclarator::declare_spawns_threads](crate::assembly::DependencyDeclarator::declare_spawns_threads),
+    /// which is checked in debug builds. Threads cannot be spawned
+    /// when the program is run with [SyncScheduler::run_main_async],
+    /// which therefore rejects programs that contain such reactions
+    /// before they start. Use a [SchedulerHandle] to schedule
+    /// physical actions from outside of those programs instead.
+    ///
     /// ### Example
     ///
     /// ```no_run
@@ -418,12 +661,22 @@ where
         F: 'x + Send,
         R: 'x + Send,
     {
-        let tx = self.rx.new_sender();
+        let tx = self.channel.new_sender();
         let dataflow = self.dataflow;
         let initial_time = self.initial_time;
         let was_terminated = self.was_terminated_atomic.clone();
+        if let Some(reaction) = self.current_reaction {
+            debug_assert!(
+                self.dataflow.thread_spawners().contains(&reaction),
+                "Reaction {} spawns a physical thread, but was not declared with declare_spawns_threads",
+                self.debug_info.display_reaction(reaction)
+            );
+        }
+        let thread_spawner = self
+            .thread_spawner
+            .expect("spawn_physical_thread is not supported with run_main_async, the reaction should be declared with declare_spawns_threads");
 
-        self.thread_spawner.spawn(move |subscope| {
+        thread_spawner.spawn(move |subscope| {
             let mut link = AsyncCtx {
                 tx,
                 dataflow,
@@ -435,6 +688,43 @@ where
         })
     }
 
+    /// Submit a job that may block, eg to do some I/O, to a pool
+    /// of threads managed by the scheduler. When the job completes,
+    /// its result is scheduled as the value of the given physical
+    /// action, like with [AsyncCtx::schedule_physical_with_v].
+    ///
+    /// The pool has at most [SchedulerOptions::blocking_threads]
+    /// threads, jobs beyond that wait for a thread to be free.
+    /// Pending jobs keep the program alive. At shutdown, they
+    /// are cancelled or awaited depending on [SchedulerOptions::blocking_shutdown],
+    /// and the results of the jobs that complete after that are
+    /// discarded.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use reactor_rt::prelude::*;
+    /// fn some_reaction(ctx: &mut ReactionCtx, file_read: &PhysicalActionRef<std::io::Result<String>>) {
+    ///     ctx.spawn_blocking(file_read, || std::fs::read_to_string("input.txt"));
+    /// }
+    /// ```
+    pub fn spawn_blocking<T, F>(&mut self, action: &PhysicalActionRef<T>, job: F)
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Sync + Send + 'static,
+    {
+        let handle = self.channel.new_handle(self.was_terminated_atomic.clone());
+        let action = action.clone();
+        self.channel.blocking_pool().submit(Box::new(move || {
+            let value = job();
+            match handle.schedule_physical_with_v(&action, Some(value), Offset::Asap) {
+                Ok(_) => {}
+                Err(TrySendError::Full(_)) => warn!("Result of blocking job was dropped, the asynchronous channel is full"),
+                Err(TrySendError::Disconnected(_)) => trace!("Result of blocking job was discarded, the scheduler has shut down"),
+            }
+        }));
+    }
+
     /// Request that the application shutdown, possibly with
     /// a particular offset. Just like for actions, even a zero
     /// offset will only trigger the special `shutdown` trigger
@@ -460,18 +750,73 @@ where
         self.insides.future_events.push(evt);
     }
 
-    /// Reschedule a periodic timer if need be.
-    /// This is called by a reaction synthesized for each timer.
-    // note: reactions can't call this as they're only passed a shared reference to a timer.
-    #[doc(hidden)]
-    #[inline]
-    pub fn reschedule_timer(&mut self, timer: &mut Timer) {
-        if timer.is_periodic() {
-            let downstream = self.reactions_triggered_by(timer.get_id());
-            self.enqueue_later(downstream, self.make_successor_tag(timer.period));
+    /// Stop the given timer. Its firings that are scheduled after
+    /// the current tag are cancelled, and it does not trigger
+    /// anymore until it is restarted with [Self::start_timer].
+    /// If the timer is triggered at the current tag, it stays
+    /// present until the end of the tag.
+    ///
+    /// ```no_run
+    /// # use reactor_rt::prelude::*;
+    /// # let ctx: &mut ReactionCtx = panic!();
+    /// # let timer: &Timer = panic!();
+    /// ctx.stop_timer(timer);
+    /// assert!(!timer.is_running());
+    /// ```
+    pub fn stop_timer(&mut self, timer: &Timer) {
+        timer.stop();
+    }
+
+    /// (Re)start the given timer so that it fires at the tag
+    /// designated by the given offset, which is computed like for
+    /// actions: it is at least one microstep after the current tag.
+    /// After that, the timer fires periodically with its
+    /// [current period](Timer::current_period). Firings that were
+    /// already scheduled for the timer are cancelled.
+    ///
+    /// ```no_run
+    /// # use reactor_rt::prelude::*;
+    /// # let ctx: &mut ReactionCtx = panic!();
+    /// # let timer: &Timer = panic!();
+    /// // fire again in 5 msec, then periodically
+    /// ctx.start_timer(timer, after!(5 ms));
+    /// ```
+    pub fn start_timer(&mut self, timer: &Timer, offset: Offset) {
+        let firing = timer.start(self.make_successor_tag(offset.to_duration()));
+        self.enqueue_timer(timer, firing);
+    }
+
+    /// Change the period of the given timer. If the timer is
+    /// running, its next firing is rescheduled to occur one new
+    /// period after the current tag, and the previously scheduled
+    /// firing is cancelled. With a period of zero, the timer keeps
+    /// its next firing, but does not repeat after that.
+    ///
+    /// ```no_run
+    /// # use reactor_rt::prelude::*;
+    /// # let ctx: &mut ReactionCtx = panic!();
+    /// # let timer: &Timer = panic!();
+    /// ctx.set_timer_period(timer, delay!(100 ms));
+    /// ```
+    pub fn set_timer_period(&mut self, timer: &Timer, period: Duration) {
+        if let Some(firing) = timer.set_period(self.tag, period) {
+            self.enqueue_timer(timer, firing);
         }
     }
 
+    fn enqueue_timer(&mut self, timer: &Timer, firing: TimerFiring) {
+        debug_assert!(firing.tag() > self.get_tag());
+        let downstream = self.reactions_triggered_by(timer.get_id());
+        self.insides.future_events.push(Event::timer(firing, downstream));
+    }
+
+    /// Formerly rescheduled a periodic timer. Timers are now
+    /// rescheduled by the scheduler when they fire, so this does
+    /// nothing. It is kept for compatibility with generated code.
+    #[doc(hidden)]
+    #[inline]
+    pub fn reschedule_timer(&mut self, _timer: &mut Timer) {}
+
     /// Schedule the first triggering of the given timer.
     /// This is called by a reaction synthesized for each timer.
     // note: reactions can't call this as they're only passed a shared references to timers.
@@ -479,12 +824,19 @@ where
     #[inline]
     pub fn bootstrap_timer(&mut self, timer: &mut Timer) {
         // we're in startup
-        let downstream = self.reactions_triggered_by(timer.get_id());
         if timer.offset.is_zero() {
-            // no offset
-            self.enqueue_now(Cow::Borrowed(downstream))
+            // no offset, the timer fires at the current tag
+            let firing = timer.start(self.tag);
+            if let Ok(next) = firing.fire() {
+                let downstream = self.reactions_triggered_by(timer.get_id());
+                self.enqueue_now(Cow::Borrowed(downstream));
+                if let Some(next) = next {
+                    self.enqueue_timer(timer, next);
+                }
+            }
         } else {
-            self.enqueue_later(downstream, self.make_successor_tag(timer.offset))
+            let firing = timer.start(self.make_successor_tag(timer.offset));
+            self.enqueue_timer(timer, firing)
         }
     }
 
@@ -497,18 +849,43 @@ where
             self.cur_level
         );
         debug_assert_eq!(reactor.id(), reaction_id.0.container(), "Wrong reactor");
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!(
+            parent: &self.tag_span,
+            "reaction",
+            tag = %self.tag,
+            level = %self.cur_level,
+            reactor = %self.debug_info.id_registry.get_debug_info(reaction_id.0.container()),
+            reaction = %reaction_id,
+            label = self.debug_info.id_registry.reaction_label(reaction_id),
+        )
+        .entered();
+        if let Some(metrics) = self.metrics {
+            metrics.record_reaction(reaction_id.0.container());
+        }
+        if let Some(debugger) = self.debugger.filter(|d| d.pauses_before(reaction_id)) {
+            debugger.pause(&PauseCtx {
+                point: PausePoint::Reaction(self.tag, reaction_id),
+                debug: &self.debug_info,
+                event_queue: self.event_queue,
+                start: self.initial_time,
+                inspect: &|inspector| reactor.inspect(inspector),
+                inspectable: Some(reaction_id.0.container()),
+            });
+        }
         self.current_reaction.replace(reaction_id);
         reactor.react(self, reaction_id.0.local());
         self.current_reaction.take();
     }
 
     pub(super) fn new(
-        rx: &'a Receiver<Event<'x>>,
+        channel: &'a AsyncChannel<'x>,
+        event_queue: &'a EventQueue<'x>,
         tag: EventTag,
         initial_time: Instant,
         todo: ReactionPlan<'x>,
         dataflow: &'x DataflowInfo,
-        thread_spawner: &'a Scope<'t>,
+        thread_spawner: Option<&'a Scope<'t>>,
         debug_info: DebugInfoProvider<'a>,
         was_terminated_atomic: &'a Arc<AtomicBool>,
         was_terminated: bool,
@@ -518,13 +895,19 @@ where
             cur_level: Default::default(),
             tag,
             current_reaction: None,
-            rx,
+            channel,
+            event_queue,
             initial_time,
             dataflow,
             thread_spawner,
             was_terminated_atomic,
             debug_info,
             was_terminated,
+            #[cfg(feature = "tracing")]
+            tag_span: tracing::Span::current(),
+            metrics: None,
+            debugger: None,
+            reaction_log: Default::default(),
         }
     }
 
@@ -537,7 +920,8 @@ where
 
             // all of that is common to all contexts
             tag: self.tag,
-            rx: self.rx,
+            channel: self.channel,
+            event_queue: self.event_queue,
             cur_level: self.cur_level,
             initial_time: self.initial_time,
             thread_spawner: self.thread_spawner,
@@ -546,6 +930,11 @@ where
             was_terminated_atomic: self.was_terminated_atomic,
             debug_info: self.debug_info.clone(),
             current_reaction: self.current_reaction,
+            #[cfg(feature = "tracing")]
+            tag_span: self.tag_span.clone(),
+            metrics: self.metrics,
+            debugger: self.debugger,
+            reaction_log: self.reaction_log,
         }
     }
 }
@@ -589,7 +978,7 @@ impl RContextForwardableStuff<'_> {
 ///
 #[derive(Clone)]
 pub struct AsyncCtx<'a, 'x, 't> {
-    tx: Sender<Event<'x>>,
+    tx: AsyncSender<'x>,
     initial_time: Instant,
     dataflow: &'x DataflowInfo,
     /// Whether the scheduler has been terminated.
@@ -606,6 +995,13 @@ impl AsyncCtx<'_, '_, '_> {
         self.was_terminated.load(Ordering::SeqCst)
     }
 
+    /// Returns statistics about the channel through which
+    /// this context sends events to the scheduler.
+    /// See [ReactionCtx::async_channel_stats].
+    pub fn async_channel_stats(&self) -> AsyncChannelStats {
+        self.tx.state().stats()
+    }
+
     /// Request that the application shutdown, possibly with
     /// a particular offset from the current physical time.
     ///
@@ -616,14 +1012,16 @@ impl AsyncCtx<'_, '_, '_> {
     /// or its shutdown might be programmed for a logical
     /// time which precedes the current physical time.
     pub fn request_stop(&mut self, offset: Offset) -> Result<(), SendError<()>> {
-        // physical time must be ahead of logical time so
-        // this event is scheduled for the future
-        let tag = EventTag::absolute(self.initial_time, Instant::now() + offset.to_duration());
-
-        let evt = Event::terminate_at(tag);
-        self.tx.send(evt).map_err(|e| {
-            warn!("Event could not be sent! {:?}", e);
-            SendError(())
+        let now = Instant::now();
+        self.tx.state().with_latest_started(|latest_started| {
+            let tag = physical_tag(Some(self.initial_time), now, offset, latest_started);
+
+            let evt = Event::terminate_at(tag);
+            // this one is important enough to ignore the capacity of the channel
+            self.tx.send_unchecked(evt).map_err(|e| {
+                warn!("Event could not be sent! {:?}", e);
+                SendError(())
+            })
         })
     }
 
@@ -644,7 +1042,7 @@ impl AsyncCtx<'_, '_, '_> {
         &mut self,
         action: &PhysicalActionRef<T>,
         offset: Offset,
-    ) -> Result<(), SendError<Option<T>>> {
+    ) -> Result<ScheduleOutcome, TrySendError<Option<T>>> {
         self.schedule_physical_with_v(action, None, offset)
     }
 
@@ -661,34 +1059,128 @@ impl AsyncCtx<'_, '_, '_> {
     /// or its shutdown might be programmed for a logical
     /// time which precedes the current physical time.
     ///
+    /// If the action has a [minimum spacing](MinSpacing),
+    /// the Ok outcome tells whether the event was deferred,
+    /// dropped, or replaced the value of the previous event.
+    ///
+    /// Each new event gets a distinct tag, which is later than the
+    /// tag the scheduler is processing. If the clock has not ticked
+    /// since the previous event of the action, or if logical time
+    /// is ahead of physical time, the tag is moved to the next
+    /// microstep.
+    ///
+    /// If the asynchronous channel is full (see [SchedulerOptions::async_capacity]),
+    /// this blocks, fails with [TrySendError::Full], or replaces
+    /// the value of a pending event of the same action, depending
+    /// on the [OverflowPolicy].
+    ///
     pub fn schedule_physical_with_v<T: Sync>(
         &mut self,
         action: &PhysicalActionRef<T>,
         value: Option<T>,
         offset: Offset,
-    ) -> Result<(), SendError<Option<T>>> {
-        // physical time must be ahead of logical time so
-        // this event is scheduled for the future
-        action
-            .use_mut_p(value, |action, value| {
-                let tag = EventTag::absolute(self.initial_time, Instant::now() + offset.to_duration());
-                action.0.schedule_future_value(tag, value);
-
-                let downstream = self.dataflow.reactions_triggered_by(&action.get_id());
+    ) -> Result<ScheduleOutcome, TrySendError<Option<T>>> {
+        let downstream = self.dataflow.reactions_triggered_by(&action.get_id());
+        schedule_physical_impl(
+            self.tx.state(),
+            Some(self.initial_time),
+            &self.was_terminated,
+            action,
+            value,
+            offset,
+            |tag| {
                 let evt = Event::execute(tag, Cow::Borrowed(downstream));
-                self.tx.send(evt).map_err(|e| {
-                    warn!("Event could not be sent! {:?}", e);
-                    SendError(action.0.forget_value(&tag))
-                })
-            })
-            .unwrap_or_else(|value| Err(SendError(value)))
+                self.tx
+                    .send_reserved(evt)
+                    .map_err(|e| warn!("Event could not be sent! {:?}", e))
+            },
+        )
+    }
+}
+
+/// Compute the tag of an asynchronous event scheduled at the
+/// given physical time. If the scheduler has not started yet
+/// (`initial_time` is None), the offset is taken from T0.
+pub(super) fn physical_tag(initial_time: Option<Instant>, now: Instant, offset: Offset, latest_started: EventTag) -> EventTag {
+    let tag = match initial_time {
+        Some(t0) => EventTag::absolute_delayed(t0, now, offset.to_duration()),
+        None => EventTag::ORIGIN.successor(offset.to_duration()),
+    };
+    // physical time is usually ahead of logical time, but
+    // not always, eg with SchedulerOptions::fast
+    tag.max(latest_started.next_microstep())
+}
+
+/// Schedule a physical action from outside the scheduler, see
+/// [AsyncCtx::schedule_physical_with_v]. The `send` function
+/// sends the event with the given tag, for which room has been
+/// reserved in the channel.
+pub(super) fn schedule_physical_impl<T: Sync>(
+    channel: &ChannelState,
+    initial_time: Option<Instant>,
+    was_terminated: &AtomicBool,
+    action: &PhysicalActionRef<T>,
+    value: Option<T>,
+    offset: Offset,
+    send: impl FnOnce(EventTag) -> Result<(), ()>,
+) -> Result<ScheduleOutcome, TrySendError<Option<T>>> {
+    let mut value = value;
+    // Reserve room in the channel before locking the action:
+    // reactions that use the action would otherwise block
+    // the scheduler while we wait for it.
+    if !channel.try_reserve() {
+        if channel.policy() == OverflowPolicy::Coalesce {
+            let latest_started = channel.latest_started();
+            match action.use_mut_p(value, |action, value| action.0.replace_pending_value(latest_started, value)) {
+                Ok(Ok(tag)) => {
+                    channel.record_coalesced();
+                    return Ok(ScheduleOutcome::Replaced(tag));
+                }
+                Ok(Err(v)) | Err(v) => value = v,
+            }
+        }
+        if channel.policy() == OverflowPolicy::Fail {
+            channel.record_rejected();
+            return Err(TrySendError::Full(value));
+        } else if !channel.reserve_blocking(was_terminated) {
+            return Err(TrySendError::Disconnected(value));
+        }
     }
+
+    let now = Instant::now();
+    action
+        .use_mut_p(value, |action, value| {
+            channel.with_latest_started(|latest_started| {
+                // The tag must also be distinct from the pending events
+                // of this action, even if the clock has not ticked since.
+                let tag = physical_tag(initial_time, now, offset, latest_started);
+                let tag = action.0.first_free_tag(tag);
+
+                let outcome = action.0.schedule_future_value(latest_started, tag, value);
+                let tag = match outcome {
+                    ScheduleOutcome::Scheduled(tag) | ScheduleOutcome::Deferred(tag) => tag,
+                    _ => {
+                        channel.cancel_reservation();
+                        return Ok(outcome);
+                    }
+                };
+
+                send(tag)
+                    .map(|_| outcome)
+                    .map_err(|_| TrySendError::Disconnected(action.0.forget_value(&tag)))
+            })
+        })
+        .unwrap_or_else(|value| {
+            channel.cancel_reservation();
+            Err(TrySendError::Disconnected(value))
+        })
 }
 
 /// An offset from the current event.
 ///
 /// This is to be used with [ReactionCtx::schedule].
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Offset {
     /// Specify that the trigger will fire at least after
     /// the provided duration.
@@ -757,12 +1249,12 @@ impl CleanupCtx {
     pub fn cleanup_multiport<T: Sync>(&self, port: &mut PortBank<T>) {
         // todo bound ports don't need to be cleared
         for channel in port {
-            channel.clear_value()
+            channel.clear_value(&self.tag)
         }
     }
 
     pub fn cleanup_port<T: Sync>(&self, port: &mut Port<T>) {
-        port.clear_value()
+        port.clear_value(&self.tag)
     }
 
     pub fn cleanup_logical_action<T: Sync>(&self, action: &mut LogicalAction<T>) {
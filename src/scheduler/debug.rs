@@ -23,6 +23,7 @@
  */
 
 use core::any::type_name;
+use std::any::Any;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter, Result};
@@ -32,13 +33,20 @@ use index_vec::{Idx, IndexVec};
 
 use crate::assembly::{ReactorInitializer, TriggerId};
 use crate::vecmap::VecMap;
-use crate::{GlobalReactionId, ReactorId};
+use crate::{GlobalReactionId, LocalReactionId, PhysicalActionRef, ReactorId, TriggerInspector};
+
+/// Reports the state of a single component to the debugger,
+/// see [DebugInfoRegistry::record_probe].
+type Probe = Box<dyn Fn(&mut TriggerInspector) + Send + Sync>;
 
 /// Maps IDs to debug information, stores all the debug info.
 /// This is built during assembly.
-/// At runtime, this is only used to format debug messages and
+/// At runtime, this is mostly used to format debug messages and
 /// perform debug assertions, so compactness is more important
-/// than speed of the methods.
+/// than speed of the methods. It also lets code outside of the
+/// program find its physical actions, and the debugger
+/// inspect components of reactors that do not implement
+/// [ReactorBehavior::inspect](crate::ReactorBehavior::inspect).
 pub(crate) struct DebugInfoRegistry {
     /// Maps reactor ids to their debug info.
     reactor_infos: IndexVec<ReactorId, ReactorDebugInfo>,
@@ -60,6 +68,17 @@ pub(crate) struct DebugInfoRegistry {
     // todo better data structure, eg IndexVec<ReactorId, IndexVec<LocalReactionId, _>>
     /// Labels of each reaction, only reactions that have one are in here.
     reaction_labels: HashMap<GlobalReactionId, Cow<'static, str>>,
+
+    /// Number of reactions of each reactor.
+    num_reactions: VecMap<ReactorId, usize>,
+
+    /// The [PhysicalActionRef]s of the program, with their
+    /// value type erased.
+    physical_actions: HashMap<TriggerId, Box<dyn Any + Send + Sync>>,
+
+    /// Probes of the components that the runtime can inspect
+    /// on its own, ie ports, physical actions and timers.
+    probes: HashMap<TriggerId, Probe>,
 }
 
 /// The reactor ID, and the local index within the reactor.
@@ -76,6 +95,9 @@ impl DebugInfoRegistry {
             trigger_infos: Default::default(),
             reaction_labels: Default::default(),
             reactor_container: Default::default(),
+            num_reactions: Default::default(),
+            physical_actions: Default::default(),
+            probes: Default::default(),
             main_reactor: None,
         };
 
@@ -137,6 +159,48 @@ impl DebugInfoRegistry {
         self.fmt_component_path(self.raw_id_of_trigger(id), Some(&self.trigger_infos[id]), false)
     }
 
+    /// Format the id of a component, returns None if the
+    /// component has not been fully recorded yet. This may
+    /// occur if assembly was aborted.
+    pub fn try_fmt_component(&self, id: TriggerId) -> Option<String> {
+        match id {
+            TriggerId::STARTUP | TriggerId::SHUTDOWN if self.reactor_bound.is_empty() => None,
+            TriggerId::STARTUP | TriggerId::SHUTDOWN => Some(self.fmt_component(id).to_string()),
+            id if id.index() < self.trigger_infos.len() && matches!(self.reactor_bound.last(), Some(&max) if id < max) => {
+                Some(self.fmt_component(id).to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Format the id of a reaction, returns None if its
+    /// reactor has not been recorded.
+    pub fn try_fmt_reaction(&self, id: GlobalReactionId) -> Option<String> {
+        if id.0.container().index() < self.reactor_infos.len() {
+            Some(self.fmt_reaction(id).to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the label of a trigger, ie the last segment of its path.
+    pub fn trigger_label(&self, id: TriggerId) -> &str {
+        &self.trigger_infos[id]
+    }
+
+    /// Returns the label of a reaction, if it has one.
+    pub fn reaction_label(&self, id: GlobalReactionId) -> Option<&str> {
+        self.reaction_labels.get(&id).map(Cow::as_ref)
+    }
+
+    /// Iterates over all recorded reactors, along with
+    /// the id of their container.
+    pub fn reactors(&self) -> impl Iterator<Item = (ReactorId, Option<ReactorId>)> + '_ {
+        self.reactor_infos
+            .indices()
+            .map(move |id| (id, self.reactor_container.get(&id).cloned()))
+    }
+
     #[inline]
     pub fn get_container(&self, id: ReactorId) -> Option<ReactorId> {
         let container = self.reactor_container.get(&id);
@@ -196,11 +260,42 @@ impl DebugInfoRegistry {
         debug_assert_eq!(ix, id);
     }
 
+    pub(crate) fn record_physical_action<T: Sync + Send + 'static>(&mut self, id: TriggerId, action: PhysicalActionRef<T>) {
+        self.physical_actions.insert(id, Box::new(action));
+    }
+
+    /// Returns the physical action with the given id, None if
+    /// there is none, or if its values are not of type T.
+    pub(crate) fn get_physical_action<T: Sync + Send + 'static>(&self, id: TriggerId) -> Option<PhysicalActionRef<T>> {
+        self.physical_actions
+            .get(&id)?
+            .downcast_ref::<PhysicalActionRef<T>>()
+            .cloned()
+    }
+
+    /// Record how the debugger inspects the given component,
+    /// replacing the previous probe of the component if any.
+    pub(crate) fn record_probe(&mut self, id: TriggerId, probe: impl Fn(&mut TriggerInspector) + Send + Sync + 'static) {
+        self.probes.insert(id, Box::new(probe));
+    }
+
+    /// Pass the components that have a probe, and that are
+    /// accepted by the filter, to the inspector.
+    pub(crate) fn probe(&self, inspector: &mut TriggerInspector, filter: impl Fn(TriggerId) -> bool) {
+        for (_, probe) in self.probes.iter().filter(|(id, _)| filter(**id)) {
+            probe(inspector);
+        }
+    }
+
     pub(crate) fn record_reaction(&mut self, id: GlobalReactionId, name: Cow<'static, str>) {
         let existing = self.reaction_labels.insert(id, name);
         debug_assert!(existing.is_none())
     }
 
+    pub(crate) fn record_num_reactions(&mut self, id: ReactorId, num_reactions: usize) {
+        self.num_reactions.insert(id, num_reactions);
+    }
+
     pub(crate) fn record_reactor(&mut self, id: ReactorId, debug: ReactorDebugInfo) {
         let ix = self.reactor_infos.push(debug);
         debug_assert_eq!(ix, id);
@@ -225,6 +320,131 @@ impl DebugInfoRegistry {
     }
 }
 
+/// Identifies a reactor, a trigger component (port, action, timer),
+/// or a reaction of the program. See [ReactionCtx::lookup](crate::ReactionCtx::lookup)
+/// and [SchedulerHandle::lookup](crate::SchedulerHandle::lookup).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ElementId {
+    Reactor(ReactorId),
+    Trigger(TriggerId),
+    Reaction(GlobalReactionId),
+}
+
+impl DebugInfoRegistry {
+    /// Returns all elements whose path matches the given pattern,
+    /// in order of reactor, then triggers before reactions.
+    ///
+    /// Paths are relative to the main reactor, segments are
+    /// separated by `/` or `.`, like `child[3]/port` or
+    /// `child[3].port`. A leading separator is ignored, and the
+    /// empty path designates the main reactor. Bank members and
+    /// multiport channels carry their index in the segment, like
+    /// `child[3]` and `port[1]`. A reaction segment matches
+    /// either the index of the reaction within its reactor, or its label.
+    ///
+    /// In a pattern, `*` matches any sequence of characters within
+    /// a segment, eg `child[*]` matches every member of the bank
+    /// `child`. A segment `**` matches any number of segments.
+    pub fn lookup(&self, pattern: &str) -> Vec<ElementId> {
+        let pattern: Vec<&str> = split_path(pattern).collect();
+        let mut result = Vec::new();
+        for (rid, info) in self.reactor_infos.iter_enumerated() {
+            let reactor_path: Vec<&str> = split_path(&info.inst_path).collect();
+            if match_path(&pattern, &reactor_path, None) {
+                result.push(ElementId::Reactor(rid));
+            }
+            // The reactor path is a prefix of the paths of its
+            // elements, skip them all if it cannot match.
+            if !match_prefix(&pattern, &reactor_path) {
+                continue;
+            }
+            if let Some(bound) = self.reactor_bound.get(rid) {
+                let first = self.get_reactor_lower_bound(rid);
+                for trigger in TriggerId::iter_range(&(first..*bound)) {
+                    let label: &str = &self.trigger_infos[trigger];
+                    if match_path(&pattern, &reactor_path, Some(&|p| glob(p, label))) {
+                        result.push(ElementId::Trigger(trigger));
+                    }
+                }
+            }
+            let num_reactions = self.num_reactions.get(&rid).copied().unwrap_or(0);
+            for local in 0..num_reactions {
+                let id = GlobalReactionId::new(rid, LocalReactionId::from_usize(local));
+                let label = self.reaction_labels.get(&id);
+                let matches = |p: &str| match label {
+                    Some(l) => glob(p, &local.to_string()) || glob(p, l) || glob(p, &format!("{}@{}", local, l)),
+                    None => glob(p, &local.to_string()),
+                };
+                if match_path(&pattern, &reactor_path, Some(&matches)) {
+                    result.push(ElementId::Reaction(id));
+                }
+            }
+        }
+        result
+    }
+
+    /// Formats the path of an element, as it is accepted by [Self::lookup].
+    pub fn fmt_element(&self, id: ElementId) -> String {
+        match id {
+            ElementId::Reactor(id) => self.get_debug_info(id).to_string(),
+            ElementId::Trigger(id) => self.fmt_component(id).to_string(),
+            ElementId::Reaction(id) => self.fmt_reaction(id).to_string(),
+        }
+    }
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split(['/', '.']).filter(|s| !s.is_empty())
+}
+
+/// Whether the path can be the prefix of a path matched by the pattern.
+fn match_prefix(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (_, None) => true,
+        (None, Some(_)) => false,
+        (Some((&"**", _)), Some(_)) => true,
+        (Some((p, pattern)), Some((s, path))) => glob(p, s) && match_prefix(pattern, path),
+    }
+}
+
+/// Matches a path against a pattern, which may contain `**`
+/// segments. If `last` is present, the path has an additional
+/// last segment, which is matched by this function.
+fn match_path(pattern: &[&str], path: &[&str], last: Option<&dyn Fn(&str) -> bool>) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty() && last.is_none(),
+        Some((&"**", rest)) => {
+            match_path(rest, path, last)
+                || match (path.split_first(), last) {
+                    (Some((_, tail)), _) => match_path(pattern, tail, last),
+                    (None, Some(_)) => match_path(pattern, path, None),
+                    (None, None) => false,
+                }
+        }
+        Some((p, rest)) => match (path.split_first(), last) {
+            (Some((s, tail)), _) => glob(p, s) && match_path(rest, tail, last),
+            (None, Some(last)) => last(p) && match_path(rest, path, None),
+            (None, None) => false,
+        },
+    }
+}
+
+/// Matches a single segment against a pattern, where `*`
+/// matches any sequence of characters.
+fn glob(pattern: &str, text: &str) -> bool {
+    match pattern.find('*') {
+        None => pattern == text,
+        Some(star) => {
+            let (prefix, rest) = (&pattern[..star], &pattern[star + 1..]);
+            text.starts_with(prefix)
+                && (prefix.len()..=text.len())
+                    .filter(|&i| text.is_char_boundary(i))
+                    .any(|i| glob(rest, &text[i..]))
+        }
+    }
+}
+
 /// Debug information for a single reactor.
 pub(crate) struct ReactorDebugInfo {
     /// Type name
@@ -287,8 +507,10 @@ impl Display for ReactorDebugInfo {
 
 #[cfg(test)]
 pub mod test {
+    use index_vec::Idx;
+
     use crate::assembly::TriggerId;
-    use crate::{DebugInfoRegistry, ReactorDebugInfo, ReactorId};
+    use crate::{DebugInfoRegistry, ElementId, GlobalReactionId, LocalReactionId, ReactorDebugInfo, ReactorId};
 
     #[test]
     fn test_raw_id_from_trigger() -> Result<(), ()> {
@@ -319,4 +541,60 @@ pub mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_lookup_paths() -> Result<(), ()> {
+        let mut debug = DebugInfoRegistry::new();
+        let mut trigger_id = TriggerId::FIRST_REGULAR;
+        // bank members are assembled before their container
+        for (i, name) in ["/child[0]", "/child[1]"].iter().enumerate() {
+            let reactor = ReactorId::from_usize(i);
+            let first_trigger = trigger_id;
+            debug.record_reactor(reactor, ReactorDebugInfo::test_named(*name));
+            debug.record_trigger(trigger_id.get_and_incr()?, "in".into());
+            debug.record_trigger(trigger_id.get_and_incr()?, "out".into());
+            debug.set_id_range(reactor, first_trigger..trigger_id);
+            debug.record_num_reactions(reactor, 2);
+            debug.record_reaction(GlobalReactionId::new(reactor, LocalReactionId::new(1)), "on_in".into());
+        }
+        let main = ReactorId::new(2);
+        let first_trigger = trigger_id;
+        debug.record_reactor(main, ReactorDebugInfo::test_named(""));
+        debug.record_trigger(trigger_id.get_and_incr()?, "out".into());
+        debug.set_id_range(main, first_trigger..trigger_id);
+        debug.record_main_reactor(main);
+
+        let reaction = |r, l| ElementId::Reaction(GlobalReactionId::new(ReactorId::new(r), LocalReactionId::new(l)));
+        let trigger = |i| ElementId::Trigger(TriggerId::new(i));
+
+        assert_eq!(debug.lookup(""), vec![ElementId::Reactor(main)]);
+        assert_eq!(debug.lookup("/child[1]"), vec![ElementId::Reactor(ReactorId::new(1))]);
+        assert_eq!(debug.lookup("child[1].in"), vec![trigger(4)]);
+        assert_eq!(debug.lookup("child[*]/out"), vec![trigger(3), trigger(5)]);
+        assert_eq!(debug.lookup("out"), vec![trigger(6)]);
+        assert_eq!(debug.lookup("**/out"), vec![trigger(3), trigger(5), trigger(6)]);
+        assert_eq!(debug.lookup("child[0]/on_in"), vec![reaction(0, 1)]);
+        assert_eq!(debug.lookup("child[0]/0"), vec![reaction(0, 0)]);
+        assert_eq!(
+            debug.lookup("child[*]/*"),
+            vec![
+                trigger(2),
+                trigger(3),
+                reaction(0, 0),
+                reaction(0, 1),
+                trigger(4),
+                trigger(5),
+                reaction(1, 0),
+                reaction(1, 1)
+            ]
+        );
+        assert_eq!(debug.lookup("nothing/*"), vec![]);
+
+        // formatted paths can be looked up
+        for id in debug.lookup("**") {
+            assert_eq!(debug.lookup(&debug.fmt_element(id)), vec![id]);
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,694 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+//! An interactive debugger, see [SchedulerOptions::debugger](crate::SchedulerOptions::debugger).
+
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use super::*;
+use crate::assembly::{TriggerId, TriggerLike};
+use crate::{try_parse_duration, EventTag, ReactionTrigger};
+
+/// Where the debugger reads commands from, and writes its
+/// output to. See [SchedulerOptions::debugger](crate::SchedulerOptions::debugger).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum DebuggerInput {
+    /// Read commands from stdin, and write to stderr.
+    Stdin,
+    /// Listen on this address, and talk with the first client
+    /// that connects, eg with `nc localhost 9000`. The program
+    /// waits for a client the first time it is paused.
+    Socket(SocketAddr),
+}
+
+impl FromStr for DebuggerInput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use std::net::ToSocketAddrs;
+
+        if s == "stdin" {
+            return Ok(DebuggerInput::Stdin);
+        }
+        let mut addrs = s
+            .to_socket_addrs()
+            .map_err(|e| format!("expected 'stdin' or an address, got '{}': {}", s, e))?;
+        addrs
+            .next()
+            .map(DebuggerInput::Socket)
+            .ok_or_else(|| format!("cannot resolve '{}'", s))
+    }
+}
+
+impl Display for DebuggerInput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DebuggerInput::Stdin => write!(f, "stdin"),
+            DebuggerInput::Socket(addr) => write!(f, "{}", addr),
+        }
+    }
+}
+
+/// A point where the [debugger](crate::SchedulerOptions::debugger)
+/// pauses the program.
+///
+/// A breakpoint is parsed from a tag like `T0 + 10 ms` or
+/// `(T0 + 10 ms, 1)`, or otherwise from a pattern for the
+/// paths of reactions, like `child[*]/on_tick`, in the syntax
+/// of [ReactionCtx::lookup](crate::ReactionCtx::lookup).
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Breakpoint {
+    /// Pause before executing any of the reactions matched
+    /// by this pattern.
+    Reaction(String),
+    /// Pause at the first tag that is not earlier than this one.
+    Tag(EventTag),
+}
+
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let tag = s.strip_prefix('(').and_then(|s| s.strip_suffix(')')).unwrap_or(s);
+        let Some(tag) = tag.strip_prefix("T0") else {
+            return Ok(Breakpoint::Reaction(s.to_owned()));
+        };
+        let (offset, microstep) = match tag.split_once(',') {
+            Some((offset, microstep)) => {
+                let microstep = microstep
+                    .trim()
+                    .parse()
+                    .map_err(|e| format!("invalid microstep in '{}': {}", s, e))?;
+                (offset, microstep)
+            }
+            None => (tag, 0),
+        };
+        let offset = match offset.trim() {
+            "" => Duration::ZERO,
+            offset => match offset.strip_prefix('+') {
+                Some(offset) => try_parse_duration(offset.trim()).map_err(|e| format!("invalid tag '{}': {}", s, e))?,
+                None => return Err(format!("invalid tag '{}', expected eg 'T0 + 10 ms'", s)),
+            },
+        };
+        Ok(Breakpoint::Tag(EventTag::offset(offset, microstep)))
+    }
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Breakpoint::Reaction(pattern) => write!(f, "{}", pattern),
+            Breakpoint::Tag(tag) => write!(f, "{}", tag),
+        }
+    }
+}
+
+/// Collects the state of the ports, actions and timers of
+/// reactors, for the debugger. See [ReactorBehavior::inspect](crate::ReactorBehavior::inspect).
+pub struct TriggerInspector {
+    tag: EventTag,
+    start: Instant,
+    /// If set, the other components are ignored.
+    target: Option<TriggerId>,
+    states: Vec<TriggerState>,
+}
+
+struct TriggerState {
+    id: TriggerId,
+    present: bool,
+    /// None if the values cannot be formatted.
+    value: Option<String>,
+}
+
+impl TriggerInspector {
+    fn new(tag: EventTag, start: Instant, target: Option<TriggerId>) -> Self {
+        Self { tag, start, target, states: Vec::new() }
+    }
+
+    /// Record whether the component is present, and its
+    /// value, which is formatted with [Debug].
+    pub fn value<T: Debug>(&mut self, trigger: &(impl ReactionTrigger<T> + TriggerLike)) -> &mut Self {
+        self.record(trigger, |v| {
+            Some(v.map_or_else(|| "absent".to_owned(), |v| format!("{:?}", v)))
+        })
+    }
+
+    /// Record whether the component is present, for components
+    /// whose values cannot be formatted.
+    pub fn presence<T>(&mut self, trigger: &(impl ReactionTrigger<T> + TriggerLike)) -> &mut Self {
+        self.record(trigger, |_| None)
+    }
+
+    fn record<T>(
+        &mut self,
+        trigger: &(impl ReactionTrigger<T> + TriggerLike),
+        fmt: impl FnOnce(Option<&T>) -> Option<String>,
+    ) -> &mut Self {
+        let id = trigger.get_id();
+        if self.target.is_none_or(|target| target == id) {
+            let present = trigger.is_present(&self.tag, &self.start);
+            let value = trigger.use_value_ref(&self.tag, &self.start, fmt);
+            self.states.push(TriggerState { id, present, value });
+        }
+        self
+    }
+}
+
+/// Where the program is paused.
+pub(super) enum PausePoint {
+    /// Before executing the reactions of a tag.
+    Tag(EventTag),
+    /// Before executing a reaction.
+    Reaction(EventTag, GlobalReactionId),
+}
+
+impl PausePoint {
+    fn tag(&self) -> EventTag {
+        match *self {
+            PausePoint::Tag(tag) | PausePoint::Reaction(tag, _) => tag,
+        }
+    }
+}
+
+/// What the debugger can look at while the program is paused.
+pub(super) struct PauseCtx<'a, 'x> {
+    pub point: PausePoint,
+    pub debug: &'a DebugInfoProvider<'a>,
+    pub event_queue: &'a EventQueue<'x>,
+    pub start: Instant,
+    /// Calls [ReactorBehavior::inspect] on the reactors
+    /// that can be inspected.
+    pub inspect: &'a dyn Fn(&mut TriggerInspector),
+    /// If set, only the components of this reactor can be
+    /// inspected, as the others are not accessible.
+    pub inspectable: Option<ReactorId>,
+}
+
+impl PauseCtx<'_, '_> {
+    /// Returns the state of the components that can be inspected,
+    /// sorted by id. Those that are not reported by their reactor
+    /// are inspected with the probes recorded during assembly.
+    fn inspect(&self, target: Option<TriggerId>) -> Vec<TriggerState> {
+        let mut inspector = TriggerInspector::new(self.point.tag(), self.start, target);
+        (self.inspect)(&mut inspector);
+        let registry = self.debug.id_registry;
+        registry.probe(&mut inspector, |id| {
+            target.is_none_or(|target| target == id)
+                && self
+                    .inspectable
+                    .is_none_or(|reactor| registry.get_trigger_container(id) == Some(reactor))
+        });
+        let mut states = inspector.states;
+        // keeps what the reactor reported over the probe
+        states.sort_by_key(|s| s.id);
+        states.dedup_by_key(|s| s.id);
+        states
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Mode {
+    /// Run until a breakpoint.
+    Continue,
+    /// Pause before the next reaction.
+    StepReaction,
+    /// Pause at the next tag.
+    StepTag,
+    /// Never pause again, as the client has gone.
+    Detached,
+}
+
+enum Connection {
+    Listening(TcpListener),
+    Connected {
+        input: Box<dyn BufRead + Send>,
+        output: Box<dyn Write + Send>,
+    },
+}
+
+#[derive(Default)]
+struct Breakpoints {
+    reactions: HashSet<GlobalReactionId>,
+    /// Tag breakpoints that have not been reached yet.
+    tags: Vec<EventTag>,
+}
+
+impl Breakpoints {
+    fn add(&mut self, breakpoint: &Breakpoint, debug: &DebugInfoRegistry) -> Result<(), String> {
+        match breakpoint {
+            Breakpoint::Tag(tag) => self.tags.push(*tag),
+            Breakpoint::Reaction(pattern) => {
+                let reactions: Vec<_> = debug
+                    .lookup(pattern)
+                    .into_iter()
+                    .filter_map(|id| match id {
+                        ElementId::Reaction(id) => Some(id),
+                        _ => None,
+                    })
+                    .collect();
+                if reactions.is_empty() {
+                    return Err(format!("Breakpoint '{}' does not match any reaction", pattern));
+                }
+                self.reactions.extend(reactions);
+            }
+        }
+        Ok(())
+    }
+}
+
+struct DebuggerState {
+    mode: Mode,
+    breakpoints: Breakpoints,
+    connection: Connection,
+}
+
+/// Pauses the program at breakpoints, and executes the
+/// commands of the user. The state is behind a mutex, as
+/// the debugger is shared with reaction contexts.
+pub(super) struct Debugger {
+    state: Mutex<DebuggerState>,
+}
+
+const HELP: &str = "\
+Commands:
+  continue, c      run until the next breakpoint
+  step, s          run until the next reaction
+  next, n          run until the next tag
+  queue, q         print the pending events
+  print, p <path>  print the value of ports and actions, eg 'p child/out'
+  present          list the ports, actions and timers that are present
+  break, b <spec>  pause at a tag like 'T0 + 1 s', or before reactions like 'child/on_tick'
+  help, h          print this message
+";
+
+impl Debugger {
+    /// Create a debugger, which pauses at the first tag if
+    /// there are no breakpoints.
+    pub(super) fn new(input: DebuggerInput, breakpoints: &[Breakpoint], debug: &DebugInfoRegistry) -> io::Result<Self> {
+        let connection = match input {
+            DebuggerInput::Stdin => Connection::Connected {
+                input: Box::new(BufReader::new(io::stdin())),
+                output: Box::new(io::stderr()),
+            },
+            DebuggerInput::Socket(addr) => Connection::Listening(TcpListener::bind(addr)?),
+        };
+        Ok(Self::with_connection(connection, breakpoints, debug))
+    }
+
+    fn with_connection(connection: Connection, breakpoints: &[Breakpoint], debug: &DebugInfoRegistry) -> Self {
+        let mut state = DebuggerState {
+            mode: if breakpoints.is_empty() {
+                Mode::StepTag
+            } else {
+                Mode::Continue
+            },
+            breakpoints: Default::default(),
+            connection,
+        };
+        for breakpoint in breakpoints {
+            if let Err(e) = state.breakpoints.add(breakpoint, debug) {
+                warn!("{}", e);
+            }
+        }
+        Self { state: Mutex::new(state) }
+    }
+
+    /// Whether to pause before processing the given tag.
+    pub(super) fn pauses_at_tag(&self, tag: EventTag) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let tags = &mut state.breakpoints.tags;
+        let reached = tags.iter().any(|bp| *bp <= tag);
+        tags.retain(|bp| *bp > tag);
+        match state.mode {
+            Mode::StepTag => true,
+            Mode::Detached => false,
+            _ => reached,
+        }
+    }
+
+    /// Whether to pause before executing the given reaction.
+    pub(super) fn pauses_before(&self, reaction: GlobalReactionId) -> bool {
+        let state = self.state.lock().unwrap();
+        match state.mode {
+            Mode::StepReaction => true,
+            Mode::Detached => false,
+            _ => state.breakpoints.reactions.contains(&reaction),
+        }
+    }
+
+    /// Pause the program, and execute commands until one of
+    /// them resumes execution.
+    pub(super) fn pause(&self, ctx: &PauseCtx) {
+        let mut state = self.state.lock().unwrap();
+        match state.pause(ctx) {
+            Ok(mode) => state.mode = mode,
+            Err(e) => {
+                // nobody can resume execution anymore
+                warn!("Debugger disconnected ({}), resuming execution", e);
+                state.mode = Mode::Detached;
+            }
+        }
+    }
+}
+
+impl DebuggerState {
+    /// Execute commands until one of them resumes execution,
+    /// and return the new mode.
+    fn pause(&mut self, ctx: &PauseCtx) -> io::Result<Mode> {
+        if let Connection::Listening(listener) = &self.connection {
+            info!("Waiting for a debugger to connect on {}", listener.local_addr()?);
+            let (stream, _) = listener.accept()?;
+            self.connection = Connection::Connected {
+                input: Box::new(BufReader::new(stream.try_clone()?)),
+                output: Box::new(stream),
+            };
+        }
+        let Connection::Connected { input, output } = &mut self.connection else {
+            unreachable!("connected above")
+        };
+
+        match ctx.point {
+            PausePoint::Tag(tag) => writeln!(output, "Paused at {}", tag)?,
+            PausePoint::Reaction(tag, reaction) => writeln!(
+                output,
+                "Paused at {} before reaction {}",
+                tag,
+                ctx.debug.display_reaction(reaction)
+            )?,
+        }
+
+        loop {
+            write!(output, "(debug) ")?;
+            output.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let line = line.trim();
+            let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+            let arg = arg.trim();
+            match command {
+                "continue" | "c" => return Ok(Mode::Continue),
+                "step" | "s" => return Ok(Mode::StepReaction),
+                "next" | "n" => return Ok(Mode::StepTag),
+                "queue" | "q" => write!(output, "{}", ctx.event_queue.snapshot(ctx.debug))?,
+                "print" | "p" => print_triggers(output, ctx, arg)?,
+                "present" => print_present(output, ctx)?,
+                "break" | "b" => {
+                    let added = arg
+                        .parse()
+                        .and_then(|bp| self.breakpoints.add(&bp, ctx.debug.id_registry).map(|_| bp));
+                    match added {
+                        Ok(bp) => writeln!(output, "Added breakpoint {}", bp)?,
+                        Err(e) => writeln!(output, "{}", e)?,
+                    }
+                }
+                "help" | "h" => write!(output, "{}", HELP)?,
+                "" => {}
+                _ => writeln!(output, "Unknown command '{}', type 'help' for a list of commands", command)?,
+            }
+        }
+    }
+}
+
+/// Print the value of the ports and actions matched by the pattern.
+fn print_triggers(output: &mut dyn Write, ctx: &PauseCtx, pattern: &str) -> io::Result<()> {
+    let registry = ctx.debug.id_registry;
+    let triggers: Vec<TriggerId> = registry
+        .lookup(pattern)
+        .into_iter()
+        .filter_map(|id| match id {
+            ElementId::Trigger(id) => Some(id),
+            _ => None,
+        })
+        .collect();
+    if triggers.is_empty() {
+        return writeln!(output, "No port or action matches '{}'", pattern);
+    }
+    for trigger in triggers {
+        let path = registry.fmt_component(trigger);
+        if let (Some(inspectable), Some(container)) = (ctx.inspectable, registry.get_trigger_container(trigger)) {
+            if container != inspectable {
+                writeln!(
+                    output,
+                    "{}: only the components of {} can be inspected before its reaction",
+                    path,
+                    registry.get_debug_info(inspectable)
+                )?;
+                continue;
+            }
+        }
+        match ctx.inspect(Some(trigger)).pop() {
+            Some(TriggerState { value: Some(value), .. }) => writeln!(output, "{} = {}", path, value)?,
+            Some(TriggerState { present, .. }) => writeln!(
+                output,
+                "{} is {}, its value cannot be displayed",
+                path,
+                if present { "present" } else { "absent" }
+            )?,
+            None => writeln!(output, "{}: its reactor does not support inspection", path)?,
+        }
+    }
+    Ok(())
+}
+
+/// List the components that are present.
+fn print_present(output: &mut dyn Write, ctx: &PauseCtx) -> io::Result<()> {
+    let registry = ctx.debug.id_registry;
+    let present: Vec<TriggerState> = ctx.inspect(None).into_iter().filter(|t| t.present).collect();
+    if present.is_empty() {
+        writeln!(output, "Nothing is present, among the components that can be inspected")?;
+    }
+    for TriggerState { id, value, .. } in present {
+        match value {
+            Some(value) => writeln!(output, "{} = {}", registry.fmt_component(id), value)?,
+            None => writeln!(output, "{}", registry.fmt_component(id))?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::assembly::PortKind;
+    use crate::scheduler::debug::ReactorDebugInfo;
+    use crate::{tag, LocalReactionId, Port};
+
+    /// Output shared with the test.
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn take(&self) -> String {
+            String::from_utf8(std::mem::take(&mut *self.0.lock().unwrap())).unwrap()
+        }
+    }
+
+    fn registry() -> DebugInfoRegistry {
+        let mut registry = DebugInfoRegistry::new();
+        let main = ReactorId::new(0);
+        registry.record_reactor(main, ReactorDebugInfo::test_named(""));
+        registry.record_trigger(TriggerId::new(2), "out".into());
+        registry.record_trigger(TriggerId::new(3), "raw".into());
+        registry.set_id_range(main, TriggerId::new(2)..TriggerId::new(4));
+        registry.record_num_reactions(main, 2);
+        registry.record_reaction(tick(), "tick".into());
+        registry.record_main_reactor(main);
+        registry
+    }
+
+    fn tick() -> GlobalReactionId {
+        GlobalReactionId::new(ReactorId::new(0), LocalReactionId::new(1))
+    }
+
+    fn debugger(commands: &'static str, breakpoints: &[Breakpoint], output: &Output) -> Debugger {
+        let connection = Connection::Connected {
+            input: Box::new(Cursor::new(commands.as_bytes())),
+            output: Box::new(output.clone()),
+        };
+        Debugger::with_connection(connection, breakpoints, &registry())
+    }
+
+    #[test]
+    fn test_parse_breakpoints() {
+        let parse = |s: &str| s.parse::<Breakpoint>();
+        assert_eq!(parse("T0"), Ok(Breakpoint::Tag(tag!(T0))));
+        assert_eq!(parse("T0 + 10 ms"), Ok(Breakpoint::Tag(tag!(T0 + 10 ms))));
+        assert_eq!(parse("(T0 + 1.5 s, 2)"), Ok(Breakpoint::Tag(tag!(T0 + 1500 ms, 2))));
+        assert_eq!(parse("child[*]/tick"), Ok(Breakpoint::Reaction("child[*]/tick".into())));
+        assert!(parse("T0 + 10").is_err());
+        assert!(parse("T0 - 1 s").is_err());
+        assert!(parse("(T0, x)").is_err());
+
+        let tag = Breakpoint::Tag(tag!(T0 + 10 ms, 1));
+        assert_eq!(parse(&tag.to_string()), Ok(tag));
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let output = Output::default();
+        let breakpoints = ["T0 + 5 ms".parse().unwrap(), "tick".parse().unwrap()];
+        let debugger = debugger("", &breakpoints, &output);
+
+        assert!(!debugger.pauses_at_tag(tag!(T0)));
+        assert!(!debugger.pauses_at_tag(tag!(T0 + 3 ms)));
+        assert!(debugger.pauses_at_tag(tag!(T0 + 7 ms)));
+        assert!(!debugger.pauses_at_tag(tag!(T0 + 8 ms)));
+
+        assert!(debugger.pauses_before(tick()));
+        assert!(!debugger.pauses_before(GlobalReactionId::new(ReactorId::new(0), LocalReactionId::new(0))));
+    }
+
+    #[test]
+    fn test_session() {
+        struct NotDebug;
+
+        let registry = registry();
+        let mut out = Port::<i32>::new(TriggerId::new(2), PortKind::Output);
+        let mut raw = Port::<NotDebug>::new(TriggerId::new(3), PortKind::Output);
+        out.set_impl(Some(3));
+        raw.set_impl(Some(NotDebug));
+
+        let output = Output::default();
+        let debugger = debugger("p out\npresent\nq\nb T0 + 5 ms\nb nothing\nfrobnicate\ns\n", &[], &output);
+        let queue = EventQueue::default();
+        let inspect = |inspector: &mut TriggerInspector| {
+            inspector.value(&out).presence(&raw);
+        };
+        let pause = |point| {
+            debugger.pause(&PauseCtx {
+                point,
+                debug: &DebugInfoProvider { id_registry: &registry },
+                event_queue: &queue,
+                start: Instant::now(),
+                inspect: &inspect,
+                inspectable: None,
+            })
+        };
+
+        // pauses at startup without breakpoints
+        assert!(debugger.pauses_at_tag(tag!(T0)));
+        pause(PausePoint::Tag(tag!(T0)));
+        assert_eq!(
+            output.take(),
+            "Paused at (T0, 0)\n\
+             (debug) /out = 3\n\
+             (debug) /out = 3\n\
+             /raw\n\
+             (debug) 0 pending event(s)\n\
+             (debug) Added breakpoint (T0 + 5 ms, 0)\n\
+             (debug) Breakpoint 'nothing' does not match any reaction\n\
+             (debug) Unknown command 'frobnicate', type 'help' for a list of commands\n\
+             (debug) "
+        );
+
+        // stepping pauses before the next reaction
+        assert!(debugger.pauses_before(tick()));
+        pause(PausePoint::Reaction(tag!(T0), tick()));
+        assert_eq!(output.take(), "Paused at (T0, 0) before reaction /1@tick\n(debug) ");
+
+        // the input is exhausted, so the debugger is detached
+        assert!(!debugger.pauses_before(tick()));
+        assert!(!debugger.pauses_at_tag(tag!(T0 + 5 ms)));
+    }
+
+    #[test]
+    fn test_probes() {
+        let mut registry = registry();
+        let child = ReactorId::new(1);
+        registry.record_reactor(child, ReactorDebugInfo::test_named("child"));
+        registry.set_id_range(child, TriggerId::new(4)..TriggerId::new(4));
+        registry.record_reactor_container(ReactorId::new(0), child);
+        let mut out = Port::<i32>::new(TriggerId::new(2), PortKind::Output);
+        let raw = Port::<i32>::new(TriggerId::new(3), PortKind::Output);
+        let (out_probe, raw_probe) = (out.probe(), raw.probe());
+        registry.record_probe(TriggerId::new(2), move |i| {
+            i.value(&out_probe);
+        });
+        registry.record_probe(TriggerId::new(3), move |i| {
+            i.presence(&raw_probe);
+        });
+        out.set_impl(Some(5));
+
+        let output = Output::default();
+        let debugger = debugger("p out\np raw\npresent\nc\np out\npresent\nc\n", &[], &output);
+        let queue = EventQueue::default();
+        let pause = |inspectable| {
+            debugger.pause(&PauseCtx {
+                point: PausePoint::Tag(tag!(T0)),
+                debug: &DebugInfoProvider { id_registry: &registry },
+                event_queue: &queue,
+                start: Instant::now(),
+                // the reactor does not implement inspect
+                inspect: &|_| {},
+                inspectable,
+            })
+        };
+
+        pause(None);
+        assert_eq!(
+            output.take(),
+            "Paused at (T0, 0)\n\
+             (debug) /out = 5\n\
+             (debug) /raw is absent, its value cannot be displayed\n\
+             (debug) /out = 5\n\
+             (debug) "
+        );
+
+        // probes are restricted to the reactor whose reaction is next
+        pause(Some(child));
+        assert_eq!(
+            output.take(),
+            "Paused at (T0, 0)\n\
+             (debug) /out: only the components of child/ can be inspected before its reaction\n\
+             (debug) Nothing is present, among the components that can be inspected\n\
+             (debug) "
+        );
+    }
+}
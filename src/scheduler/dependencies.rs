@@ -31,7 +31,7 @@ use std::ops::Range;
 use std::sync::Arc;
 
 use index_vec::{Idx, IndexVec};
-use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::graph::{DiGraph, EdgeIndex, NodeIndex};
 use petgraph::visit::EdgeRef;
 use petgraph::Direction::{Incoming, Outgoing};
 
@@ -43,10 +43,10 @@ use crate::util::vecmap::{Entry as VEntry, VecMap};
 use crate::vecmap::KeyRef;
 use crate::*;
 
-type GraphIx = NodeIndex<GlobalIdImpl>;
+pub(super) type GraphIx = NodeIndex<GlobalIdImpl>;
 
 #[derive(Debug, Eq, PartialEq, Hash)]
-enum NodeKind {
+pub(super) enum NodeKind {
     /// startup/shutdown
     Special,
     MultiportUpstream,
@@ -57,13 +57,13 @@ enum NodeKind {
 }
 
 /// Weight of graph nodes.
-struct GraphNode {
-    kind: NodeKind,
-    id: GraphId,
+pub(super) struct GraphNode {
+    pub(super) kind: NodeKind,
+    pub(super) id: GraphId,
 }
 
 #[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
-enum GraphId {
+pub(super) enum GraphId {
     Trigger(TriggerId),
     Reaction(GlobalReactionId),
 }
@@ -109,8 +109,8 @@ pub(super) struct DepGraph {
     /// - port/action -> reaction: the port/action triggers the reaction
     /// - port -> port: a binding of a port to another
     /// - reaction n -> reaction m: means n has higher priority
-    /// than m, only filled in for reactions of the same reactor.
-    dataflow: DepGraphImpl,
+    ///   than m, only filled in for reactions of the same reactor.
+    pub(super) dataflow: DepGraphImpl,
 
     /// Maps global IDs back to graph indices.
     ix_by_id: HashMap<GraphId, GraphIx>,
@@ -120,6 +120,9 @@ pub(super) struct DepGraph {
     multiport_containment: HashMap<GraphId, TriggerId>,
     /// Map of multiport ID -> range of IDs for its channels
     multiport_ranges: VecMap<TriggerId, Range<TriggerId>>,
+
+    /// Reactions that declared they may spawn physical threads.
+    thread_spawners: Vec<GlobalReactionId>,
 }
 
 impl Debug for GraphNode {
@@ -140,34 +143,13 @@ impl DepGraph {
             ix_by_id: Default::default(),
             multiport_containment: Default::default(),
             multiport_ranges: Default::default(),
+            thread_spawners: Default::default(),
         };
         ich.record_special(TriggerId::STARTUP);
         ich.record_special(TriggerId::SHUTDOWN);
         ich
     }
 
-    /// Produce a dot representation of the graph.
-    #[cold]
-    #[inline(never)]
-    pub fn format_dot(&self, id_registry: &DebugInfoRegistry) -> String {
-        use petgraph::dot::{Config, Dot};
-
-        // Map the node weights to nice strings.
-        // petgraph doesn't support custom node formatters
-        // https://github.com/petgraph/petgraph/issues/194
-        let labeled = self.dataflow.map(
-            |_, n| match n.id {
-                GraphId::Reaction(id) => format!("Reaction({})", id_registry.fmt_reaction(id)),
-                GraphId::Trigger(TriggerId::STARTUP) => "startup".to_string(),
-                GraphId::Trigger(TriggerId::SHUTDOWN) => "shutdown".to_string(),
-                GraphId::Trigger(id) => format!("{:?}({})", n.kind, id_registry.fmt_component(id)),
-            },
-            |_, _| "",
-        );
-
-        format!("{}", Dot::with_config(&labeled, &[Config::EdgeNoLabel]))
-    }
-
     pub(super) fn record_port(&mut self, id: TriggerId) {
         self.record_port_impl(id);
     }
@@ -196,7 +178,7 @@ impl DepGraph {
 
         for channel_id in id
             .iter_next_range(len)
-            .map_err(|_| AssemblyError(AssemblyErrorImpl::IdOverflow))?
+            .map_err(|_| AssemblyError::from(AssemblyDiagnostic::IdOverflow))?
         {
             self.multiport_containment.insert(GraphId::Trigger(channel_id), id);
 
@@ -276,6 +258,10 @@ impl DepGraph {
             .add_edge(self.get_ix(trigger.into()), self.get_ix(reaction.into()), EdgeWeight::Use);
     }
 
+    pub fn reaction_spawns_threads(&mut self, reaction: GlobalReactionId) {
+        self.thread_spawners.push(reaction);
+    }
+
     fn get_ix(&self, id: GraphId) -> GraphIx {
         self.ix_by_id[&id]
     }
@@ -299,7 +285,7 @@ impl DepGraph {
 }
 
 impl DepGraph {
-    pub(self) fn number_reactions_by_level(&self) -> HashMap<GlobalReactionId, LevelIx> {
+    pub(super) fn number_reactions_by_level(&self) -> HashMap<GlobalReactionId, LevelIx> {
         // note: this will infinitely recurse with a cyclic graph
         let mut level_numbers = HashMap::<GlobalReactionId, LevelIx>::new();
         let mut todo = self.get_roots();
@@ -342,10 +328,76 @@ impl DepGraph {
             .filter(|node| self.dataflow.edges_directed(*node, Incoming).next().is_none())
             .collect()
     }
+
+    /// Returns a cycle of the graph if there is one. Each
+    /// element is paired with the kind of the edge that
+    /// leads to the next element, the last element leads
+    /// back to the first.
+    pub(super) fn find_cycle(&self) -> Option<Vec<(GraphElement, DependencyKind)>> {
+        // Any strongly connected component with several nodes
+        // contains a cycle. A single node is a cycle if it has
+        // a self-loop.
+        let scc = petgraph::algo::tarjan_scc(&self.dataflow)
+            .into_iter()
+            .find(|scc| scc.len() > 1 || self.dataflow.contains_edge(scc[0], scc[0]))?;
+        // start with the earliest recorded node, to be deterministic
+        let start = *scc.iter().min().unwrap();
+
+        // Breadth-first search for the shortest path from start
+        // back to itself within the component.
+        let mut predecessor = HashMap::<GraphIx, EdgeIndex<GlobalIdImpl>>::new();
+        let mut todo = std::collections::VecDeque::from(vec![start]);
+        'search: while let Some(ix) = todo.pop_front() {
+            for edge in self.dataflow.edges_directed(ix, Outgoing) {
+                let target = edge.target();
+                if !scc.contains(&target) || predecessor.contains_key(&target) {
+                    continue;
+                }
+                predecessor.insert(target, edge.id());
+                if target == start {
+                    break 'search;
+                }
+                todo.push_back(target);
+            }
+        }
+
+        // Walk the path backwards from start.
+        let mut cycle = Vec::new();
+        let mut ix = start;
+        loop {
+            let edge = predecessor[&ix];
+            let (source, _) = self.dataflow.edge_endpoints(edge).unwrap();
+            cycle.push((self.graph_element(source), self.dependency_kind(edge)));
+            ix = source;
+            if ix == start {
+                break;
+            }
+        }
+        cycle.reverse();
+        Some(cycle)
+    }
+
+    fn graph_element(&self, ix: GraphIx) -> GraphElement {
+        match self.dataflow[ix].id {
+            GraphId::Reaction(id) => GraphElement::Reaction { id, path: None },
+            GraphId::Trigger(id) => GraphElement::Component(ComponentRef::new(id)),
+        }
+    }
+
+    pub(super) fn dependency_kind(&self, edge: EdgeIndex<GlobalIdImpl>) -> DependencyKind {
+        let (source, target) = self.dataflow.edge_endpoints(edge).unwrap();
+        match (&self.dataflow[source].kind, &self.dataflow[target].kind, &self.dataflow[edge]) {
+            (NodeKind::Reaction, NodeKind::Reaction, _) => DependencyKind::Priority,
+            (NodeKind::Reaction, _, _) => DependencyKind::Effect,
+            (_, NodeKind::Reaction, EdgeWeight::Use) => DependencyKind::Use,
+            (_, NodeKind::Reaction, EdgeWeight::Default) => DependencyKind::Trigger,
+            (_, _, _) => DependencyKind::Binding,
+        }
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
-enum EdgeWeight {
+pub(super) enum EdgeWeight {
     /// Default semantics for this edge (determined by the
     /// kind of source and target vertex). This only makes a
     /// difference for edges from a port/action to a reaction:
@@ -387,18 +439,27 @@ pub(super) struct DataflowInfo {
     /// to be scheduled when it is triggered.
     /// Todo: many of those are never asked for, eg those of bound ports
     trigger_to_plan: IndexVec<TriggerId, Arc<ExecutableReactions<'static>>>,
+    /// Reactions that may spawn physical threads.
+    thread_spawners: Vec<GlobalReactionId>,
 }
 
 impl DataflowInfo {
     pub fn new(mut graph: DepGraph) -> Result<Self, AssemblyError> {
-        if petgraph::algo::is_cyclic_directed(&graph.dataflow) {
-            return Err(AssemblyError(AssemblyErrorImpl::CyclicDependencyGraph));
+        if let Some(cycle) = graph.find_cycle() {
+            return Err(AssemblyError::from(AssemblyDiagnostic::CyclicDependencyGraph { cycle }));
         }
 
         let level_info = ReactionLevelInfo::new(graph.number_reactions_by_level());
         let trigger_to_plan = Self::collect_trigger_to_plan(&mut graph, &level_info);
 
-        Ok(DataflowInfo { trigger_to_plan })
+        let thread_spawners = std::mem::take(&mut graph.thread_spawners);
+        Ok(DataflowInfo { trigger_to_plan, thread_spawners })
+    }
+
+    /// Reactions that declared they may spawn physical threads,
+    /// see [DependencyDeclarator::declare_spawns_threads](crate::assembly::DependencyDeclarator::declare_spawns_threads).
+    pub fn thread_spawners(&self) -> &[GlobalReactionId] {
+        &self.thread_spawners
     }
 
     fn collect_trigger_to_plan(
@@ -749,7 +810,7 @@ pub mod test {
 
         #[allow(unused)]
         fn eprintln_graph(&self) {
-            eprintln!("{}", self.graph.format_dot(&self.debug_info));
+            eprintln!("{}", self.graph.export(&self.debug_info, GraphFormat::Dot));
         }
     }
 
@@ -923,36 +984,125 @@ pub mod test {
         assert_eq!(levels.len(), 120);
     }
 
+    #[test]
+    fn test_cycle_is_reported_with_path() {
+        let mut test = TestGraphFixture::new();
+
+        let mut builder = test.new_reactor("main");
+        let [n0, n1] = builder.new_reactions();
+        let [p0, p1] = builder.new_ports(["p0", "p1"]);
+        drop(builder);
+
+        test.graph.reaction_effects(n1, p0);
+        test.graph.port_bind_untyped(p0, p1);
+        test.graph.triggers_reaction(p1, n0);
+
+        let error = DataflowInfo::new(test.graph).err().expect("graph is cyclic");
+        assert_eq!(
+            error.lift(&test.debug_info),
+            "Cyclic dependency graph: reaction main/0 --(priority)--> reaction main/1 --(effect)--> main/p0 --(binding)--> main/p1 --(trigger)--> reaction main/0"
+        );
+    }
+
     #[test]
     fn test_graph_dump() {
         let mut test = TestGraphFixture::new();
 
         let mut builder = test.new_reactor("main");
         let [n1, n2] = builder.new_reactions();
-        let [p0, p1] = builder.new_ports(["p0", "p1"]);
+        let [p0] = builder.new_ports(["p0"]);
+        drop(builder);
+        let mut builder = test.new_reactor("main/child");
+        let [c1] = builder.new_reactions();
+        let [cin] = builder.new_ports(["in"]);
+        drop(builder);
+        test.debug_info.record_reactor_container(ReactorId::new(0), ReactorId::new(1));
+
+        test.graph.reaction_effects(n1, p0);
+        test.graph.port_bind_untyped(p0, cin);
+        test.graph.triggers_reaction(cin, c1);
+        test.graph.reaction_uses(n2, p0);
+
+        assert_eq!(
+            test.graph.export(&test.debug_info, GraphFormat::Dot),
+            r##"digraph {
+    node [style=filled];
+    n0 [label="startup", tooltip="startup", shape=ellipse, fillcolor="#d9d9d9"];
+    n1 [label="shutdown", tooltip="shutdown", shape=ellipse, fillcolor="#d9d9d9"];
+    subgraph cluster_0 {
+        label="main/";
+        n2 [label="0\nlevel 0", tooltip="main/0", shape=box, fillcolor="#a6cee3"];
+        n3 [label="1\nlevel 2", tooltip="main/1", shape=box, fillcolor="#a6cee3"];
+        n4 [label="p0", tooltip="main/p0", shape=ellipse, fillcolor="#ffffb3"];
+        subgraph cluster_1 {
+            label="main/child/";
+            n5 [label="0\nlevel 3", tooltip="main/child/0", shape=box, fillcolor="#a6cee3"];
+            n6 [label="in", tooltip="main/child/in", shape=ellipse, fillcolor="#ffffb3"];
+        }
+    }
+    n2 -> n3 [style=dotted];
+    n2 -> n4;
+    n4 -> n6 [style=bold];
+    n6 -> n5;
+    n4 -> n3 [style=dashed];
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_graph_dump_json_and_mermaid() {
+        let mut test = TestGraphFixture::new();
+
+        let mut builder = test.new_reactor("main");
+        let [n1, n2] = builder.new_reactions();
+        let [p0] = builder.new_ports(["p0"]);
         drop(builder);
 
         test.graph.reaction_effects(n1, p0);
-        test.graph.reaction_effects(n1, p1);
         test.graph.triggers_reaction(p0, n2);
-        test.graph.triggers_reaction(p1, n2);
 
         assert_eq!(
-            test.graph.format_dot(&test.debug_info),
-            r#"digraph {
-    0 [ label = "startup" ]
-    1 [ label = "shutdown" ]
-    2 [ label = "Reaction(main/0)" ]
-    3 [ label = "Reaction(main/1)" ]
-    4 [ label = "Port(main/p0)" ]
-    5 [ label = "Port(main/p1)" ]
-    2 -> 3 [ ]
-    2 -> 4 [ ]
-    2 -> 5 [ ]
-    4 -> 3 [ ]
-    5 -> 3 [ ]
+            test.graph.export(&test.debug_info, GraphFormat::Json),
+            r##"{
+  "reactors": [
+    {"id": 0, "path": "main/", "container": null}
+  ],
+  "nodes": [
+    {"id": 0, "kind": "Special", "label": "startup", "path": "startup", "reactor": null, "level": null},
+    {"id": 1, "kind": "Special", "label": "shutdown", "path": "shutdown", "reactor": null, "level": null},
+    {"id": 2, "kind": "Reaction", "label": "0", "path": "main/0", "reactor": 0, "level": 0},
+    {"id": 3, "kind": "Reaction", "label": "1", "path": "main/1", "reactor": 0, "level": 2},
+    {"id": 4, "kind": "Port", "label": "p0", "path": "main/p0", "reactor": 0, "level": null}
+  ],
+  "edges": [
+    {"source": 2, "target": 3, "kind": "priority"},
+    {"source": 2, "target": 4, "kind": "effect"},
+    {"source": 4, "target": 3, "kind": "trigger"}
+  ]
 }
-"#
+"##
+        );
+        assert_eq!(
+            test.graph.export(&test.debug_info, GraphFormat::Mermaid),
+            r##"flowchart TD
+    n0(["startup"]):::Special
+    n1(["shutdown"]):::Special
+    subgraph r0 ["main/"]
+        n2["0<br>level 0"]:::Reaction
+        n3["1<br>level 2"]:::Reaction
+        n4(["p0"]):::Port
+    end
+    n2 -.-> n3
+    n2 --> n4
+    n4 --> n3
+    classDef Special fill:#d9d9d9
+    classDef Multiport fill:#fdbf6f
+    classDef Port fill:#ffffb3
+    classDef Action fill:#b2df8a
+    classDef Timer fill:#fb9a99
+    classDef Reaction fill:#a6cee3
+"##
         );
     }
 }
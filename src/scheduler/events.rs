@@ -1,10 +1,13 @@
 use std::borrow::Cow;
 use std::collections::VecDeque;
 use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
-use super::ReactionPlan;
+use super::{DebugInfoProvider, ReactionPlan};
 use crate::scheduler::dependencies::ExecutableReactions;
+use crate::timers::TimerFiring;
 use crate::*;
 
 /// The tag of an event.
@@ -18,6 +21,7 @@ use crate::*;
 /// Use the [tag!](crate::tag) macro to create this struct with
 /// convenient syntax.
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EventTag {
     /// The time offset from the origin of the logical timeline.
     /// Knowing the start time of the application is necessary to
@@ -35,10 +39,21 @@ impl EventTag {
     };
 
     /// Returns the logical instant for this tag, using the
-    /// initial time `t0`.
+    /// initial time `t0`. Tags that are too far in the future
+    /// for an [Instant] to represent, like those at [delay!(forever)](crate::delay),
+    /// are mapped to about a century after `t0`.
     #[inline]
     pub fn to_logical_time(&self, t0: Instant) -> Instant {
-        t0 + self.offset_from_t0
+        const FAR_FUTURE: Duration = Duration::from_secs(100 * 365 * 24 * 3600);
+        t0.checked_add(self.offset_from_t0).unwrap_or_else(|| t0 + FAR_FUTURE)
+    }
+
+    /// Returns the absolute logical time of this tag, using the
+    /// initial time `t0`.
+    #[inline]
+    pub fn to_absolute(&self, t0: Instant) -> LogicalTime {
+        let timestamp = Timestamp::from_instant(t0).saturating_add(self.offset_from_t0);
+        LogicalTime::new(timestamp, self.microstep)
     }
 
     /// Returns the amount of time elapsed since the start
@@ -62,11 +77,13 @@ impl EventTag {
         self.microstep
     }
 
-    /// Create a tag for the zeroth microstep of the given instant.
+    /// Create a tag for the zeroth microstep of the given
+    /// instant, delayed by the given duration. The offset
+    /// saturates instead of overflowing.
     #[inline]
-    pub(crate) fn absolute(t0: Instant, instant: Instant) -> Self {
+    pub(crate) fn absolute_delayed(t0: Instant, instant: Instant, delay: Duration) -> Self {
         Self {
-            offset_from_t0: instant - t0,
+            offset_from_t0: (instant - t0).saturating_add(delay),
             microstep: MicroStep::ZERO,
         }
     }
@@ -82,13 +99,15 @@ impl EventTag {
     }
 
     /// Returns a tag that is strictly greater than this one.
+    /// The offset saturates at [Duration::MAX], where it can
+    /// only be greater by a microstep.
     #[inline]
     pub(crate) fn successor(self, offset: Duration) -> Self {
-        if offset.is_zero() {
+        if offset.is_zero() || self.offset_from_t0 == Duration::MAX {
             self.next_microstep()
         } else {
             Self {
-                offset_from_t0: self.offset_from_t0 + offset,
+                offset_from_t0: self.offset_from_t0.saturating_add(offset),
                 microstep: MicroStep::ZERO,
             }
         }
@@ -113,14 +132,11 @@ impl EventTag {
 
 impl Display for EventTag {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let elapsed = self.offset_from_t0;
-        write!(
-            f,
-            "(T0 + {} ns = {} ms, {})",
-            elapsed.as_nanos(),
-            elapsed.as_millis(),
-            self.microstep
-        )
+        if self.offset_from_t0.is_zero() {
+            write!(f, "(T0, {})", self.microstep)
+        } else {
+            write!(f, "(T0 + {}, {})", format_duration(self.offset_from_t0), self.microstep)
+        }
     }
 }
 
@@ -141,20 +157,55 @@ pub(super) struct Event<'x> {
     /// Whether we should terminate the application at
     /// the tag of this event (after processing the tag).
     pub terminate: bool,
+    /// Timers that fire at this tag, with their downstream
+    /// reactions. They are only added to [Self::reactions] when
+    /// the tag is processed, as they may be cancelled until then.
+    pub timers: Vec<(TimerFiring, &'x ExecutableReactions<'x>)>,
 }
 
 impl<'x> Event<'x> {
-    pub fn absorb(&mut self, other: Event<'x>) {
+    pub fn absorb(&mut self, mut other: Event<'x>) {
         debug_assert_eq!(self.tag, other.tag);
         self.reactions = ExecutableReactions::merge_cows(self.reactions.take(), other.reactions);
         self.terminate |= other.terminate;
+        self.timers.append(&mut other.timers);
     }
 
     pub fn execute(tag: EventTag, reactions: Cow<'x, ExecutableReactions<'x>>) -> Self {
-        Self { tag, reactions: Some(reactions), terminate: false }
+        Self {
+            tag,
+            reactions: Some(reactions),
+            terminate: false,
+            timers: Vec::new(),
+        }
     }
     pub fn terminate_at(tag: EventTag) -> Self {
-        Self { tag, reactions: None, terminate: true }
+        Self {
+            tag,
+            reactions: None,
+            terminate: true,
+            timers: Vec::new(),
+        }
+    }
+    pub fn timer(firing: TimerFiring, reactions: &'x ExecutableReactions<'x>) -> Self {
+        Self {
+            tag: firing.tag(),
+            reactions: None,
+            terminate: false,
+            timers: vec![(firing, reactions)],
+        }
+    }
+
+    /// The reactions that will be executed at the tag of this
+    /// event, including those of timers that have not been
+    /// cancelled so far.
+    pub fn pending_reactions(&self) -> ReactionPlan<'x> {
+        self.timers
+            .iter()
+            .filter(|(firing, _)| !firing.is_cancelled())
+            .fold(self.reactions.clone(), |plan, (_, reactions)| {
+                ExecutableReactions::merge_cows(plan, Some(Cow::Borrowed(*reactions)))
+            })
     }
 }
 
@@ -172,9 +223,18 @@ pub(super) struct EventQueue<'x> {
     ///      ...
     ///     ....
     value_list: VecDeque<Event<'x>>,
+
+    /// Snapshot of this queue shared with [EventQueueMonitor]s, if any.
+    monitor: Arc<QueueMonitorState>,
 }
 
 impl<'x> EventQueue<'x> {
+    /// Create an empty queue that publishes its snapshots
+    /// to the given state, see [Self::publish_snapshot].
+    pub(super) fn with_monitor(monitor: Arc<QueueMonitorState>) -> Self {
+        Self { value_list: Default::default(), monitor }
+    }
+
     /// Removes and returns the earliest tag
     pub fn take_earliest(&mut self) -> Option<Event<'x>> {
         self.value_list.pop_front()
@@ -191,6 +251,11 @@ impl<'x> EventQueue<'x> {
     //  portion of `self.value_list`. Basically the routine of an insertion
     //  sort.
 
+    /// Number of pending events.
+    pub(super) fn len(&self) -> usize {
+        self.value_list.len()
+    }
+
     /// Push an event into the heap.
     pub(super) fn push(&mut self, evt: Event<'x>) {
         match self.value_list.binary_search_by_key(&evt.tag, |e| e.tag) {
@@ -198,4 +263,205 @@ impl<'x> EventQueue<'x> {
             Err(idx) => self.value_list.insert(idx, evt),
         }
     }
+
+    /// Describe the pending events, in the order in which
+    /// they will be processed.
+    pub(super) fn snapshot(&self, debug: &DebugInfoProvider) -> EventQueueSnapshot {
+        let events = self
+            .value_list
+            .iter()
+            .map(|evt| PendingEvent {
+                tag: evt.tag,
+                reactions: debug.display_reactions(&evt.pending_reactions()),
+                terminate: evt.terminate,
+            })
+            .collect();
+        EventQueueSnapshot { events }
+    }
+
+    /// Create a handle to observe this queue from other threads.
+    pub(super) fn new_monitor(&self, debug: &DebugInfoProvider) -> EventQueueMonitor {
+        if !self.monitor.enabled.swap(true, Ordering::SeqCst) {
+            // the first snapshot should not be empty
+            *self.monitor.snapshot.lock().unwrap() = self.snapshot(debug);
+        }
+        EventQueueMonitor { state: self.monitor.clone() }
+    }
+
+    /// Publish the current state of the queue to the
+    /// [EventQueueMonitor]s, if there are any.
+    pub(super) fn publish_snapshot(&self, debug: &DebugInfoProvider) {
+        if self.monitor.enabled.load(Ordering::Relaxed) {
+            *self.monitor.snapshot.lock().unwrap() = self.snapshot(debug);
+        }
+    }
+}
+
+/// Description of an event that is waiting in the event
+/// queue of the scheduler. See [EventQueueSnapshot].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PendingEvent {
+    /// The tag at which the event will be processed.
+    pub tag: EventTag,
+    /// The reactions that will be executed at that tag,
+    /// formatted like in trace messages, ie grouped by level.
+    pub reactions: String,
+    /// Whether the program will terminate after processing
+    /// this event.
+    pub terminate: bool,
+}
+
+impl Display for PendingEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at {}: run {}", self.tag, self.reactions)?;
+        if self.terminate {
+            write!(f, ", then terminate")?;
+        }
+        Ok(())
+    }
+}
+
+/// A read-only view of the pending events of the scheduler,
+/// ordered by tag. It does not change when the queue changes,
+/// take a new snapshot to observe newer events.
+///
+/// See [ReactionCtx::pending_events](crate::ReactionCtx::pending_events)
+/// and [EventQueueMonitor].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventQueueSnapshot {
+    events: Vec<PendingEvent>,
+}
+
+impl EventQueueSnapshot {
+    /// The pending events, earliest first.
+    pub fn events(&self) -> &[PendingEvent] {
+        &self.events
+    }
+
+    /// Number of pending events. Note that several actions
+    /// scheduled at the same tag make up a single event.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Whether no event is pending.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The tag of the next event to be processed, if any.
+    pub fn next_tag(&self) -> Option<EventTag> {
+        self.events.first().map(|e| e.tag)
+    }
+}
+
+impl Display for EventQueueSnapshot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} pending event(s)", self.len())?;
+        for evt in &self.events {
+            writeln!(f, " - {}", evt)?;
+        }
+        Ok(())
+    }
+}
+
+/// A handle to observe the event queue from outside the
+/// scheduler, for instance from a monitoring thread. It can
+/// be cloned and sent to other threads freely.
+///
+/// The scheduler publishes a new snapshot each time it looks
+/// up the next event to process, which means that events
+/// sent asynchronously (eg physical actions) only appear once
+/// the scheduler has received them. Snapshots are only
+/// published once a monitor has been created, see
+/// [ReactionCtx::event_queue_monitor](crate::ReactionCtx::event_queue_monitor)
+/// and [SchedulerHandle::event_queue_monitor](crate::SchedulerHandle::event_queue_monitor).
+#[derive(Clone)]
+pub struct EventQueueMonitor {
+    state: Arc<QueueMonitorState>,
+}
+
+impl EventQueueMonitor {
+    /// Returns the latest snapshot published by the scheduler.
+    pub fn snapshot(&self) -> EventQueueSnapshot {
+        self.state.snapshot.lock().unwrap().clone()
+    }
+
+    /// Returns the number of pending events in the latest snapshot.
+    pub fn len(&self) -> usize {
+        self.state.snapshot.lock().unwrap().len()
+    }
+
+    /// Returns whether the latest snapshot is empty.
+    pub fn is_empty(&self) -> bool {
+        self.state.snapshot.lock().unwrap().is_empty()
+    }
+
+    /// Returns the tag of the earliest event of the latest snapshot.
+    pub fn next_tag(&self) -> Option<EventTag> {
+        self.state.snapshot.lock().unwrap().next_tag()
+    }
+}
+
+/// State shared between the event queue and [EventQueueMonitor]s.
+#[derive(Default)]
+pub(super) struct QueueMonitorState {
+    /// Set when the first monitor is created. Before that,
+    /// the scheduler doesn't bother publishing snapshots.
+    enabled: AtomicBool,
+    snapshot: Mutex<EventQueueSnapshot>,
+}
+
+impl QueueMonitorState {
+    /// Create a monitor from outside the scheduler. Until the
+    /// scheduler publishes a snapshot, it sees the latest one
+    /// published, or an empty queue.
+    pub(super) fn new_monitor(self: &Arc<Self>) -> EventQueueMonitor {
+        self.enabled.store(true, Ordering::SeqCst);
+        EventQueueMonitor { state: self.clone() }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::DebugInfoRegistry;
+
+    #[test]
+    fn test_tag_display() {
+        assert_eq!(tag!(T0).to_string(), "(T0, 0)");
+        assert_eq!(tag!(T0 + 1.5 s, 2).to_string(), "(T0 + 1.5 s, 2)");
+        assert_eq!(tag!(T0 + 90 min, 0).to_string(), "(T0 + 1 h 30 min, 0)");
+    }
+
+    #[test]
+    fn test_queue_snapshot() {
+        let registry = DebugInfoRegistry::new();
+        let debug = DebugInfoProvider { id_registry: &registry };
+        let mut queue = EventQueue::default();
+        let monitor = queue.new_monitor(&debug);
+        assert!(monitor.is_empty());
+
+        queue.push(Event::terminate_at(tag!(T0 + 20 ms)));
+        queue.push(Event::terminate_at(tag!(T0 + 10 ms, 1)));
+        queue.push(Event::terminate_at(tag!(T0 + 20 ms)));
+
+        let snapshot = queue.snapshot(&debug);
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot.next_tag(), Some(tag!(T0 + 10 ms, 1)));
+        assert_eq!(snapshot.events()[1].tag, tag!(T0 + 20 ms));
+        assert!(snapshot.events().iter().all(|e| e.terminate && e.reactions == "[]"));
+
+        // monitors only see published snapshots
+        assert!(monitor.is_empty());
+        queue.publish_snapshot(&debug);
+        assert_eq!(monitor.snapshot(), snapshot);
+
+        queue.take_earliest();
+        queue.publish_snapshot(&debug);
+        assert_eq!(monitor.len(), 1);
+        assert_eq!(monitor.next_tag(), Some(tag!(T0 + 20 ms)));
+    }
 }
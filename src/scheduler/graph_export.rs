@@ -0,0 +1,380 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+//! Export of the dependency graph to external formats.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use petgraph::visit::EdgeRef;
+
+use super::dependencies::{DepGraph, GraphId, GraphIx, LevelIx, NodeKind};
+use crate::assembly::{DependencyKind, TriggerId};
+use crate::*;
+
+/// Format of a dependency graph export,
+/// see [SchedulerOptions::dump_graph].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum GraphFormat {
+    /// A [Graphviz](https://graphviz.org/) DOT file.
+    #[default]
+    Dot,
+    /// A JSON document with a list of reactors, nodes and edges.
+    Json,
+    /// A [Mermaid](https://mermaid.js.org/) flowchart.
+    Mermaid,
+}
+
+impl GraphFormat {
+    /// The usual file extension for this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            GraphFormat::Dot => "dot",
+            GraphFormat::Json => "json",
+            GraphFormat::Mermaid => "mmd",
+        }
+    }
+}
+
+impl std::str::FromStr for GraphFormat {
+    type Err = String;
+
+    /// Parses the name of a format, case-insensitively
+    /// (`dot`, `json` or `mermaid`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dot" => Ok(GraphFormat::Dot),
+            "json" => Ok(GraphFormat::Json),
+            "mermaid" | "mmd" => Ok(GraphFormat::Mermaid),
+            _ => Err(format!("unknown graph format '{}', expected one of dot, json, mermaid", s)),
+        }
+    }
+}
+
+/// A node of the graph, prepared for export.
+struct ExportNode {
+    /// Index of the node in the graph, used as its id.
+    ix: usize,
+    kind: &'static str,
+    /// Label of the node within its reactor.
+    label: String,
+    /// Full path of the node.
+    path: String,
+    reactor: Option<ReactorId>,
+    /// Level of a reaction, if levels could be computed.
+    level: Option<LevelIx>,
+}
+
+/// Flattened view of the graph, with the containment
+/// hierarchy of reactors.
+struct GraphExport {
+    nodes: Vec<ExportNode>,
+    edges: Vec<(usize, usize, DependencyKind)>,
+    /// Path and container of each reactor, in order of ids.
+    reactors: Vec<(ReactorId, String, Option<ReactorId>)>,
+    /// Children of each reactor, and top-level reactors
+    /// under the key None.
+    children: HashMap<Option<ReactorId>, Vec<ReactorId>>,
+    /// Nodes of each reactor. Startup and shutdown are
+    /// top-level, under the key None.
+    nodes_by_reactor: HashMap<Option<ReactorId>, Vec<usize>>,
+}
+
+impl DepGraph {
+    /// Produce a representation of the graph in the given format.
+    /// Nodes are grouped by reactor instance, following the
+    /// containment hierarchy of reactors. Reactions are annotated
+    /// with their level, unless the graph is cyclic.
+    #[cold]
+    #[inline(never)]
+    pub fn export(&self, id_registry: &DebugInfoRegistry, format: GraphFormat) -> String {
+        let export = self.prepare_export(id_registry);
+        match format {
+            GraphFormat::Dot => export.format_dot(),
+            GraphFormat::Json => export.format_json(),
+            GraphFormat::Mermaid => export.format_mermaid(),
+        }
+    }
+
+    fn prepare_export(&self, id_registry: &DebugInfoRegistry) -> GraphExport {
+        // levels cannot be computed on a cyclic graph
+        let levels = match self.find_cycle() {
+            None => self.number_reactions_by_level(),
+            Some(_) => Default::default(),
+        };
+
+        let mut nodes_by_reactor = HashMap::<_, Vec<_>>::new();
+        let nodes = self
+            .dataflow
+            .node_indices()
+            .map(|ix: GraphIx| {
+                let node = &self.dataflow[ix];
+                let (label, path, reactor, level) = match node.id {
+                    GraphId::Reaction(id) => {
+                        let label = match id_registry.reaction_label(id) {
+                            Some(label) => format!("{}@{}", id.0.local().index(), label),
+                            None => id.0.local().index().to_string(),
+                        };
+                        let path = id_registry.fmt_reaction(id).to_string();
+                        (label, path, Some(id.0.container()), levels.get(&id).copied())
+                    }
+                    GraphId::Trigger(id @ (TriggerId::STARTUP | TriggerId::SHUTDOWN)) => {
+                        let label = id_registry.trigger_label(id).to_string();
+                        (label.clone(), label, None, None)
+                    }
+                    GraphId::Trigger(id) => {
+                        let label = id_registry.trigger_label(id).to_string();
+                        let path = id_registry.fmt_component(id).to_string();
+                        (label, path, id_registry.get_trigger_container(id), None)
+                    }
+                };
+                nodes_by_reactor.entry(reactor).or_default().push(ix.index());
+                ExportNode {
+                    ix: ix.index(),
+                    kind: kind_name(&node.kind),
+                    label,
+                    path,
+                    reactor,
+                    level,
+                }
+            })
+            .collect();
+
+        let edges = self
+            .dataflow
+            .edge_references()
+            .map(|e| (e.source().index(), e.target().index(), self.dependency_kind(e.id())))
+            .collect();
+
+        let mut children = HashMap::<_, Vec<_>>::new();
+        let reactors = id_registry
+            .reactors()
+            .map(|(id, container)| {
+                children.entry(container).or_default().push(id);
+                (id, id_registry.get_debug_info(id).to_string(), container)
+            })
+            .collect();
+
+        GraphExport { nodes, edges, reactors, children, nodes_by_reactor }
+    }
+}
+
+fn kind_name(kind: &NodeKind) -> &'static str {
+    match kind {
+        NodeKind::Special => "Special",
+        NodeKind::MultiportUpstream => "Multiport",
+        NodeKind::Port => "Port",
+        NodeKind::Action => "Action",
+        NodeKind::Timer => "Timer",
+        NodeKind::Reaction => "Reaction",
+    }
+}
+
+/// Fill color of nodes, also used by the Mermaid output.
+fn kind_color(kind: &str) -> &'static str {
+    match kind {
+        "Reaction" => "#a6cee3",
+        "Port" => "#ffffb3",
+        "Multiport" => "#fdbf6f",
+        "Action" => "#b2df8a",
+        "Timer" => "#fb9a99",
+        _ => "#d9d9d9",
+    }
+}
+
+impl GraphExport {
+    fn display_label(node: &ExportNode) -> String {
+        match node.level {
+            Some(level) => format!("{}\nlevel {}", node.label, level),
+            None => node.label.clone(),
+        }
+    }
+
+    fn format_dot(&self) -> String {
+        let mut out = String::new();
+        out += "digraph {\n";
+        out += "    node [style=filled];\n";
+        self.write_dot_cluster(&mut out, None, 1);
+        for (source, target, kind) in &self.edges {
+            let style = match kind {
+                DependencyKind::Use => " [style=dashed]",
+                DependencyKind::Priority => " [style=dotted]",
+                DependencyKind::Binding => " [style=bold]",
+                DependencyKind::Trigger | DependencyKind::Effect => "",
+            };
+            writeln!(out, "    n{} -> n{}{};", source, target, style).unwrap();
+        }
+        out += "}\n";
+        out
+    }
+
+    fn write_dot_cluster(&self, out: &mut String, reactor: Option<ReactorId>, depth: usize) {
+        let indent = "    ".repeat(depth);
+        for &ix in self.nodes_by_reactor.get(&reactor).into_iter().flatten() {
+            let node = &self.nodes[ix];
+            let shape = if node.kind == "Reaction" { "box" } else { "ellipse" };
+            writeln!(
+                out,
+                "{}n{} [label=\"{}\", tooltip=\"{}\", shape={}, fillcolor=\"{}\"];",
+                indent,
+                node.ix,
+                escape_dot(&Self::display_label(node)),
+                escape_dot(&node.path),
+                shape,
+                kind_color(node.kind)
+            )
+            .unwrap();
+        }
+        for &child in self.children.get(&reactor).into_iter().flatten() {
+            writeln!(out, "{}subgraph cluster_{} {{", indent, child.index()).unwrap();
+            writeln!(
+                out,
+                "{}    label=\"{}\";",
+                indent,
+                escape_dot(&self.reactors[child.index()].1)
+            )
+            .unwrap();
+            self.write_dot_cluster(out, Some(child), depth + 1);
+            writeln!(out, "{}}}", indent).unwrap();
+        }
+    }
+
+    fn format_mermaid(&self) -> String {
+        let mut out = String::new();
+        out += "flowchart TD\n";
+        self.write_mermaid_subgraph(&mut out, None, 1);
+        for (source, target, kind) in &self.edges {
+            let arrow = match kind {
+                DependencyKind::Use | DependencyKind::Priority => "-.->",
+                DependencyKind::Binding => "==>",
+                DependencyKind::Trigger | DependencyKind::Effect => "-->",
+            };
+            writeln!(out, "    n{} {} n{}", source, arrow, target).unwrap();
+        }
+        for kind in ["Special", "Multiport", "Port", "Action", "Timer", "Reaction"] {
+            writeln!(out, "    classDef {} fill:{}", kind, kind_color(kind)).unwrap();
+        }
+        out
+    }
+
+    fn write_mermaid_subgraph(&self, out: &mut String, reactor: Option<ReactorId>, depth: usize) {
+        let indent = "    ".repeat(depth);
+        for &ix in self.nodes_by_reactor.get(&reactor).into_iter().flatten() {
+            let node = &self.nodes[ix];
+            let label = escape_mermaid(&Self::display_label(node)).replace('\n', "<br>");
+            if node.kind == "Reaction" {
+                writeln!(out, "{}n{}[\"{}\"]:::{}", indent, node.ix, label, node.kind).unwrap();
+            } else {
+                writeln!(out, "{}n{}([\"{}\"]):::{}", indent, node.ix, label, node.kind).unwrap();
+            }
+        }
+        for &child in self.children.get(&reactor).into_iter().flatten() {
+            let path = escape_mermaid(&self.reactors[child.index()].1);
+            writeln!(out, "{}subgraph r{} [\"{}\"]", indent, child.index(), path).unwrap();
+            self.write_mermaid_subgraph(out, Some(child), depth + 1);
+            writeln!(out, "{}end", indent).unwrap();
+        }
+    }
+
+    fn format_json(&self) -> String {
+        let mut out = String::new();
+        out += "{\n  \"reactors\": [";
+        for (i, (id, path, container)) in self.reactors.iter().enumerate() {
+            out += if i == 0 { "\n" } else { ",\n" };
+            write!(
+                out,
+                "    {{\"id\": {}, \"path\": {}, \"container\": ",
+                id.index(),
+                json_string(path)
+            )
+            .unwrap();
+            match container {
+                Some(container) => write!(out, "{}}}", container.index()).unwrap(),
+                None => out += "null}",
+            }
+        }
+        out += "\n  ],\n  \"nodes\": [";
+        for (i, node) in self.nodes.iter().enumerate() {
+            out += if i == 0 { "\n" } else { ",\n" };
+            write!(
+                out,
+                "    {{\"id\": {}, \"kind\": {}, \"label\": {}, \"path\": {}, \"reactor\": ",
+                node.ix,
+                json_string(node.kind),
+                json_string(&node.label),
+                json_string(&node.path)
+            )
+            .unwrap();
+            match node.reactor {
+                Some(reactor) => write!(out, "{}", reactor.index()).unwrap(),
+                None => out += "null",
+            }
+            out += ", \"level\": ";
+            match node.level {
+                Some(level) => write!(out, "{}}}", level).unwrap(),
+                None => out += "null}",
+            }
+        }
+        out += "\n  ],\n  \"edges\": [";
+        for (i, (source, target, kind)) in self.edges.iter().enumerate() {
+            out += if i == 0 { "\n" } else { ",\n" };
+            write!(
+                out,
+                "    {{\"source\": {}, \"target\": {}, \"kind\": {}}}",
+                source,
+                target,
+                json_string(&kind.to_string())
+            )
+            .unwrap();
+        }
+        out += "\n  ]\n}\n";
+        out
+    }
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn escape_mermaid(s: &str) -> String {
+    s.replace('"', "#quot;")
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
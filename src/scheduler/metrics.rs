@@ -0,0 +1,463 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+//! Runtime metrics served in the Prometheus text format, see
+//! [SchedulerOptions::metrics_addr](crate::SchedulerOptions::metrics_addr).
+
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write as _};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use index_vec::IndexVec;
+
+use super::async_channel::ChannelState;
+use crate::{DebugInfoRegistry, Duration, ReactorId};
+
+/// Upper bounds (inclusive) of the buckets of the lag
+/// histogram, in seconds. The last bucket is unbounded.
+const LAG_BUCKETS: [f64; 7] = [1e-5, 1e-4, 1e-3, 1e-2, 0.1, 1.0, 10.0];
+
+/// Maximum number of connections served concurrently by
+/// the [MetricsServer], so that clients cannot make it
+/// spawn threads without limit.
+const MAX_CONNECTION_THREADS: usize = 4;
+
+/// Counters and gauges maintained by the scheduler. They're
+/// updated with relaxed atomics, as reactions may execute in
+/// parallel, and are read by the thread of the [MetricsServer].
+pub(super) struct Metrics {
+    tags_processed: AtomicU64,
+    /// Instance path of each reactor, used as a label.
+    reactor_names: IndexVec<ReactorId, String>,
+    reactions_executed: IndexVec<ReactorId, AtomicU64>,
+    deadline_violations: IndexVec<ReactorId, AtomicU64>,
+    /// Number of tags whose lag fell in each bucket, the
+    /// last one is for lags above all of [LAG_BUCKETS].
+    lag_buckets: [AtomicU64; LAG_BUCKETS.len() + 1],
+    lag_sum_ns: AtomicU64,
+    event_queue_len: AtomicUsize,
+    /// The depth of the channel is read when rendering.
+    channel: Arc<ChannelState>,
+}
+
+impl Metrics {
+    pub(super) fn new(id_registry: &DebugInfoRegistry, channel: Arc<ChannelState>) -> Self {
+        let reactor_names: IndexVec<ReactorId, String> = id_registry
+            .reactors()
+            .map(|(id, _)| id_registry.get_debug_info(id).to_string())
+            .collect();
+        let counters = || reactor_names.iter().map(|_| AtomicU64::new(0)).collect();
+        Self {
+            tags_processed: AtomicU64::new(0),
+            reactions_executed: counters(),
+            deadline_violations: counters(),
+            reactor_names,
+            lag_buckets: Default::default(),
+            lag_sum_ns: AtomicU64::new(0),
+            event_queue_len: AtomicUsize::new(0),
+            channel,
+        }
+    }
+
+    /// Record that a tag is processed, with the given lag
+    /// of physical time behind logical time.
+    pub(super) fn record_tag(&self, lag: Duration) {
+        self.tags_processed.fetch_add(1, Ordering::Relaxed);
+        let secs = lag.as_secs_f64();
+        let bucket = LAG_BUCKETS.iter().position(|&le| secs <= le).unwrap_or(LAG_BUCKETS.len());
+        self.lag_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.lag_sum_ns.fetch_add(lag.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_reaction(&self, reactor: ReactorId) {
+        self.reactions_executed[reactor].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_deadline_violation(&self, reactor: ReactorId) {
+        self.deadline_violations[reactor].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn set_event_queue_len(&self, len: usize) {
+        self.event_queue_len.store(len, Ordering::Relaxed);
+    }
+
+    /// Render all metrics in the Prometheus text exposition format.
+    pub(super) fn render(&self) -> String {
+        let mut out = String::new();
+
+        write_header(&mut out, "tags_processed_total", "counter", "Number of processed tags.");
+        writeln!(out, "lf_tags_processed_total {}", self.tags_processed.load(Ordering::Relaxed)).unwrap();
+
+        write_header(
+            &mut out,
+            "reactions_executed_total",
+            "counter",
+            "Number of executed reactions, by reactor.",
+        );
+        self.write_per_reactor(&mut out, "reactions_executed_total", &self.reactions_executed);
+
+        write_header(
+            &mut out,
+            "deadline_violations_total",
+            "counter",
+            "Number of missed deadlines, by reactor.",
+        );
+        self.write_per_reactor(&mut out, "deadline_violations_total", &self.deadline_violations);
+
+        write_header(
+            &mut out,
+            "lag_seconds",
+            "histogram",
+            "Lag of physical time behind logical time when a tag is processed.",
+        );
+        let mut cumulative = 0;
+        for (le, count) in LAG_BUCKETS.iter().zip(&self.lag_buckets) {
+            cumulative += count.load(Ordering::Relaxed);
+            writeln!(out, "lf_lag_seconds_bucket{{le=\"{}\"}} {}", le, cumulative).unwrap();
+        }
+        cumulative += self.lag_buckets[LAG_BUCKETS.len()].load(Ordering::Relaxed);
+        writeln!(out, "lf_lag_seconds_bucket{{le=\"+Inf\"}} {}", cumulative).unwrap();
+        let sum = Duration::from_nanos(self.lag_sum_ns.load(Ordering::Relaxed));
+        writeln!(out, "lf_lag_seconds_sum {}", sum.as_secs_f64()).unwrap();
+        writeln!(out, "lf_lag_seconds_count {}", cumulative).unwrap();
+
+        write_header(
+            &mut out,
+            "async_channel_depth",
+            "gauge",
+            "Number of asynchronous events sent but not received yet by the scheduler.",
+        );
+        writeln!(out, "lf_async_channel_depth {}", self.channel.stats().depth).unwrap();
+
+        write_header(&mut out, "event_queue_length", "gauge", "Number of pending events.");
+        writeln!(out, "lf_event_queue_length {}", self.event_queue_len.load(Ordering::Relaxed)).unwrap();
+        out
+    }
+
+    fn write_per_reactor(&self, out: &mut String, name: &str, counters: &IndexVec<ReactorId, AtomicU64>) {
+        for (reactor, count) in self.reactor_names.iter().zip(counters) {
+            out.push_str("lf_");
+            out.push_str(name);
+            out.push_str("{reactor=\"");
+            escape_label_value(out, reactor);
+            writeln!(out, "\"}} {}", count.load(Ordering::Relaxed)).unwrap();
+        }
+    }
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP lf_{} {}", name, help).unwrap();
+    writeln!(out, "# TYPE lf_{} {}", name, kind).unwrap();
+}
+
+fn escape_label_value(out: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Serves the [Metrics] over HTTP, in a background thread.
+/// Any `GET` request is answered with the metrics, so they
+/// can be scraped at `/metrics`. Each connection is handled
+/// on its own short-lived thread, which is not waited for.
+/// At most [MAX_CONNECTION_THREADS] of them run at a time,
+/// further connections are answered with a 503 and closed.
+/// The thread accepting connections is stopped when this
+/// is dropped.
+pub(super) struct MetricsServer {
+    metrics: Arc<Metrics>,
+    addr: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    pub(super) fn start(addr: SocketAddr, metrics: Metrics) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let metrics = Arc::new(metrics);
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let metrics = metrics.clone();
+            let stopped = stopped.clone();
+            let connections = Arc::new(AtomicUsize::new(0));
+            std::thread::Builder::new().name("metrics".into()).spawn(move || {
+                for stream in listener.incoming() {
+                    if stopped.load(Ordering::Acquire) {
+                        break;
+                    }
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(e) => {
+                            debug!("Error while accepting metrics connection: {}", e);
+                            continue;
+                        }
+                    };
+                    let Some(slot) = ConnectionSlot::acquire(&connections) else {
+                        debug!("Too many concurrent metrics connections, rejecting one");
+                        let _ = reject(stream);
+                        continue;
+                    };
+                    // a slow client must not hold up other requests, nor shutdown
+                    let metrics = metrics.clone();
+                    let spawned = std::thread::Builder::new().name("metrics-connection".into()).spawn(move || {
+                        let _slot = slot;
+                        if let Err(e) = respond(stream, &metrics) {
+                            debug!("Error while serving metrics: {}", e);
+                        }
+                    });
+                    if let Err(e) = spawned {
+                        debug!("Could not spawn thread to serve metrics: {}", e);
+                    }
+                }
+            })?
+        };
+        info!("Serving metrics on http://{}/metrics", addr);
+        Ok(Self { metrics, addr, stopped, thread: Some(thread) })
+    }
+
+    pub(super) fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// The address the server listens on, which has a
+    /// definite port even if the requested one was zero.
+    #[cfg(test)]
+    pub(super) fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Release);
+        // wake up the thread, which is blocked accepting connections
+        let mut addr = self.addr;
+        if addr.ip().is_unspecified() {
+            addr.set_ip(if addr.is_ipv4() {
+                Ipv4Addr::LOCALHOST.into()
+            } else {
+                Ipv6Addr::LOCALHOST.into()
+            });
+        }
+        if TcpStream::connect(addr).is_ok() {
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+/// Counts a connection thread towards [MAX_CONNECTION_THREADS]
+/// for as long as it is alive.
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    fn acquire(connections: &Arc<AtomicUsize>) -> Option<Self> {
+        connections
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < MAX_CONNECTION_THREADS).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Self(connections.clone()))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Answers without reading the request, so this does not
+/// block the thread accepting connections.
+fn reject(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+    let body = "too many concurrent requests\n";
+    write!(
+        stream,
+        "HTTP/1.1 503 Service Unavailable\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn respond(mut stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // skip the headers, requests have no body
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && header.trim_end() != "" {
+        header.clear();
+    }
+
+    let (status, content_type, body) = if request_line.starts_with("GET ") {
+        ("200 OK", "text/plain; version=0.0.4", metrics.render())
+    } else {
+        ("405 Method Not Allowed", "text/plain", "only GET is supported\n".to_owned())
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::io::Read;
+
+    use super::*;
+    use crate::scheduler::debug::ReactorDebugInfo;
+    use crate::OverflowPolicy;
+
+    fn metrics() -> Metrics {
+        let mut registry = DebugInfoRegistry::new();
+        registry.record_reactor(ReactorId::new(0), ReactorDebugInfo::test_named("main"));
+        registry.record_reactor(ReactorId::new(1), ReactorDebugInfo::test_named("main/sink"));
+        Metrics::new(&registry, ChannelState::new(None, OverflowPolicy::default()))
+    }
+
+    #[test]
+    fn test_render() {
+        let metrics = metrics();
+        metrics.record_tag(Duration::ZERO);
+        metrics.record_tag(Duration::from_micros(500));
+        metrics.record_tag(Duration::from_secs(20));
+        metrics.record_reaction(ReactorId::new(1));
+        metrics.record_reaction(ReactorId::new(1));
+        metrics.record_deadline_violation(ReactorId::new(0));
+        metrics.set_event_queue_len(4);
+
+        let text = metrics.render();
+        for line in [
+            "# TYPE lf_tags_processed_total counter",
+            "lf_tags_processed_total 3",
+            r#"lf_reactions_executed_total{reactor="main/"} 0"#,
+            r#"lf_reactions_executed_total{reactor="main/sink/"} 2"#,
+            r#"lf_deadline_violations_total{reactor="main/"} 1"#,
+            "# TYPE lf_lag_seconds histogram",
+            r#"lf_lag_seconds_bucket{le="0.0001"} 1"#,
+            r#"lf_lag_seconds_bucket{le="0.001"} 2"#,
+            r#"lf_lag_seconds_bucket{le="10"} 2"#,
+            r#"lf_lag_seconds_bucket{le="+Inf"} 3"#,
+            "lf_lag_seconds_sum 20.0005",
+            "lf_lag_seconds_count 3",
+            "lf_async_channel_depth 0",
+            "lf_event_queue_length 4",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {:?} in\n{}", line, text);
+        }
+    }
+
+    #[test]
+    fn test_label_escaping() {
+        let mut out = String::new();
+        escape_label_value(&mut out, "a\"b\\c\nd");
+        assert_eq!(out, r#"a\"b\\c\nd"#);
+    }
+
+    #[test]
+    fn test_scrape_from_localhost() {
+        let server = MetricsServer::start("127.0.0.1:0".parse().unwrap(), metrics()).unwrap();
+        server.metrics().record_tag(Duration::ZERO);
+
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("Content-Type: text/plain; version=0.0.4\r\n"));
+        assert!(response.ends_with(&server.metrics().render()));
+        assert!(response.contains("\nlf_tags_processed_total 1\n"));
+        // stops the thread
+        drop(server);
+    }
+
+    #[test]
+    fn test_stalled_client_does_not_block() {
+        let server = MetricsServer::start("127.0.0.1:0".parse().unwrap(), metrics()).unwrap();
+        // connects, but never sends its request
+        let _stalled = TcpStream::connect(server.local_addr()).unwrap();
+
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+
+        let start = std::time::Instant::now();
+        drop(server);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_connection_threads_are_bounded() {
+        let server = MetricsServer::start("127.0.0.1:0".parse().unwrap(), metrics()).unwrap();
+        // each holds a connection thread until the read timeout
+        let stalled: Vec<_> = (0..MAX_CONNECTION_THREADS)
+            .map(|_| TcpStream::connect(server.local_addr()).unwrap())
+            .collect();
+
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"), "{}", response);
+
+        // closing the stalled connections frees their slots
+        drop(stalled);
+        let mut response = String::new();
+        for _ in 0..100 {
+            let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+            stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+            response.clear();
+            let _ = stream.read_to_string(&mut response);
+            if response.starts_with("HTTP/1.1 200 OK\r\n") {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("slots were not freed, last response: {}", response);
+    }
+}
@@ -25,20 +25,36 @@
 use std::borrow::Cow;
 use std::fmt::Display;
 
+pub use async_channel::{AsyncChannelStats, OverflowPolicy};
+pub use async_rt::{AsyncTimer, SchedulerHandle};
+pub use blocking_pool::BlockingShutdown;
 pub use context::*;
+pub use debug::ElementId;
+pub use debugger::{Breakpoint, DebuggerInput, TriggerInspector};
 pub use events::*;
+pub use graph_export::GraphFormat;
 use index_vec::IndexVec;
+pub use reaction_log::ReactionLogFormat;
 pub use scheduler_impl::*;
+pub use zeno::{ZenoError, ZenoPolicy};
 
 use self::dependencies::ExecutableReactions;
 use crate::*;
 
 pub(crate) mod assembly_impl;
+mod async_channel;
+mod async_rt;
+mod blocking_pool;
 mod context;
 pub(crate) mod debug;
+mod debugger;
 mod dependencies;
 mod events;
+mod graph_export;
+mod metrics;
+mod reaction_log;
 mod scheduler_impl;
+mod zeno;
 
 #[cfg(feature = "public-internals")]
 pub mod internals {
@@ -57,10 +73,9 @@ pub(self) struct DebugInfoProvider<'a> {
 
 impl DebugInfoProvider<'_> {
     pub(self) fn display_event(&self, evt: &Event) -> String {
-        let Event { tag, reactions, terminate } = evt;
-        let mut str = format!("at {}: run {}", tag, self.display_reactions(reactions));
+        let mut str = format!("at {}: run {}", evt.tag, self.display_reactions(&evt.pending_reactions()));
 
-        if *terminate {
+        if evt.terminate {
             str += ", then terminate"
         }
         str
@@ -86,6 +101,16 @@ impl DebugInfoProvider<'_> {
         str
     }
 
+    /// Returns the names of the given reactions, in execution order.
+    pub(self) fn reaction_names(&self, reactions: &ReactionPlan) -> Vec<String> {
+        reactions
+            .iter()
+            .flat_map(|reactions| reactions.batches())
+            .flat_map(|(_, batch)| batch.iter())
+            .map(|id| self.display_reaction(id).to_string())
+            .collect()
+    }
+
     #[inline]
     pub(self) fn display_reaction(&self, id: GlobalReactionId) -> impl Display + '_ {
         self.id_registry.fmt_reaction(id)
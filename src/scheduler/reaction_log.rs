@@ -0,0 +1,245 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+//! Logging from reactions, see [ReactionCtx::log](crate::ReactionCtx::log).
+
+use std::fmt::{Arguments, Write as _};
+use std::io::Write as _;
+
+use log::{Level, LevelFilter};
+
+use crate::{format_duration, EventTag};
+
+/// Format of the messages logged by reactions with
+/// [ReactionCtx::log](crate::ReactionCtx::log), see
+/// [SchedulerOptions::reaction_log_format](crate::SchedulerOptions::reaction_log_format).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ReactionLogFormat {
+    /// Forward messages to the [log](mod@log) crate, with target
+    /// `reaction`, prefixed with the tag and the reaction.
+    /// Messages are then filtered and written by the installed
+    /// logger, eg `env_logger`.
+    #[default]
+    Log,
+    /// Write one line per message to stderr, like
+    /// `INFO  (T0 + 1 ms, 0) /child/0@tick: message`.
+    Text,
+    /// Write one JSON object per line to stderr, with fields
+    /// `level`, `time_ns` (elapsed since startup), `time` (the
+    /// same, formatted like `1.5 ms`), `microstep`, `timestamp_ns`
+    /// (since the Unix epoch), `reactor` (the instance path),
+    /// `reaction` (label or index) and `message`.
+    Json,
+}
+
+impl std::str::FromStr for ReactionLogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "log" => Ok(ReactionLogFormat::Log),
+            "text" => Ok(ReactionLogFormat::Text),
+            "json" => Ok(ReactionLogFormat::Json),
+            _ => Err(format!(
+                "unknown reaction log format '{}', expected one of log, text, json",
+                s
+            )),
+        }
+    }
+}
+
+/// How the messages of reactions are written, see
+/// [SchedulerOptions::reaction_log_format](crate::SchedulerOptions::reaction_log_format).
+/// Each scheduler has its own, so that programs that share a
+/// process may log differently.
+#[derive(Copy, Clone, Debug)]
+pub(super) struct ReactionLogConfig {
+    format: ReactionLogFormat,
+    /// Max level of messages written with the [Text](ReactionLogFormat::Text)
+    /// and [Json](ReactionLogFormat::Json) formats.
+    max_level: LevelFilter,
+}
+
+impl Default for ReactionLogConfig {
+    fn default() -> Self {
+        Self::new(ReactionLogFormat::default(), None)
+    }
+}
+
+impl ReactionLogConfig {
+    pub(super) fn new(format: ReactionLogFormat, log_level: Option<LevelFilter>) -> Self {
+        Self {
+            format,
+            max_level: log_level.unwrap_or(LevelFilter::Info),
+        }
+    }
+
+    /// Whether a message at the given level would be written,
+    /// so that callers can skip building the record otherwise.
+    pub(super) fn enabled(&self, level: Level) -> bool {
+        match self.format {
+            ReactionLogFormat::Log => log::log_enabled!(target: "reaction", level),
+            _ => level <= self.max_level,
+        }
+    }
+
+    /// Write the record in the configured format.
+    pub(super) fn emit(&self, record: &ReactionLogRecord) {
+        match self.format {
+            ReactionLogFormat::Log => {
+                log!(target: "reaction", record.level, "{} {}: {}", record.tag, record.fmt_reaction(), record.message)
+            }
+            format if record.level <= self.max_level => {
+                let line = if format == ReactionLogFormat::Json {
+                    record.to_json()
+                } else {
+                    record.to_text()
+                };
+                // a single write, so that lines of parallel reactions don't interleave
+                let _ = std::io::stderr().lock().write_all(line.as_bytes());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A message logged by a reaction.
+pub(super) struct ReactionLogRecord<'a> {
+    pub level: Level,
+    pub tag: EventTag,
+    /// Nanoseconds since the Unix epoch of the tag.
+    pub timestamp_ns: i64,
+    /// Instance path of the reactor, with a trailing slash.
+    pub reactor: String,
+    /// Index of the reaction in its reactor.
+    pub reaction_index: usize,
+    pub reaction_label: Option<&'a str>,
+    pub message: Arguments<'a>,
+}
+
+impl ReactionLogRecord<'_> {
+    fn fmt_reaction(&self) -> String {
+        match self.reaction_label {
+            Some(label) => format!("{}{}@{}", self.reactor, self.reaction_index, label),
+            None => format!("{}{}", self.reactor, self.reaction_index),
+        }
+    }
+
+    pub(super) fn to_text(&self) -> String {
+        format!("{:<5} {} {}: {}\n", self.level, self.tag, self.fmt_reaction(), self.message)
+    }
+
+    pub(super) fn to_json(&self) -> String {
+        let mut line = String::new();
+        write!(
+            line,
+            r#"{{"level":"{}","time_ns":{},"time":"{}","microstep":{},"timestamp_ns":{},"reactor":"#,
+            self.level,
+            self.tag.duration_since_start().as_nanos(),
+            format_duration(self.tag.duration_since_start()),
+            self.tag.microstep(),
+            self.timestamp_ns,
+        )
+        .unwrap();
+        write_json_str(&mut line, &self.reactor);
+        line.push_str(r#","reaction":"#);
+        match self.reaction_label {
+            Some(label) => write_json_str(&mut line, label),
+            None => write!(line, "{}", self.reaction_index).unwrap(),
+        }
+        line.push_str(r#","message":"#);
+        write_json_str(&mut line, &self.message.to_string());
+        line.push_str("}\n");
+        line
+    }
+}
+
+fn write_json_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::tag;
+
+    fn record<'a>(label: Option<&'a str>, message: Arguments<'a>) -> ReactionLogRecord<'a> {
+        ReactionLogRecord {
+            level: Level::Info,
+            tag: tag!(T0 + 1500 us, 2),
+            timestamp_ns: 1_000_001_500_000,
+            reactor: "/child[1]/".into(),
+            reaction_index: 0,
+            reaction_label: label,
+            message,
+        }
+    }
+
+    #[test]
+    fn test_level_filter_of_each_config() {
+        let text = ReactionLogConfig::new(ReactionLogFormat::Text, Some(LevelFilter::Warn));
+        let json = ReactionLogConfig::new(ReactionLogFormat::Json, None);
+        assert!(text.enabled(Level::Warn));
+        assert!(!text.enabled(Level::Info));
+        assert!(json.enabled(Level::Info));
+        assert!(!json.enabled(Level::Debug));
+    }
+
+    #[test]
+    fn test_text_format() {
+        let line = record(Some("tick"), format_args!("count={}", 3)).to_text();
+        assert_eq!(line, "INFO  (T0 + 1.5 ms, 2) /child[1]/0@tick: count=3\n");
+        let line = record(None, format_args!("hi")).to_text();
+        assert_eq!(line, "INFO  (T0 + 1.5 ms, 2) /child[1]/0: hi\n");
+    }
+
+    #[test]
+    fn test_json_format() {
+        let line = record(Some("tick"), format_args!("say \"hi\"\n")).to_json();
+        assert_eq!(
+            line,
+            concat!(
+                r#"{"level":"INFO","time_ns":1500000,"time":"1.5 ms","microstep":2,"timestamp_ns":1000001500000,"#,
+                r#""reactor":"/child[1]/","reaction":"tick","message":"say \"hi\"\n"}"#,
+                "\n"
+            )
+        );
+        let line = record(None, format_args!("")).to_json();
+        assert!(line.contains(r#""reaction":0,"#));
+    }
+}
@@ -24,23 +24,50 @@
 
 //! Home of the scheduler component.
 
+use std::future::Future;
+use std::net::SocketAddr;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use crossbeam_channel::reconnectable::*;
+use crossbeam_channel::reconnectable::RecvTimeoutError;
 use crossbeam_utils::thread::{scope, Scope};
 
 use super::assembly_impl::RootAssembler;
+use super::async_channel::{AsyncChannel, ChannelState, Wakeup};
+use super::async_rt::yield_now;
+use super::blocking_pool::BlockingPool;
+use super::debugger::{Debugger, PauseCtx, PausePoint};
+use super::metrics::{Metrics, MetricsServer};
+use super::reaction_log::ReactionLogConfig;
 use super::*;
 use crate::assembly::*;
 use crate::scheduler::dependencies::DataflowInfo;
+use crate::time::MS;
 use crate::*;
 
 /// Construction parameters for the scheduler.
 ///
 /// LFC uses target properties to set them. With the "cli"
 /// feature, generated programs also feature CLI options to
-/// override the defaults at runtime.
+/// override the defaults at runtime (see `reactor_rt::Cli`).
+///
+/// New options may be added in any release, so struct
+/// literals must end with `..Default::default()`, which
+/// leaves the options they don't name at their default:
+/// ```
+/// # use std::time::Duration;
+/// # use reactor_rt::SchedulerOptions;
+/// let options = SchedulerOptions {
+///     keep_alive: false,
+///     timeout: Some(Duration::from_secs(1)),
+///     threads: 0,
+///     dump_graph: false,
+///     ..Default::default()
+/// };
+/// assert!(!options.fast);
+/// ```
 #[derive(Default)]
 pub struct SchedulerOptions {
     /// If true, we won't shut down the scheduler as soon as
@@ -52,6 +79,7 @@ pub struct SchedulerOptions {
     /// Timeout of reactor execution. If provided, the reactor
     /// program will be shut down *at the latest* at `T0 + timeout`.
     /// Calls to `request_stop` may make the program terminate earlier.
+    /// A timeout of [Duration::MAX] (`forever`) is the same as none.
     pub timeout: Option<Duration>,
 
     /// Max number of threads to use in the thread pool.
@@ -59,9 +87,90 @@ pub struct SchedulerOptions {
     /// building with feature `parallel-runtime`.
     pub threads: usize,
 
+    /// If true, the scheduler does not wait for physical time
+    /// to catch up with the logical time of events, and executes
+    /// them as fast as possible instead. Logical time may then
+    /// be ahead of physical time, in which case physical actions
+    /// are tagged just after the current logical time.
+    pub fast: bool,
+
+    /// If set, overrides the maximum log level for this program.
+    /// Note that the logs are only output if a logger has
+    /// been installed, for instance with `env_logger`.
+    pub log_level: Option<log::LevelFilter>,
+
+    /// Capacity of the channel through which asynchronous
+    /// events, like those of physical actions, are sent to the
+    /// scheduler. If None, the channel is unbounded, which means
+    /// a thread that schedules physical actions faster than they
+    /// are processed makes memory usage grow without limit.
+    pub async_capacity: Option<usize>,
+
+    /// What to do when a physical action is scheduled while the
+    /// asynchronous channel is full, see [Self::async_capacity].
+    pub async_overflow: OverflowPolicy,
+
+    /// Max number of threads executing the jobs submitted with
+    /// [ReactionCtx::spawn_blocking]. If zero, uses one thread
+    /// per core. Threads are only started when needed.
+    pub blocking_threads: usize,
+
+    /// What to do with blocking jobs that are still pending
+    /// at shutdown.
+    pub blocking_shutdown: BlockingShutdown,
+
+    /// Format of the messages logged by reactions, see
+    /// [ReactionCtx::log]. With the [Text](ReactionLogFormat::Text)
+    /// and [Json](ReactionLogFormat::Json) formats, messages
+    /// are filtered by [Self::log_level], or at level info if
+    /// it is not set.
+    pub reaction_log_format: ReactionLogFormat,
+
+    /// Max microstep of a tag. If the scheduler is about to
+    /// process a tag with a greater microstep, it considers that
+    /// the program exhibits Zeno behavior, and reacts according
+    /// to [Self::on_zeno]. If None, this only happens when
+    /// microsteps are about to overflow.
+    pub max_microsteps: Option<u32>,
+
+    /// What to do when the bound [Self::max_microsteps] is
+    /// exceeded.
+    pub on_zeno: ZenoPolicy,
+
+    /// If set, the scheduler maintains metrics, like the number
+    /// of processed tags and executed reactions, or the lag of
+    /// physical time behind logical time, and serves them in the
+    /// Prometheus text format over HTTP on this address, for
+    /// instance `127.0.0.1:9464`. Missed deadlines are counted
+    /// by [ReactionCtx::check_deadline].
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// If set, the program runs under an interactive debugger,
+    /// which reads commands from the given input. It pauses at
+    /// the [breakpoints](Self::breakpoints), or at startup if
+    /// there are none. Type `help` for a list of commands. While
+    /// debugging, reactions are never executed in parallel.
+    ///
+    /// The debugger blocks the scheduler thread while it reads
+    /// commands, so it is not supported by [SyncScheduler::run_main_async],
+    /// which would stall the executor.
+    pub debugger: Option<DebuggerInput>,
+
+    /// Initial breakpoints of the [debugger](Self::debugger).
+    pub breakpoints: Vec<Breakpoint>,
+
     /// If true, dump the dependency graph to a file before
     /// starting execution.
     pub dump_graph: bool,
+
+    /// Format of the dependency graph dump.
+    pub dump_graph_format: GraphFormat,
+
+    /// Path of the file the dependency graph is dumped to. By
+    /// default, this is `reactors.<ext>` in the temp directory,
+    /// where `<ext>` is the [extension](GraphFormat::extension)
+    /// of the format.
+    pub dump_graph_path: Option<PathBuf>,
 }
 
 // Macros are placed a bit out of order to avoid exporting them
@@ -100,8 +209,9 @@ where
     dataflow: &'x DataflowInfo,
 
     /// Can spawn scoped threads, which are used for threads
-    /// producing physical actions.
-    thread_spawner: &'a Scope<'t>,
+    /// producing physical actions. None if the scheduler runs
+    /// asynchronously, see [Self::run_main_async].
+    thread_spawner: Option<&'a Scope<'t>>,
 
     /// All reactors.
     reactors: ReactorVec<'x>,
@@ -109,10 +219,10 @@ where
     /// Pending events/ tags to process.
     event_queue: EventQueue<'x>,
 
-    /// Receiver through which asynchronous events are
+    /// Channel through which asynchronous events are
     /// communicated to the scheduler. We only block when
     /// no events are ready to be processed.
-    rx: Receiver<Event<'x>>,
+    channel: AsyncChannel<'x>,
 
     /// Initial time of the logical system.
     #[allow(unused)] // might be useful someday
@@ -131,8 +241,26 @@ where
     /// scheduler only.
     was_terminated: Arc<AtomicBool>,
 
+    /// Whether to ignore physical time, see [SchedulerOptions::fast].
+    fast: bool,
+
+    /// Max microstep of a processed tag, see [SchedulerOptions::max_microsteps].
+    max_microstep: MicroStep,
+
+    /// See [SchedulerOptions::on_zeno].
+    on_zeno: ZenoPolicy,
+
+    /// Serves metrics, see [SchedulerOptions::metrics_addr].
+    metrics: Option<MetricsServer>,
+
+    /// See [SchedulerOptions::debugger].
+    debugger: Option<Debugger>,
+
+    /// See [SchedulerOptions::reaction_log_format].
+    reaction_log: ReactionLogConfig,
+
     /// Debug information.
-    id_registry: DebugInfoRegistry,
+    id_registry: Arc<DebugInfoRegistry>,
 }
 
 impl<'a, 'x, 't> SyncScheduler<'a, 'x, 't>
@@ -140,26 +268,7 @@ where
     'x: 't,
 {
     pub fn run_main<R: ReactorInitializer + 'static>(options: SchedulerOptions, args: R::Params) {
-        let start = Instant::now();
-        info!("Starting assembly...");
-        let (reactors, graph, id_registry) = RootAssembler::assemble_tree::<R>(args);
-        let time = Instant::now() - start;
-        info!("Assembly done in {} µs...", time.as_micros());
-
-        if options.dump_graph {
-            use std::fs::File;
-            use std::io::Write;
-
-            let path = std::env::temp_dir().join("reactors.dot");
-
-            File::create(path.clone())
-                .and_then(|mut dot_file| writeln!(dot_file, "{}", graph.format_dot(&id_registry)))
-                .expect("Error while writing DOT file");
-            eprintln!("Wrote dot file to {}", path.to_string_lossy());
-        }
-
-        // collect dependency information
-        let dataflow_info = DataflowInfo::new(graph).map_err(|e| e.lift(&id_registry)).unwrap();
+        let (reactors, id_registry, dataflow_info) = assemble_program::<R>(&options, args);
 
         // Using thread::scope here introduces an unnamed lifetime for
         // the scope, which is captured as 't by the SyncScheduler.
@@ -168,11 +277,19 @@ where
         // can be spawned in threads that capture references
         // to 'x.
         scope(|scope| {
-            let initial_time = Instant::now();
             #[cfg(feature = "parallel-runtime")]
             let rayon_thread_pool = rayon::ThreadPoolBuilder::new().num_threads(options.threads).build().unwrap();
 
-            let scheduler = SyncScheduler::new(options, id_registry, &dataflow_info, scope, reactors, initial_time);
+            let channel = ChannelState::new(options.async_capacity, options.async_overflow);
+            let scheduler = SyncScheduler::new(
+                options,
+                id_registry,
+                &dataflow_info,
+                Some(scope),
+                reactors,
+                channel,
+                Default::default(),
+            );
 
             cfg_if::cfg_if! {
                 if #[cfg(feature = "parallel-runtime")] {
@@ -193,6 +310,72 @@ where
         .unwrap();
     }
 
+    /// Assemble the program and return a future that runs it,
+    /// and a handle to schedule physical actions or request the
+    /// program to stop from other tasks. This is the async
+    /// counterpart of [Self::run_main]: instead of blocking the
+    /// current thread, the event loop sleeps with the given
+    /// [AsyncTimer], and wakes up when an event is sent with the
+    /// handle. The program starts (T0 is fixed) when the future
+    /// is first polled.
+    ///
+    /// The future is not [Send], as reactors are not. It can be
+    /// awaited next to other futures, or spawned on a local task
+    /// set, for instance with tokio:
+    ///
+    /// ```ignore
+    /// let timer = |deadline: Instant| tokio::time::sleep_until(deadline.into());
+    /// let (handle, program) = SyncScheduler::run_main_async::<MainReactor, _>(options, (), timer);
+    /// tokio::task::LocalSet::new().run_until(program).await;
+    /// ```
+    ///
+    /// Reactions cannot use [ReactionCtx::spawn_physical_thread],
+    /// physical actions should be scheduled with the handle instead,
+    /// see [SchedulerHandle::physical_action].
+    /// This panics if a reaction declared it spawns threads with
+    /// [DependencyDeclarator::declare_spawns_threads], or if the
+    /// [debugger](SchedulerOptions::debugger) is enabled.
+    /// With feature `parallel-runtime`, reactions are executed in
+    /// the global rayon thread pool.
+    pub fn run_main_async<R: ReactorInitializer + 'static, T: AsyncTimer>(
+        options: SchedulerOptions,
+        args: R::Params,
+        timer: T,
+    ) -> (SchedulerHandle, impl Future<Output = ()>) {
+        if cfg!(feature = "parallel-runtime") && options.threads != 0 {
+            warn!("'workers' runtime parameter has no effect with run_main_async")
+        }
+        if let Some(input) = &options.debugger {
+            panic!(
+                "The debugger ({}) is not supported with run_main_async, as it would block the executor",
+                input
+            );
+        }
+
+        let (reactors, id_registry, dataflow_info) = assemble_program::<R>(&options, args);
+        if !dataflow_info.thread_spawners().is_empty() {
+            let reactions: Vec<_> = dataflow_info
+                .thread_spawners()
+                .iter()
+                .map(|&r| id_registry.fmt_reaction(r).to_string())
+                .collect();
+            panic!(
+                "Reactions {} may spawn physical threads, which is not supported with run_main_async. \
+                 Schedule physical actions with the SchedulerHandle instead.",
+                reactions.join(", ")
+            );
+        }
+        let channel = ChannelState::new(options.async_capacity, options.async_overflow);
+        let was_terminated = Arc::new(AtomicBool::new(false));
+        let handle = SchedulerHandle::new(channel.clone(), was_terminated.clone(), id_registry.clone());
+
+        let program = async move {
+            let scheduler = SyncScheduler::new(options, id_registry, &dataflow_info, None, reactors, channel, was_terminated);
+            scheduler.launch_event_loop_async(timer).await
+        };
+        (handle, program)
+    }
+
     /// Launch the event loop in this thread.
     fn launch_event_loop(mut self) {
         /************************************************
@@ -202,54 +385,218 @@ where
         self.startup();
 
         loop {
-            // flush pending events, this doesn't block
-            for evt in self.rx.try_iter() {
-                push_event!(self, evt);
-            }
+            match self.next_step() {
+                Step::Process(evt, target) => {
+                    let evt = match self.catch_up_physical_time(target) {
+                        Ok(_) => evt,
+                        Err(async_event) => match self.interrupted(evt, async_event) {
+                            Some(evt) => evt,
+                            None => continue,
+                        },
+                    };
+                    // at this point we're at the correct time
 
-            if let Some(evt) = self.event_queue.take_earliest() {
-                if self.is_after_shutdown(evt.tag) {
-                    trace!("Event is late, shutting down - event tag: {}", evt.tag);
+                    if self.start_event(evt).is_break() {
+                        return;
+                    }
+                }
+                Step::Wait(deadline) => {
+                    // this may block
+                    if let Some(evt) = self.receive_event(deadline) {
+                        push_event!(self, evt);
+                        continue;
+                    }
+                    // all senders have hung up, or timeout
+                    info!("Event queue is empty forever, shutting down.");
                     break;
                 }
-                trace!("Processing event {}", self.debug().display_event(&evt));
-                match self.catch_up_physical_time(evt.tag.to_logical_time(self.initial_time)) {
-                    Ok(_) => {}
-                    Err(async_event) => {
-                        // an asynchronous event woke our sleep
-                        if async_event.tag < evt.tag {
-                            // reinsert both events to order them and try again.
-                            push_event!(self, evt);
-                            push_event!(self, async_event);
-                            continue;
-                        } else {
-                            // we can process this event first and not care about the async event
-                            push_event!(self, async_event);
+                Step::Shutdown => break,
+            }
+        } // end loop
+
+        self.finish();
+        // self destructor is called here
+    }
+
+    /// Launch the event loop as a future, see [Self::run_main_async].
+    /// This mirrors [Self::launch_event_loop], but waits with the
+    /// given timer instead of blocking.
+    async fn launch_event_loop_async<T: AsyncTimer>(mut self, timer: T) {
+        self.startup();
+
+        loop {
+            match self.next_step() {
+                Step::Process(evt, target) => {
+                    let evt = if !self.fast && Instant::now() < target {
+                        trace!("  - Need to sleep until {:?}", target);
+                        let sleep = timer.sleep_until(target);
+                        match self.channel.recv_async(Some(sleep), false).await {
+                            Wakeup::Event(async_event) => match self.interrupted(evt, async_event) {
+                                Some(evt) => evt,
+                                None => continue,
+                            },
+                            Wakeup::Timeout | Wakeup::Disconnected => evt,
                         }
+                    } else {
+                        // let other tasks run, as we may not wait at all
+                        yield_now().await;
+                        evt
+                    };
+
+                    if self.start_event(evt).is_break() {
+                        return;
                     }
-                };
-                // at this point we're at the correct time
+                }
+                Step::Wait(deadline) => {
+                    trace!("Will wait for asynchronous event");
+                    let sleep = deadline.map(|deadline| timer.sleep_until(deadline));
+                    if let Wakeup::Event(evt) = self.channel.recv_async(sleep, true).await {
+                        push_event!(self, evt);
+                        continue;
+                    }
+                    // all handles have been dropped, or timeout
+                    info!("Event queue is empty forever, shutting down.");
+                    break;
+                }
+                Step::Shutdown => break,
+            }
+        }
+
+        self.finish();
+    }
+
+    /// Flush asynchronous events into the queue and decide what
+    /// the event loop does next. This is the part of the loop that
+    /// does not block, shared by [Self::launch_event_loop] and
+    /// [Self::launch_event_loop_async].
+    fn next_step(&mut self) -> Step<'x> {
+        // flush pending events, this doesn't block
+        while let Some(evt) = self.channel.try_recv() {
+            push_event!(self, evt);
+        }
+        self.publish_queue();
+
+        if let Some(evt) = self.event_queue.take_earliest() {
+            if self.is_after_shutdown(evt.tag) {
+                trace!("Event is late, shutting down - event tag: {}", evt.tag);
+                return Step::Shutdown;
+            }
+            trace!("Processing event {}", self.debug().display_event(&evt));
+            let target = evt.tag.to_logical_time(self.initial_time);
+            return Step::Process(evt, target);
+        }
+
+        let deadline = self.shutdown_time.map(|t| t.to_logical_time(self.initial_time));
+        if deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+            trace!("Cannot wait, already past programmed shutdown time...");
+            info!("Event queue is empty forever, shutting down.");
+            Step::Shutdown
+        } else {
+            Step::Wait(deadline)
+        }
+    }
 
-                if evt.terminate || self.shutdown_time == Some(evt.tag) {
-                    return self.shutdown(evt.tag, evt.reactions);
+    /// Deal with an asynchronous event received while waiting
+    /// for physical time to catch up with the given event.
+    /// Returns the event if it can still be processed first.
+    fn interrupted(&mut self, evt: Event<'x>, async_event: Event<'x>) -> Option<Event<'x>> {
+        if async_event.tag < evt.tag {
+            // reinsert both events to order them and try again.
+            push_event!(self, evt);
+            push_event!(self, async_event);
+            None
+        } else {
+            // we can process this event first and not care about the async event
+            push_event!(self, async_event);
+            Some(evt)
+        }
+    }
+
+    /// Process the given event, whose time has come. Breaks
+    /// if the scheduler has been shut down.
+    fn start_event(&mut self, evt: Event<'x>) -> ControlFlow<()> {
+        // Asynchronous events are tagged after the latest tag
+        // we started, but one may have been sent with an
+        // earlier tag than this one since we last looked.
+        let mut received = Vec::new();
+        let can_start = self.channel.start_tag(evt.tag, &mut received);
+        for async_event in received {
+            push_event!(self, async_event);
+        }
+        if !can_start {
+            push_event!(self, evt);
+            return ControlFlow::Continue(());
+        }
+
+        let mut evt = evt;
+        self.fire_timers(&mut evt);
+
+        if evt.tag.microstep() > self.max_microstep {
+            self.on_zeno(evt);
+            return ControlFlow::Break(());
+        }
+
+        if evt.terminate || self.shutdown_time == Some(evt.tag) {
+            self.shutdown(evt.tag, evt.reactions);
+            return ControlFlow::Break(());
+        }
+
+        self.process_tag(false, evt.tag, evt.reactions);
+        ControlFlow::Continue(())
+    }
+
+    /// Fire the timers of the event that have not been cancelled,
+    /// adding their reactions to those of the event, and schedule
+    /// their next firing.
+    fn fire_timers(&mut self, evt: &mut Event<'x>) {
+        for (firing, reactions) in std::mem::take(&mut evt.timers) {
+            match firing.fire() {
+                Ok(next) => {
+                    evt.reactions = ExecutableReactions::merge_cows(evt.reactions.take(), Some(Cow::Borrowed(reactions)));
+                    if let Some(next) = next {
+                        let next = Event::timer(next, reactions);
+                        push_event!(self, next);
+                    }
                 }
+                Err(()) => trace!("Timer firing at {} was cancelled", firing.tag()),
+            }
+        }
+    }
 
-                self.process_tag(false, evt.tag, evt.reactions);
-            } else if let Some(evt) = self.receive_event() {
-                // this may block
-                push_event!(self, evt);
-                continue;
-            } else {
-                // all senders have hung up, or timeout
-                info!("Event queue is empty forever, shutting down.");
-                break;
+    /// Handle an event whose microstep exceeds the bound,
+    /// according to the [ZenoPolicy].
+    fn on_zeno(&mut self, evt: Event<'x>) {
+        let error = ZenoError {
+            tag: evt.tag,
+            reactions: debug_info!(self).reaction_names(&evt.reactions),
+        };
+        match self.on_zeno {
+            ZenoPolicy::Stop => {
+                error!("{}, shutting down", error);
+                self.shutdown(evt.tag, None);
             }
-        } // end loop
+            ZenoPolicy::Panic => {
+                // let concurrent threads terminate
+                self.notify_terminated();
+                std::panic::panic_any(error)
+            }
+        }
+    }
 
+    /// Shut down after the event loop ended without processing
+    /// a shutdown event.
+    fn finish(&mut self) {
         let shutdown_tag = self.shutdown_time.unwrap_or_else(|| EventTag::now(self.initial_time));
         self.shutdown(shutdown_tag, None);
+        self.publish_queue();
+    }
 
-        // self destructor is called here
+    /// Publish the state of the event queue to monitors and metrics.
+    fn publish_queue(&self) {
+        self.event_queue.publish_snapshot(&debug_info!(self));
+        if let Some(server) = &self.metrics {
+            server.metrics().set_event_queue_len(self.event_queue.len());
+        }
     }
 
     /// Creates a new scheduler. An empty scheduler doesn't
@@ -257,11 +604,12 @@ where
     /// See [Self::launch_event_loop].
     fn new(
         options: SchedulerOptions,
-        id_registry: DebugInfoRegistry,
+        id_registry: Arc<DebugInfoRegistry>,
         dependency_info: &'x DataflowInfo,
-        thread_spawner: &'a Scope<'t>,
+        thread_spawner: Option<&'a Scope<'t>>,
         reactors: ReactorVec<'x>,
-        initial_time: Instant,
+        channel: Arc<ChannelState>,
+        was_terminated: Arc<AtomicBool>,
     ) -> Self {
         if !cfg!(feature = "parallel-runtime") && options.threads != 0 {
             warn!("'workers' runtime parameter has no effect unless feature 'parallel-runtime' is enabled")
@@ -271,16 +619,34 @@ where
             warn!("'keepalive' runtime parameter has no effect in the Rust target")
         }
 
-        let (_, rx) = unbounded::<Event<'x>>();
-        Self {
-            rx,
+        let metrics = options.metrics_addr.and_then(|addr| {
+            MetricsServer::start(addr, Metrics::new(&id_registry, channel.clone()))
+                .map_err(|e| error!("Cannot serve metrics on {}: {}", addr, e))
+                .ok()
+        });
+
+        let debugger = options.debugger.map(|input| {
+            Debugger::new(input, &options.breakpoints, &id_registry)
+                .unwrap_or_else(|e| panic!("Cannot start the debugger on {}: {}", input, e))
+        });
 
-            event_queue: Default::default(),
+        let initial_time = channel.start_clock();
+        let event_queue = EventQueue::with_monitor(channel.queue_monitor().clone());
+        Self {
+            channel: AsyncChannel::new(
+                channel,
+                dependency_info,
+                BlockingPool::new(options.blocking_threads, options.blocking_shutdown),
+                id_registry.clone(),
+            ),
+
+            event_queue,
             reactors,
 
             initial_time,
             latest_processed_tag: None,
-            shutdown_time: options.timeout.map(|timeout| {
+            // a timeout of forever is no timeout
+            shutdown_time: options.timeout.filter(|&timeout| timeout != Duration::MAX).map(|timeout| {
                 let shutdown_tag = EventTag::ORIGIN.successor(timeout);
                 trace!("Timeout specified, will shut down at most at tag {}", shutdown_tag);
                 shutdown_tag
@@ -288,7 +654,14 @@ where
             dataflow: dependency_info,
             id_registry,
             thread_spawner,
-            was_terminated: Default::default(),
+            was_terminated,
+            fast: options.fast,
+            // a reaction at the last microstep could not schedule anything at the next one
+            max_microstep: MicroStep::new(options.max_microsteps.unwrap_or(MS::MAX).min(MS::MAX - 1)),
+            on_zeno: options.on_zeno,
+            metrics,
+            debugger,
+            reaction_log: ReactionLogConfig::new(options.reaction_log_format, options.log_level),
         }
     }
 
@@ -311,11 +684,18 @@ where
 
         self.process_tag(true, shutdown_tag, reactions);
 
-        // notify concurrent threads.
-        self.was_terminated.store(true, Ordering::SeqCst);
+        self.notify_terminated();
         info!("Scheduler has been shut down")
     }
 
+    /// Notify concurrent threads that the scheduler has been
+    /// terminated, including those blocked on a full channel.
+    fn notify_terminated(&self) {
+        self.was_terminated.store(true, Ordering::SeqCst);
+        self.channel.notify_terminated();
+        self.channel.blocking_pool().shutdown();
+    }
+
     /// Returns whether the given event should be ignored and
     /// the event loop be terminated. This would be the case
     /// if the tag of the event is later than the projected
@@ -327,25 +707,24 @@ where
 
     /// Wait for an asynchronous event for as long as we can
     /// expect it.
-    fn receive_event(&mut self) -> Option<Event<'x>> {
-        if let Some(shutdown_t) = self.shutdown_time {
-            let absolute = shutdown_t.to_logical_time(self.initial_time);
-            if let Some(timeout) = absolute.checked_duration_since(Instant::now()) {
-                trace!("Will wait for asynchronous event {} ns", timeout.as_nanos());
-                self.rx.recv_timeout(timeout).ok()
-            } else {
-                trace!("Cannot wait, already past programmed shutdown time...");
-                None
-            }
+    fn receive_event(&mut self, deadline: Option<Instant>) -> Option<Event<'x>> {
+        if let Some(deadline) = deadline {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            trace!("Will wait for asynchronous event {} ns", timeout.as_nanos());
+            self.channel.recv_timeout(timeout).ok()
         } else {
             trace!("Will wait for asynchronous event without timeout");
-            self.rx.recv().ok()
+            self.channel.recv().ok()
         }
     }
 
     /// Sleep/wait until the given time OR an asynchronous
     /// event is received first.
     fn catch_up_physical_time(&mut self, target: Instant) -> Result<(), Event<'x>> {
+        if self.fast {
+            return Ok(());
+        }
+
         let now = Instant::now();
 
         if now < target {
@@ -354,7 +733,7 @@ where
             // we use recv_timeout as a thread::sleep so that
             // our sleep is interrupted properly when an async
             // event arrives
-            match self.rx.recv_timeout(t) {
+            match self.channel.recv_timeout(t) {
                 Ok(async_evt) => {
                     trace!(
                         "  - Sleep interrupted by async event for tag {}, going back to queue",
@@ -364,7 +743,7 @@ where
                 }
                 Err(RecvTimeoutError::Timeout) => { /*great*/ }
                 Err(RecvTimeoutError::Disconnected) => {
-                    // ok, there are no physical actions in the program so it's useless to block on self.rx
+                    // ok, there are no physical actions in the program so it's useless to block on self.channel
                     // we still need to wait though..
                     if let Some(remaining) = target.checked_duration_since(Instant::now()) {
                         std::thread::sleep(remaining);
@@ -391,13 +770,15 @@ where
         &self,
         tag: EventTag,
         todo: ReactionPlan<'x>,
-        rx: &'a Receiver<Event<'x>>,
+        channel: &'a AsyncChannel<'x>,
+        event_queue: &'a EventQueue<'x>,
         debug_info: DebugInfoProvider<'a>,
         was_terminated_atomic: &'a Arc<AtomicBool>,
         was_terminated: bool,
     ) -> ReactionCtx<'a, 'x, 't> {
         ReactionCtx::new(
-            rx,
+            channel,
+            event_queue,
             tag,
             self.initial_time,
             todo,
@@ -424,12 +805,50 @@ where
         }
         self.latest_processed_tag = Some(tag);
 
+        // entered before creating the context, which records it as the parent of reaction spans
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("tag", tag = %tag, shutdown = is_shutdown).entered();
+
+        if let Some(server) = &self.metrics {
+            let lag = Instant::now().saturating_duration_since(tag.to_logical_time(self.initial_time));
+            server.metrics().record_tag(lag);
+        }
+
         let mut next_level = reactions.as_ref().and_then(|todo| todo.first_batch());
         if next_level.is_none() {
+            // Values of delayed connections may be delivered at
+            // a tag that triggers no reaction, they still need to
+            // be forgotten.
+            self.cleanup_tag(tag);
             return;
         }
 
-        let mut ctx = self.new_reaction_ctx(tag, None, &self.rx, debug_info!(self), &self.was_terminated, is_shutdown);
+        if let Some(debugger) = &self.debugger {
+            if debugger.pauses_at_tag(tag) {
+                let reactors = &self.reactors;
+                debugger.pause(&PauseCtx {
+                    point: PausePoint::Tag(tag),
+                    debug: &debug_info!(self),
+                    event_queue: &self.event_queue,
+                    start: self.initial_time,
+                    inspect: &|inspector| reactors.iter().for_each(|r| r.inspect(inspector)),
+                    inspectable: None,
+                });
+            }
+        }
+
+        let mut ctx = self.new_reaction_ctx(
+            tag,
+            None,
+            &self.channel,
+            &self.event_queue,
+            debug_info!(self),
+            &self.was_terminated,
+            is_shutdown,
+        );
+        ctx.metrics = self.metrics.as_ref().map(MetricsServer::metrics);
+        ctx.debugger = self.debugger.as_ref();
+        ctx.reaction_log = self.reaction_log;
 
         while let Some((level_no, batch)) = next_level {
             let level_no = level_no.cloned();
@@ -441,7 +860,8 @@ where
             /// TODO experiment with tweaking this
             const PARALLEL_THRESHOLD: usize = 3;
 
-            if cfg!(feature = "parallel-runtime") && batch.len() >= PARALLEL_THRESHOLD {
+            // the debugger pauses reactions one at a time
+            if cfg!(feature = "parallel-runtime") && batch.len() >= PARALLEL_THRESHOLD && self.debugger.is_none() {
                 #[cfg(feature = "parallel-runtime")]
                 parallel_rt_impl::process_batch(&mut ctx, &mut self.reactors, batch);
             } else {
@@ -456,11 +876,18 @@ where
             next_level = reactions.as_ref().and_then(|todo| todo.next_batch(level_no.as_ref()));
         }
 
-        for evt in ctx.insides.future_events.drain(..) {
+        // the context borrows the event queue, it must be dropped before pushing
+        let future_events = std::mem::take(&mut ctx.insides.future_events);
+        drop(ctx);
+        for evt in future_events {
             push_event!(self, evt)
         }
 
-        // cleanup tag-specific resources, eg clear port values
+        self.cleanup_tag(tag);
+    }
+
+    /// Cleanup tag-specific resources, eg clear port values.
+    fn cleanup_tag(&mut self, tag: EventTag) {
         let ctx = CleanupCtx { tag };
         // TODO measure performance of cleaning up all reactors w/ virtual dispatch like this.
         //   see also efforts in the C runtime to  avoid this
@@ -470,6 +897,61 @@ where
     }
 }
 
+/// What the event loop does next, see [SyncScheduler::next_step].
+enum Step<'x> {
+    /// Process the event once physical time has caught up
+    /// with the given instant.
+    Process(Event<'x>, Instant),
+    /// The queue is empty, wait for an asynchronous event,
+    /// until the given deadline if any.
+    Wait(Option<Instant>),
+    /// Terminate the event loop.
+    Shutdown,
+}
+
+/// Assemble the reactor program and collect its dependency
+/// information, panicking if there is a problem.
+fn assemble_program<R: ReactorInitializer + 'static>(
+    options: &SchedulerOptions,
+    args: R::Params,
+) -> (ReactorVec<'static>, Arc<DebugInfoRegistry>, DataflowInfo) {
+    if let Some(level) = options.log_level {
+        log::set_max_level(level);
+    }
+    let start = Instant::now();
+    info!("Starting assembly...");
+    let (reactors, graph, id_registry, assembly_result) = RootAssembler::assemble_tree::<R>(args);
+    let time = Instant::now() - start;
+    info!("Assembly done in {} µs...", time.as_micros());
+
+    if options.dump_graph {
+        use std::fs::File;
+        use std::io::Write;
+
+        let format = options.dump_graph_format;
+        let path = options
+            .dump_graph_path
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join(format!("reactors.{}", format.extension())));
+
+        File::create(&path)
+            .and_then(|mut file| write!(file, "{}", graph.export(&id_registry, format)))
+            .expect("Error while writing graph file");
+        eprintln!("Wrote graph file to {}", path.to_string_lossy());
+    }
+
+    // collect dependency information, and report all problems at once
+    let dataflow_info = match (assembly_result, DataflowInfo::new(graph)) {
+        (Ok(()), Ok(dataflow_info)) => dataflow_info,
+        (Ok(()), Err(e)) | (Err(e), Ok(_)) => std::panic::panic_any(e.lift(&id_registry)),
+        (Err(mut e), Err(graph_error)) => {
+            e.merge(graph_error);
+            std::panic::panic_any(e.lift(&id_registry))
+        }
+    };
+    (reactors, Arc::new(id_registry), dataflow_info)
+}
+
 #[cfg(feature = "parallel-runtime")]
 mod parallel_rt_impl {
     use rayon::prelude::*;
@@ -0,0 +1,85 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+use std::fmt::{Display, Formatter};
+
+use crate::EventTag;
+
+/// What to do when the scheduler detects Zeno behavior, that
+/// is, when the microstep of a tag exceeds
+/// [SchedulerOptions::max_microsteps](crate::SchedulerOptions::max_microsteps).
+/// This typically happens when a logical action reschedules
+/// itself with a zero delay forever, so that logical time
+/// never advances.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ZenoPolicy {
+    /// Log an error and shut down the program gracefully at
+    /// the offending tag, instead of executing the reactions
+    /// scheduled at that tag. Shutdown reactions are executed.
+    #[default]
+    Stop,
+    /// Panic with a [ZenoError] payload, which can be recovered
+    /// with [std::panic::catch_unwind] and downcast.
+    Panic,
+}
+
+impl std::str::FromStr for ZenoPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "stop" => Ok(ZenoPolicy::Stop),
+            "panic" => Ok(ZenoPolicy::Panic),
+            _ => Err(format!("unknown zeno policy '{}', expected one of stop, panic", s)),
+        }
+    }
+}
+
+/// Error reported when the microstep of a tag exceeds
+/// the configured bound, see [ZenoPolicy].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ZenoError {
+    /// The tag whose microstep exceeds the bound.
+    pub tag: EventTag,
+    /// Names of the reactions that were scheduled at that
+    /// tag. These are the reactions that keep rescheduling
+    /// events without letting logical time advance.
+    pub reactions: Vec<String>,
+}
+
+impl Display for ZenoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Zeno behavior detected at {}: logical time does not advance, reactions involved: [{}]",
+            self.tag,
+            self.reactions.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ZenoError {}
@@ -23,5 +23,16 @@
  */
 
 pub mod stuff_that_must_compile;
+pub mod test_actions;
+pub mod test_async;
+pub mod test_blocking;
 pub mod test_ports;
+#[cfg(feature = "serde")]
+pub mod test_serde;
+pub mod test_time;
+pub mod test_timeout;
+pub mod test_timers;
+#[cfg(feature = "tracing")]
+pub mod test_tracing;
+pub mod test_zeno;
 pub mod testutil;
@@ -97,3 +97,15 @@ fn cleanup(
     ctx.cleanup_physical_action(phys_action);
     ctx.cleanup_port(port);
 }
+
+fn scheduler_options_with_target_properties() -> SchedulerOptions {
+    // what LFC generates for the target properties, the
+    // other options keep their default
+    SchedulerOptions {
+        keep_alive: false,
+        timeout: None,
+        threads: 0,
+        dump_graph: false,
+        ..Default::default()
+    }
+}
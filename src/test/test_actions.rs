@@ -0,0 +1,225 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+use std::sync::{Arc, Mutex};
+
+use crate::assembly::*;
+use crate::test::testutil::{recorded, TestParams, TestReactor};
+use crate::*;
+
+fn action(min_spacing: Option<MinSpacing>) -> LogicalAction<u32> {
+    LogicalAction::new(TriggerId::FIRST_REGULAR, None, min_spacing)
+}
+
+fn spacing(spacing: Duration, policy: SpacingPolicy) -> Option<MinSpacing> {
+    Some(MinSpacing { spacing, policy })
+}
+
+fn value_at(action: &LogicalAction<u32>, tag: EventTag) -> Option<u32> {
+    action.use_value_ref(&tag, &Instant::now(), |v| v.cloned())
+}
+
+#[test]
+fn scheduling_twice_at_same_tag_replaces_value() {
+    let mut action = action(None);
+    let now = tag!(T0);
+    let outcome = action.0.schedule_future_value(now, tag!(T0 + 1 ms), Some(1));
+    assert_eq!(outcome, ScheduleOutcome::Scheduled(tag!(T0 + 1 ms)));
+    let outcome = action.0.schedule_future_value(now, tag!(T0 + 1 ms), Some(2));
+    assert_eq!(outcome, ScheduleOutcome::Replaced(tag!(T0 + 1 ms)));
+    assert_eq!(value_at(&action, tag!(T0 + 1 ms)), Some(2));
+}
+
+#[test]
+fn min_spacing_defer() {
+    let mut action = action(spacing(Duration::from_millis(10), SpacingPolicy::Defer));
+    let now = tag!(T0);
+    let first = action.0.schedule_future_value(now, tag!(T0 + 1 ms), Some(1));
+    assert_eq!(first, ScheduleOutcome::Scheduled(tag!(T0 + 1 ms)));
+
+    let second = action.0.schedule_future_value(now, tag!(T0 + 2 ms), Some(2));
+    assert_eq!(second, ScheduleOutcome::Deferred(tag!(T0 + 11 ms)));
+    // the next one is spaced from the deferred event
+    let third = action.0.schedule_future_value(now, tag!(T0 + 3 ms), Some(3));
+    assert_eq!(third, ScheduleOutcome::Deferred(tag!(T0 + 21 ms)));
+
+    assert_eq!(value_at(&action, tag!(T0 + 1 ms)), Some(1));
+    assert_eq!(value_at(&action, tag!(T0 + 11 ms)), Some(2));
+    assert_eq!(value_at(&action, tag!(T0 + 21 ms)), Some(3));
+    assert!(!action.is_present(&tag!(T0 + 2 ms), &Instant::now()));
+
+    let fourth = action.0.schedule_future_value(now, tag!(T0 + 40 ms), Some(4));
+    assert_eq!(fourth, ScheduleOutcome::Scheduled(tag!(T0 + 40 ms)));
+}
+
+#[test]
+fn min_spacing_drop() {
+    let mut action = action(spacing(Duration::from_millis(10), SpacingPolicy::Drop));
+    let now = tag!(T0);
+    action.0.schedule_future_value(now, tag!(T0 + 1 ms), Some(1));
+    let outcome = action.0.schedule_future_value(now, tag!(T0 + 5 ms), Some(2));
+    assert_eq!(outcome, ScheduleOutcome::Dropped);
+    assert_eq!(outcome.tag(), None);
+    assert!(!action.is_present(&tag!(T0 + 5 ms), &Instant::now()));
+    assert_eq!(value_at(&action, tag!(T0 + 1 ms)), Some(1));
+}
+
+#[test]
+fn min_spacing_replace() {
+    let mut action = action(spacing(Duration::from_millis(10), SpacingPolicy::Replace));
+    action.0.schedule_future_value(tag!(T0), tag!(T0 + 1 ms), Some(1));
+    // the previous event is pending, its value is replaced
+    let outcome = action.0.schedule_future_value(tag!(T0), tag!(T0 + 5 ms), Some(2));
+    assert_eq!(outcome, ScheduleOutcome::Replaced(tag!(T0 + 1 ms)));
+    assert_eq!(value_at(&action, tag!(T0 + 1 ms)), Some(2));
+
+    // once the previous event has been processed, the new one is deferred
+    action.0.forget_value(&tag!(T0 + 1 ms));
+    let outcome = action.0.schedule_future_value(tag!(T0 + 1 ms), tag!(T0 + 2 ms), Some(3));
+    assert_eq!(outcome, ScheduleOutcome::Deferred(tag!(T0 + 11 ms)));
+}
+
+#[test]
+fn zero_min_spacing_requires_distinct_tags() {
+    let mut action = action(spacing(Duration::ZERO, SpacingPolicy::Defer));
+    let now = tag!(T0);
+    action.0.schedule_future_value(now, tag!(T0, 1), Some(1));
+    let outcome = action.0.schedule_future_value(now, tag!(T0, 1), Some(2));
+    assert_eq!(outcome, ScheduleOutcome::Deferred(tag!(T0, 2)));
+}
+
+#[test]
+fn free_tags_are_distinct_from_pending_events() {
+    let mut action = action(None);
+    let now = tag!(T0);
+    assert_eq!(action.0.first_free_tag(tag!(T0 + 1 ms)), tag!(T0 + 1 ms));
+    action.0.schedule_future_value(now, tag!(T0 + 1 ms), Some(1));
+
+    let tag = action.0.first_free_tag(tag!(T0 + 1 ms));
+    assert_eq!(tag, tag!(T0 + 1 ms, 1));
+    assert_eq!(
+        action.0.schedule_future_value(now, tag, Some(2)),
+        ScheduleOutcome::Scheduled(tag)
+    );
+    assert_eq!(action.0.first_free_tag(tag!(T0 + 1 ms)), tag!(T0 + 1 ms, 2));
+    assert_eq!(value_at(&action, tag!(T0 + 1 ms)), Some(1));
+    assert_eq!(value_at(&action, tag!(T0 + 1 ms, 1)), Some(2));
+}
+
+#[test]
+fn delayed_event_does_not_delay_later_asap_event() {
+    let mut action = action(None);
+    let now = tag!(T0);
+    action.0.schedule_future_value(now, tag!(T0 + 1 h), Some(1));
+
+    let tag = action.0.first_free_tag(tag!(T0 + 1 ms));
+    assert_eq!(tag, tag!(T0 + 1 ms));
+    assert_eq!(
+        action.0.schedule_future_value(now, tag, Some(2)),
+        ScheduleOutcome::Scheduled(tag)
+    );
+    assert_eq!(value_at(&action, tag!(T0 + 1 ms)), Some(2));
+    assert_eq!(value_at(&action, tag!(T0 + 1 h)), Some(1));
+}
+
+/// Outcomes of scheduling the actions in [spaced_actions].
+#[derive(Default)]
+struct Outcomes {
+    logical: Vec<ScheduleOutcome>,
+    physical: Vec<ScheduleOutcome>,
+}
+
+/// At startup, schedules the logical action with a spacing of
+/// 10 ms twice, and from another thread, schedules the physical
+/// action with a spacing of 1 s twice.
+fn spaced_actions(policy: SpacingPolicy, outcomes: Arc<Mutex<Outcomes>>) -> TestParams {
+    TestParams {
+        logical_spacing: spacing(delay!(10 ms), SpacingPolicy::Defer),
+        physical_spacing: spacing(delay!(1 s), policy),
+        spawns_threads: true,
+        on_startup: Some(Box::new(move |ctx, actions| {
+            let first = ctx.schedule_with_v(&mut actions.logical, Some(1), after!(1 ms));
+            let second = ctx.schedule_with_v(&mut actions.logical, Some(2), after!(2 ms));
+            outcomes.lock().unwrap().logical.extend([first, second]);
+
+            let (physical, outcomes) = (actions.physical.clone(), outcomes.clone());
+            ctx.spawn_physical_thread(move |ctx| {
+                // the first event is still pending when the second is scheduled
+                let first = ctx.schedule_physical_with_v(&physical, Some(1), after!(100 ms)).unwrap();
+                let second = ctx.schedule_physical_with_v(&physical, Some(2), Offset::Asap).unwrap();
+                outcomes.lock().unwrap().physical.extend([first, second]);
+            });
+        })),
+        ..Default::default()
+    }
+}
+
+/// Returns the outcomes and the tags and values at which
+/// each action triggered a reaction.
+fn run_spaced_actions(policy: SpacingPolicy) -> (Outcomes, Vec<(&'static str, EventTag, Option<u32>)>) {
+    let outcomes = Arc::new(Mutex::new(Outcomes::default()));
+    let params = spaced_actions(policy, outcomes.clone());
+    let record = params.record.clone();
+    SyncScheduler::run_main::<TestReactor>(SchedulerOptions::default(), params);
+    let outcomes = Arc::try_unwrap(outcomes).ok().unwrap().into_inner().unwrap();
+    (outcomes, recorded(&record))
+}
+
+fn received_by(received: &[(&'static str, EventTag, Option<u32>)], action: &str) -> Vec<(EventTag, Option<u32>)> {
+    received
+        .iter()
+        .filter(|(name, ..)| *name == action)
+        .map(|&(_, tag, value)| (tag, value))
+        .collect()
+}
+
+#[test]
+fn reaction_ctx_schedules_deferred_event_at_deferred_tag() {
+    let (outcomes, received) = run_spaced_actions(SpacingPolicy::Drop);
+    assert_eq!(
+        vec![
+            ScheduleOutcome::Scheduled(tag!(T0 + 1 ms)),
+            ScheduleOutcome::Deferred(tag!(T0 + 11 ms))
+        ],
+        outcomes.logical
+    );
+    assert_eq!(
+        vec![(tag!(T0 + 1 ms), Some(1)), (tag!(T0 + 11 ms), Some(2))],
+        received_by(&received, "logical")
+    );
+}
+
+#[test]
+fn async_ctx_drops_event_within_min_spacing() {
+    let (outcomes, received) = run_spaced_actions(SpacingPolicy::Drop);
+    let tag = assert_matches!(outcomes.physical[..], [ScheduleOutcome::Scheduled(tag), ScheduleOutcome::Dropped] => tag);
+    assert_eq!(vec![(tag, Some(1))], received_by(&received, "physical"));
+}
+
+#[test]
+fn async_ctx_replaces_pending_event_within_min_spacing() {
+    let (outcomes, received) = run_spaced_actions(SpacingPolicy::Replace);
+    let tag = assert_matches!(outcomes.physical[..], [ScheduleOutcome::Scheduled(tag), ScheduleOutcome::Replaced(replaced)] if tag == replaced => tag);
+    assert_eq!(vec![(tag, Some(2))], received_by(&received, "physical"));
+}
@@ -0,0 +1,172 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+//! Runs programs with [SyncScheduler::run_main_async].
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::assembly::TriggerId;
+use crate::test::testutil::{block_on, recorded, thread_timer, TestParams, TestReactor};
+use crate::*;
+
+/// At startup, schedules the logical action 30 ms later.
+/// The physical action is scheduled from outside with a
+/// [SchedulerHandle].
+fn async_main() -> TestParams {
+    TestParams {
+        on_startup: Some(Box::new(|ctx, actions| {
+            ctx.schedule(&mut actions.logical, after!(30 ms));
+        })),
+        on_logical: Some(Box::new(|ctx, _| {
+            assert!(ctx.get_elapsed_physical_time() >= delay!(30 ms));
+        })),
+        ..Default::default()
+    }
+}
+
+/// Run [async_main] with a timer that counts its sleeps. The
+/// given function is run in another thread with the handle
+/// and the id of the physical action. Returns the triggers
+/// and values recorded, and the number of sleeps.
+fn run_async_main<F>(schedule: F) -> (Vec<(&'static str, Option<u32>)>, usize)
+where
+    F: FnOnce(SchedulerHandle, TriggerId) + Send + 'static,
+{
+    let sleeps = Arc::new(Mutex::new(0));
+    let timer = {
+        let sleeps = sleeps.clone();
+        move |deadline| {
+            *sleeps.lock().unwrap() += 1;
+            thread_timer(deadline)
+        }
+    };
+    let params = async_main();
+    let record = params.record.clone();
+    let (handle, program) = SyncScheduler::run_main_async::<TestReactor, _>(SchedulerOptions::default(), params, timer);
+    let physical = assert_matches!(handle.lookup("/physical")[..], [ElementId::Trigger(id)] => id);
+    let scheduling = thread::spawn(move || schedule(handle, physical));
+
+    block_on(program);
+    scheduling.join().unwrap();
+    let record = recorded(&record);
+    assert!(record.contains(&("logical", tag!(T0 + 30 ms), None)));
+    let received = record.into_iter().map(|(name, _, value)| (name, value)).collect();
+    let sleeps = *sleeps.lock().unwrap();
+    (received, sleeps)
+}
+
+#[test]
+fn run_main_async_is_woken_by_scheduler_handle() {
+    let (received, sleeps) = run_async_main(|handle, physical| {
+        let physical = handle.physical_action::<u32>(physical).unwrap();
+        for i in 0..3 {
+            thread::sleep(delay!(50 ms));
+            handle.schedule_physical_with_v(&physical, Some(i), Offset::Asap).unwrap();
+        }
+        // dropping the last handle lets the program complete
+    });
+
+    assert_eq!(
+        vec![
+            ("logical", None),
+            ("physical", Some(0)),
+            ("physical", Some(1)),
+            ("physical", Some(2)),
+            ("shutdown", None)
+        ],
+        received
+    );
+    // the logical action is waited for with the timer
+    assert!(sleeps >= 1);
+}
+
+#[test]
+fn run_main_async_stops_on_request() {
+    let (received, _) = run_async_main(|handle, physical| {
+        thread::sleep(delay!(50 ms));
+        handle.schedule_physical_by_id(physical, Some(0u32), Offset::Asap).unwrap();
+        handle.request_stop(Offset::Asap).unwrap();
+        // keep the handle alive until the program has stopped,
+        // so that only the request can end it
+        while !handle.was_terminated() {
+            thread::sleep(delay!(1 ms));
+        }
+    });
+
+    assert_eq!(vec![("logical", None), ("physical", Some(0)), ("shutdown", None)], received);
+}
+
+#[test]
+fn scheduler_handle_finds_physical_actions_of_the_right_type() {
+    let timer = |_| std::future::pending::<()>();
+    let (handle, _program) = SyncScheduler::run_main_async::<TestReactor, _>(SchedulerOptions::default(), async_main(), timer);
+    let physical = assert_matches!(handle.lookup("/physical")[..], [ElementId::Trigger(id)] => id);
+    let logical = assert_matches!(handle.lookup("/logical")[..], [ElementId::Trigger(id)] => id);
+
+    assert!(handle.physical_action::<u32>(physical).is_some());
+    assert!(handle.physical_action::<i32>(physical).is_none());
+    assert!(handle.physical_action::<u32>(logical).is_none());
+}
+
+#[test]
+#[should_panic(expected = "/logical is not a physical action with values of type u32")]
+fn scheduler_handle_rejects_scheduling_other_triggers_by_id() {
+    let timer = |_| std::future::pending::<()>();
+    let (handle, _program) = SyncScheduler::run_main_async::<TestReactor, _>(SchedulerOptions::default(), async_main(), timer);
+    let logical = assert_matches!(handle.lookup("/logical")[..], [ElementId::Trigger(id)] => id);
+    let _ = handle.schedule_physical_by_id(logical, Some(0u32), Offset::Asap);
+}
+
+#[test]
+#[should_panic(expected = "Reactions /0@start may spawn physical threads")]
+fn run_main_async_rejects_reactions_that_spawn_threads() {
+    let params = TestParams { spawns_threads: true, ..Default::default() };
+    let timer = |_| std::future::pending::<()>();
+    let _ = SyncScheduler::run_main_async::<TestReactor, _>(SchedulerOptions::default(), params, timer);
+}
+
+#[test]
+#[should_panic(expected = "The debugger (stdin) is not supported with run_main_async")]
+fn run_main_async_rejects_debugger() {
+    let options = SchedulerOptions {
+        debugger: Some(DebuggerInput::Stdin),
+        ..Default::default()
+    };
+    let timer = |_| std::future::pending::<()>();
+    let _ = SyncScheduler::run_main_async::<TestReactor, _>(options, TestParams::default(), timer);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "Reaction /0@start spawns a physical thread, but was not declared with declare_spawns_threads")]
+fn run_main_checks_that_reactions_that_spawn_threads_are_declared() {
+    let params = TestParams {
+        on_startup: Some(Box::new(|ctx, _| {
+            ctx.spawn_physical_thread(|_| {});
+        })),
+        ..Default::default()
+    };
+    SyncScheduler::run_main::<TestReactor>(SchedulerOptions::default(), params);
+}
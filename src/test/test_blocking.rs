@@ -0,0 +1,114 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+//! Runs programs that use [ReactionCtx::spawn_blocking].
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::test::testutil::{recorded, TestParams, TestReactor};
+use crate::*;
+
+/// At startup, spawns a blocking job whose result is sent to
+/// the physical action. If the program should stop, requests
+/// it, and spawns another job, that cannot start before the
+/// first one completes, after shutdown. Returns the tags and
+/// values of the physical action, and whether the second job
+/// was executed.
+fn run_blocking_jobs(stop: bool, policy: BlockingShutdown) -> (Vec<(EventTag, Option<u32>)>, bool) {
+    let second_job_ran = Arc::new(AtomicBool::new(false));
+    // signals the first job that the program is shutting down
+    let (tx, shutting_down) = channel();
+    let shutting_down = Mutex::new(Some(shutting_down));
+    let params = TestParams {
+        on_startup: Some(Box::new({
+            let second_job_ran = second_job_ran.clone();
+            move |ctx, actions| {
+                if !stop {
+                    ctx.spawn_blocking(&actions.physical, || {
+                        thread::sleep(delay!(10 ms));
+                        42
+                    });
+                    return;
+                }
+                let shutting_down = shutting_down.lock().unwrap().take().unwrap();
+                ctx.spawn_blocking(&actions.physical, move || {
+                    shutting_down.recv().unwrap();
+                    // give the scheduler time to shut down the pool
+                    thread::sleep(delay!(20 ms));
+                    1
+                });
+                let second_job_ran = second_job_ran.clone();
+                ctx.spawn_blocking(&actions.physical, move || {
+                    second_job_ran.store(true, Ordering::SeqCst);
+                    2
+                });
+                ctx.request_stop(Offset::Asap);
+            }
+        })),
+        on_shutdown: Some(Box::new(move |_, _| {
+            // nobody listens if the program does not stop
+            let _ = tx.send(());
+        })),
+        ..Default::default()
+    };
+    let record = params.record.clone();
+    let options = SchedulerOptions {
+        blocking_threads: 1,
+        blocking_shutdown: policy,
+        ..Default::default()
+    };
+    SyncScheduler::run_main::<TestReactor>(options, params);
+
+    let received = recorded(&record)
+        .into_iter()
+        .filter(|(name, ..)| *name == "physical")
+        .map(|(_, tag, value)| (tag, value))
+        .collect();
+    (received, second_job_ran.load(Ordering::SeqCst))
+}
+
+#[test]
+fn spawn_blocking_delivers_result_as_physical_action_value() {
+    let (received, _) = run_blocking_jobs(false, BlockingShutdown::Await);
+    let tag = assert_matches!(received[..], [(tag, Some(42))] => tag);
+    assert!(tag >= tag!(T0 + 10 ms));
+}
+
+#[test]
+fn spawn_blocking_awaits_pending_jobs_at_shutdown() {
+    let (received, second_job_ran) = run_blocking_jobs(true, BlockingShutdown::Await);
+    assert!(second_job_ran);
+    // the results come too late
+    assert!(received.is_empty());
+}
+
+#[test]
+fn spawn_blocking_cancels_pending_jobs_at_shutdown() {
+    let (received, second_job_ran) = run_blocking_jobs(true, BlockingShutdown::Cancel);
+    assert!(!second_job_ran);
+    assert!(received.is_empty());
+}
@@ -23,8 +23,10 @@
  */
 
 use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
 
-use crate::assembly::{PortKind, TriggerId};
+use crate::assembly::*;
+use crate::test::testutil::{block_on, thread_timer};
 use crate::*;
 
 struct TestAssembler {
@@ -69,11 +71,46 @@ impl TestFixture {
         upstream.forward_to(downstream).map_err(|e| e.lift(&self.debug))
     }
 
+    pub fn bind_delayed<T: Sync + Clone>(&self, upstream: &mut Port<T>, downstream: &mut Port<T>, delay: Duration) -> TestResult {
+        upstream
+            .forward_delayed_to(downstream, ConnectionKind::Logical, delay)
+            .map_err(|e| e.lift(&self.debug))
+    }
+
+    pub fn bind_physical<T: Sync + Clone>(&self, upstream: &mut Port<T>, downstream: &mut Port<T>) -> TestResult {
+        upstream
+            .forward_delayed_to(downstream, ConnectionKind::Physical, Duration::ZERO)
+            .map_err(|e| e.lift(&self.debug))
+    }
+
     pub fn set<T: Sync>(&self, port: &mut Port<T>, value: T) -> TestResult {
         port.set_impl(Some(value));
         Ok(())
     }
 
+    /// Set the port and send its value on delayed connections,
+    /// like [ReactionCtx::set] does. Physical connections deliver
+    /// the value at the given `physical_tag`.
+    pub fn set_at<T: Sync>(
+        &self,
+        port: &mut Port<T>,
+        value: T,
+        tag: EventTag,
+        physical_tag: EventTag,
+    ) -> Vec<(TriggerId, EventTag)> {
+        port.set_impl(Some(value));
+        let mut sent = Vec::new();
+        port.send_delayed(tag, |downstream, kind, delay, _latest| {
+            let eta = match kind {
+                ConnectionKind::Logical => tag.successor(delay),
+                ConnectionKind::Physical => physical_tag,
+            };
+            sent.push((downstream, eta));
+            eta
+        });
+        sent
+    }
+
     pub fn ok(self) -> TestResult {
         Ok(())
     }
@@ -237,6 +274,57 @@ fn transitive_binding_in_non_topo_order_is_ok() -> TestResult {
     test.ok()
 }
 
+#[test]
+fn transitive_binding_in_non_topo_order_several_times_is_ok() -> TestResult {
+    let mut test = TestAssembler::default();
+    let mut v = test.new_port("v");
+    let mut u = test.new_port("u");
+    let mut a = test.new_port("a");
+    let mut b = test.new_port("b");
+    let mut c = test.new_port("c");
+    let test = test.ready();
+
+    test.bind(&mut a, &mut b)?;
+    test.bind(&mut b, &mut c)?;
+    test.bind(&mut u, &mut a)?;
+    test.bind(&mut v, &mut u)?;
+
+    test.set(&mut v, 1)?;
+
+    assert_eq!(Some(1), u.get());
+    assert_eq!(Some(1), a.get());
+    assert_eq!(Some(1), b.get());
+    assert_eq!(Some(1), c.get());
+
+    test.ok()
+}
+
+#[test]
+fn transitive_binding_cycle_is_detected() -> TestResult {
+    let mut test = TestAssembler::default();
+    let mut u: Port<u32> = test.new_port("u");
+    let mut a = test.new_port("a");
+    let mut b = test.new_port("b");
+    let mut c = test.new_port("c");
+    let test = test.ready();
+
+    test.bind(&mut a, &mut b)?;
+    test.bind(&mut b, &mut c)?;
+    test.bind(&mut u, &mut a)?;
+
+    assert_eq!(
+        Err("Cannot bind /c to /u, this would create a cycle: /u -> /a -> /b -> /c -> /u".into()),
+        test.bind(&mut c, &mut u)
+    );
+    // the failed binding did not change anything
+    assert_eq!(
+        Err("Cannot bind /b to /u, this would create a cycle: /u -> /a -> /b -> /u".into()),
+        test.bind(&mut b, &mut u)
+    );
+
+    test.ok()
+}
+
 #[test]
 fn repeated_binding_panics() -> TestResult {
     let mut test = TestAssembler::default();
@@ -253,3 +341,345 @@ fn repeated_binding_panics() -> TestResult {
 
     test.ok()
 }
+
+#[test]
+fn assembly_error_collects_diagnostics() -> TestResult {
+    let mut test = TestAssembler::default();
+    let mut a: Port<u32> = test.new_port("a");
+    let mut b = test.new_port("b");
+    let mut c = test.new_port("c");
+    let test = test.ready();
+
+    test.bind(&mut a, &mut b)?;
+    let mut error = a.forward_to(&mut b).unwrap_err();
+    error.merge(c.forward_to(&mut b).unwrap_err());
+    error.merge(AssemblyError::custom("user validation failed"));
+
+    // paths are not known until resolved
+    assert_eq!(
+        Some(&AssemblyDiagnostic::CannotBind {
+            upstream: ComponentRef::new(c.get_id()),
+            downstream: ComponentRef::new(b.get_id())
+        }),
+        error.diagnostics().get(1)
+    );
+
+    let error = error.with_paths(&test.debug);
+    assert_eq!(3, error.diagnostics().len());
+    assert_eq!(
+        "3 errors during assembly:\n - Cannot bind /a to /b, downstream is already bound\n - Cannot bind /c to /b, downstream is already bound\n - user validation failed",
+        error.to_string()
+    );
+
+    test.ok()
+}
+
+#[test]
+fn delayed_binding_delivers_value_later() -> TestResult {
+    let mut test = TestAssembler::default();
+    let mut upstream = test.new_port("up");
+    let mut downstream = test.new_port("down");
+    let test = test.ready();
+
+    test.bind_delayed(&mut upstream, &mut downstream, delay!(10 ms))?;
+
+    let sent = test.set_at(&mut upstream, 5, tag!(T0), tag!(T0));
+    assert_eq!(vec![(downstream.get_id(), tag!(T0 + 10 ms))], sent);
+
+    assert_eq!(Some(5), upstream.get_at(&tag!(T0)));
+    assert_eq!(None, downstream.get_at(&tag!(T0)));
+    assert_eq!(Some(5), downstream.get_at(&tag!(T0 + 10 ms)));
+
+    upstream.clear_value(&tag!(T0));
+    assert_eq!(None, upstream.get_at(&tag!(T0)));
+    assert_eq!(Some(5), downstream.get_at(&tag!(T0 + 10 ms)));
+
+    downstream.clear_value(&tag!(T0 + 10 ms));
+    assert_eq!(None, downstream.get_at(&tag!(T0 + 10 ms)));
+
+    test.ok()
+}
+
+#[test]
+fn delayed_binding_with_zero_delay_is_one_microstep() -> TestResult {
+    let mut test = TestAssembler::default();
+    let mut upstream = test.new_port("up");
+    let mut downstream = test.new_port("down");
+    let test = test.ready();
+
+    test.bind_delayed(&mut upstream, &mut downstream, Duration::ZERO)?;
+
+    let sent = test.set_at(&mut upstream, 5, tag!(T0), tag!(T0));
+    assert_eq!(vec![(downstream.get_id(), tag!(T0, 1))], sent);
+    assert_eq!(Some(5), downstream.get_at(&tag!(T0, 1)));
+
+    test.ok()
+}
+
+#[test]
+fn delayed_binding_flows_through_instantaneous_bindings() -> TestResult {
+    let mut test = TestAssembler::default();
+    let mut a = test.new_port("a");
+    let mut b = test.new_port("b");
+    let mut c = test.new_port("c");
+    let mut d = test.new_port("d");
+    let test = test.ready();
+
+    // a -> b ~> c -> d
+    test.bind_delayed(&mut b, &mut c, delay!(1 ms))?;
+    test.bind(&mut c, &mut d)?;
+    // bound after the delayed connection, so b's class is merged into a's
+    test.bind(&mut a, &mut b)?;
+
+    let sent = test.set_at(&mut a, 3, tag!(T0), tag!(T0));
+    assert_eq!(vec![(c.get_id(), tag!(T0 + 1 ms))], sent);
+
+    assert_eq!(Some(3), b.get_at(&tag!(T0)));
+    assert_eq!(None, d.get_at(&tag!(T0)));
+    assert_eq!(Some(3), c.get_at(&tag!(T0 + 1 ms)));
+    assert_eq!(Some(3), d.get_at(&tag!(T0 + 1 ms)));
+
+    test.ok()
+}
+
+#[test]
+fn delayed_binding_to_bound_port_fails() -> TestResult {
+    let mut test = TestAssembler::default();
+    let mut upstream: Port<u32> = test.new_port("up");
+    let mut downstream = test.new_port("down");
+    let test = test.ready();
+
+    test.bind_delayed(&mut upstream, &mut downstream, delay!(1 ms))?;
+
+    assert_eq!(
+        Err("Cannot bind /up to /down, downstream is already bound".into()),
+        test.bind(&mut upstream, &mut downstream)
+    );
+
+    test.ok()
+}
+
+#[test]
+fn physical_binding_delivers_value_at_physical_tag() -> TestResult {
+    let mut test = TestAssembler::default();
+    let mut upstream = test.new_port("up");
+    let mut logical = test.new_port("logical");
+    let mut physical = test.new_port("physical");
+    let test = test.ready();
+
+    test.bind_delayed(&mut upstream, &mut logical, delay!(1 ms))?;
+    test.bind_physical(&mut upstream, &mut physical)?;
+
+    let sent = test.set_at(&mut upstream, 5, tag!(T0), tag!(T0 + 3 ms));
+    assert_eq!(
+        vec![(logical.get_id(), tag!(T0 + 1 ms)), (physical.get_id(), tag!(T0 + 3 ms))],
+        sent
+    );
+
+    assert_eq!(None, physical.get_at(&tag!(T0 + 1 ms)));
+    assert_eq!(Some(5), physical.get_at(&tag!(T0 + 3 ms)));
+
+    test.ok()
+}
+
+/// Sets its output at startup, and records the values
+/// received on its input, which is bound to the output
+/// with a delayed connection.
+struct DelayedLoopback {
+    id: ReactorId,
+    out: Port<u32>,
+    inp: Port<u32>,
+    received: Arc<Mutex<Vec<(EventTag, Option<u32>)>>>,
+}
+
+impl ReactorInitializer for DelayedLoopback {
+    type Wrapped = ();
+    type Params = (Duration, Arc<Mutex<Vec<(EventTag, Option<u32>)>>>);
+    const MAX_REACTION_ID: LocalReactionId = LocalReactionId::new(2);
+
+    fn assemble((delay, received): Self::Params, ctx: AssemblyCtx<Self>) -> AssemblyResult<FinishedReactor<Self>> {
+        ctx.assemble(|ctx| {
+            ctx.assemble_self(
+                |cc, id| {
+                    Ok(Self {
+                        id,
+                        out: cc.new_port("out", PortKind::Output),
+                        inp: cc.new_port("inp", PortKind::Input),
+                        received,
+                    })
+                },
+                2,
+                [Some("send"), Some("receive")],
+                |decl, me, [send, receive]| {
+                    decl.declare_triggers(TriggerId::STARTUP, send)?;
+                    decl.effects_port(send, &me.out)?;
+                    decl.declare_triggers(me.inp.get_id(), receive)?;
+                    decl.bind_ports_delayed(&mut me.out, &mut me.inp, delay)?;
+                    Ok(())
+                },
+            )
+        })
+    }
+}
+
+impl ReactorBehavior for DelayedLoopback {
+    fn id(&self) -> ReactorId {
+        self.id
+    }
+
+    fn react(&mut self, ctx: &mut ReactionCtx, rid: LocalReactionId) {
+        match rid.raw() {
+            0 => ctx.set(WritablePort::new(&mut self.out), 42),
+            1 => {
+                let value = ctx.get(&ReadablePort::new(&self.inp));
+                self.received.lock().unwrap().push((ctx.get_tag(), value));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn cleanup_tag(&mut self, ctx: &CleanupCtx) {
+        ctx.cleanup_port(&mut self.out);
+        ctx.cleanup_port(&mut self.inp);
+    }
+}
+
+#[test]
+fn delayed_connection_delivers_value_set_by_reaction() {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    SyncScheduler::run_main::<DelayedLoopback>(SchedulerOptions::default(), (delay!(10 ms), received.clone()));
+
+    assert_eq!(vec![(tag!(T0 + 10 ms), Some(42))], *received.lock().unwrap());
+}
+
+#[test]
+fn delayed_connection_with_zero_delay_delivers_at_next_microstep() {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    SyncScheduler::run_main::<DelayedLoopback>(SchedulerOptions::default(), (Duration::ZERO, received.clone()));
+
+    assert_eq!(vec![(tag!(T0, 1), Some(42))], *received.lock().unwrap());
+}
+
+/// Sets its output twice at the same tag, from two reactions.
+/// The output is bound to its input with a physical connection.
+struct PhysicalLoopback {
+    id: ReactorId,
+    tick: LogicalAction<()>,
+    out: Port<u32>,
+    inp: Port<u32>,
+    received: Arc<Mutex<Vec<(EventTag, Option<u32>)>>>,
+}
+
+impl ReactorInitializer for PhysicalLoopback {
+    type Wrapped = ();
+    type Params = Arc<Mutex<Vec<(EventTag, Option<u32>)>>>;
+    const MAX_REACTION_ID: LocalReactionId = LocalReactionId::new(4);
+
+    fn assemble(received: Self::Params, ctx: AssemblyCtx<Self>) -> AssemblyResult<FinishedReactor<Self>> {
+        ctx.assemble(|ctx| {
+            ctx.assemble_self(
+                |cc, id| {
+                    Ok(Self {
+                        id,
+                        tick: cc.new_logical_action("tick", None),
+                        out: cc.new_port("out", PortKind::Output),
+                        inp: cc.new_port("inp", PortKind::Input),
+                        received,
+                    })
+                },
+                4,
+                [Some("start"), Some("send_1"), Some("send_2"), Some("receive")],
+                |decl, me, [start, send_1, send_2, receive]| {
+                    decl.declare_triggers(TriggerId::STARTUP, start)?;
+                    decl.declare_triggers(me.tick.get_id(), send_1)?;
+                    decl.declare_triggers(me.tick.get_id(), send_2)?;
+                    decl.effects_port(send_1, &me.out)?;
+                    decl.effects_port(send_2, &me.out)?;
+                    decl.declare_triggers(me.inp.get_id(), receive)?;
+                    decl.bind_ports_physical(&mut me.out, &mut me.inp, None)?;
+                    Ok(())
+                },
+            )
+        })
+    }
+}
+
+impl ReactorBehavior for PhysicalLoopback {
+    fn id(&self) -> ReactorId {
+        self.id
+    }
+
+    fn react(&mut self, ctx: &mut ReactionCtx, rid: LocalReactionId) {
+        match rid.raw() {
+            0 => {
+                ctx.schedule(&mut self.tick, Offset::After(delay!(100 ms)));
+            }
+            1 => ctx.set(WritablePort::new(&mut self.out), 1),
+            2 => ctx.set(WritablePort::new(&mut self.out), 2),
+            3 => {
+                let value = ctx.get(&ReadablePort::new(&self.inp));
+                self.received.lock().unwrap().push((ctx.get_tag(), value));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn cleanup_tag(&mut self, ctx: &CleanupCtx) {
+        ctx.cleanup_logical_action(&mut self.tick);
+        ctx.cleanup_port(&mut self.out);
+        ctx.cleanup_port(&mut self.inp);
+    }
+}
+
+#[test]
+fn physical_connection_keeps_values_sent_at_the_same_time() {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    // In fast mode logical time runs ahead of physical time,
+    // so both values are sent for the same tag, the microstep after the tick.
+    let options = SchedulerOptions {
+        fast: true,
+        timeout: Some(delay!(200 ms)),
+        ..Default::default()
+    };
+    SyncScheduler::run_main::<PhysicalLoopback>(options, received.clone());
+
+    assert_eq!(
+        vec![(tag!(T0 + 100 ms, 1), Some(1)), (tag!(T0 + 100 ms, 2), Some(2))],
+        *received.lock().unwrap()
+    );
+}
+
+#[test]
+fn scheduler_handle_looks_up_ports_after_assembly() {
+    let params = (delay!(10 ms), Default::default());
+    let timer = |_| std::future::pending::<()>();
+    let (handle, _program) = SyncScheduler::run_main_async::<DelayedLoopback, _>(SchedulerOptions::default(), params, timer);
+
+    let ports = handle.lookup("in*");
+    assert_eq!(1, ports.len());
+    assert_matches!(ports[0], ElementId::Trigger(_));
+    assert_eq!("/inp", handle.path_of(ports[0]));
+}
+
+#[test]
+fn scheduler_handle_monitors_event_queue() {
+    let monitor: Arc<Mutex<Option<EventQueueMonitor>>> = Default::default();
+    let next_tags = Arc::new(Mutex::new(Vec::new()));
+    let timer = {
+        let (monitor, next_tags) = (monitor.clone(), next_tags.clone());
+        move |deadline| {
+            // the queue is published before the scheduler waits for its next event
+            let next_tag = monitor.lock().unwrap().as_ref().unwrap().next_tag();
+            next_tags.lock().unwrap().push(next_tag);
+            thread_timer(deadline)
+        }
+    };
+    let params = (delay!(10 ms), Default::default());
+    let (handle, program) = SyncScheduler::run_main_async::<DelayedLoopback, _>(SchedulerOptions::default(), params, timer);
+    *monitor.lock().unwrap() = Some(handle.event_queue_monitor());
+    drop(handle);
+
+    block_on(program);
+    assert_eq!(vec![Some(tag!(T0 + 10 ms))], *next_tags.lock().unwrap());
+    assert!(monitor.lock().unwrap().as_ref().unwrap().is_empty());
+}
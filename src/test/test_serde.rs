@@ -0,0 +1,75 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::assembly::TriggerId;
+use crate::*;
+
+fn round_trip<T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug>(value: T) -> String {
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(serde_json::from_str::<T>(&json).unwrap(), value);
+    json
+}
+
+#[test]
+fn tags_and_time_values() {
+    let json = round_trip(tag!(T0 + 1500 ms, 2));
+    assert_eq!(json, r#"{"offset_from_t0":{"secs":1,"nanos":500000000},"microstep":2}"#);
+    round_trip(MicroStep::new(3));
+    assert_eq!(round_trip(Timestamp::from_nanos(-5)), "-5");
+    round_trip(LogicalTime::new(Timestamp::FOREVER, MicroStep::ZERO));
+    round_trip(after!(3 ms));
+    round_trip(Offset::Asap);
+}
+
+#[test]
+fn ids() {
+    assert_eq!(round_trip(ReactorId::new(4)), "4");
+    assert_eq!(round_trip(LocalReactionId::new(1)), "1");
+    assert_eq!(round_trip(TriggerId::SHUTDOWN), "1");
+    assert_eq!(
+        round_trip(GlobalReactionId::new(ReactorId::new(3), LocalReactionId::new(1))),
+        r#""3/1""#
+    );
+    round_trip(ElementId::Reaction(GlobalReactionId::new(
+        ReactorId::new(0),
+        LocalReactionId::new(2),
+    )));
+
+    let err = serde_json::from_str::<GlobalReactionId>(r#""3""#).unwrap_err();
+    assert!(err.to_string().contains("Expected format {int}/{int}"));
+}
+
+#[test]
+fn policies_use_option_names() {
+    assert_eq!(round_trip(OverflowPolicy::Coalesce), r#""coalesce""#);
+    assert_eq!(round_trip(BlockingShutdown::Cancel), r#""cancel""#);
+    assert_eq!(round_trip(ZenoPolicy::Panic), r#""panic""#);
+    assert_eq!(round_trip(GraphFormat::Mermaid), r#""mermaid""#);
+    assert_eq!(round_trip(SpacingPolicy::Replace), r#""replace""#);
+    round_trip(ScheduleOutcome::Deferred(tag!(T0 + 1 ms)));
+    round_trip(AsyncChannelStats::default());
+}
@@ -0,0 +1,95 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::*;
+
+#[test]
+fn timestamp_arithmetic_saturates() {
+    let t = Timestamp::from_nanos(i64::MAX - 10);
+    assert_eq!(t + Duration::from_nanos(100), Timestamp::FOREVER);
+    assert_eq!(
+        Timestamp::from_nanos(i64::MIN + 10) - Duration::from_nanos(100),
+        Timestamp::NEVER
+    );
+    assert_eq!(Timestamp::EPOCH + Duration::MAX, Timestamp::FOREVER);
+    assert_eq!(Timestamp::EPOCH - delay!(1 ms), Timestamp::from_nanos(-1_000_000));
+}
+
+#[test]
+fn sentinels_are_absorbing() {
+    assert_eq!(Timestamp::FOREVER - delay!(1 s), Timestamp::FOREVER);
+    assert_eq!(Timestamp::NEVER + delay!(1 s), Timestamp::NEVER);
+    assert!(Timestamp::NEVER < Timestamp::EPOCH);
+    assert!(LogicalTime::NEVER < LogicalTime::new(Timestamp::EPOCH, MicroStep::ZERO));
+    assert!(LogicalTime::FOREVER > LogicalTime::new(Timestamp::from_nanos(i64::MAX - 1), MicroStep::new(5)));
+    assert_eq!(Timestamp::FOREVER.to_instant(), None);
+    assert_eq!(Timestamp::NEVER.to_system_time(), None);
+    assert_eq!(Timestamp::FOREVER.duration_since(Timestamp::EPOCH), None);
+}
+
+#[test]
+fn system_time_round_trip() {
+    let after = UNIX_EPOCH + Duration::from_nanos(1_500_000_000_123);
+    assert_eq!(Timestamp::from(after).as_nanos(), 1_500_000_000_123);
+    assert_eq!(Timestamp::from(after).to_system_time(), Some(after));
+
+    let before = UNIX_EPOCH - delay!(2 s);
+    assert_eq!(Timestamp::from(before).as_nanos(), -2_000_000_000);
+    assert_eq!(Timestamp::from(before).to_system_time(), Some(before));
+}
+
+#[test]
+fn instant_round_trip() {
+    let now = Instant::now();
+    let later = now + delay!(3 ms);
+    let t = Timestamp::from_instant(now);
+    assert_eq!(Timestamp::from_instant(later).duration_since(t), Some(delay!(3 ms)));
+    assert_eq!(t.to_instant(), Some(now));
+    assert_eq!(t.duration_since(Timestamp::from_instant(later)), None);
+
+    // close to the system clock
+    let drift = Timestamp::now().as_nanos() - Timestamp::from(SystemTime::now()).as_nanos();
+    assert!(drift.abs() < 1_000_000_000);
+}
+
+#[test]
+fn tag_to_absolute() {
+    let t0 = Instant::now();
+    let start = Timestamp::from_instant(t0);
+    let abs = tag!(T0 + 2 ms, 3).to_absolute(t0);
+    assert_eq!(abs, LogicalTime::new(start + delay!(2 ms), MicroStep::new(3)));
+    assert!(tag!(T0 + 2 ms, 4).to_absolute(t0) > abs);
+}
+
+#[test]
+fn tags_at_forever_do_not_overflow() {
+    let forever = tag!(T0 + 1 ms).successor(delay!(forever));
+    assert_eq!(forever, EventTag::offset(Duration::MAX, 0));
+    assert_eq!(forever.successor(delay!(1 s)), EventTag::offset(Duration::MAX, 1));
+
+    let t0 = Instant::now();
+    assert!(forever.to_logical_time(t0) > t0 + delay!(1 week));
+}
@@ -0,0 +1,60 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+//! Checks the [timeout](SchedulerOptions::timeout) of programs.
+
+use crate::test::testutil::{recorded, TestParams, TestReactor};
+use crate::*;
+
+/// Schedules the logical action 1 ms after startup, which
+/// schedules itself again after the given delay, and requests
+/// to stop 1 ms later.
+fn schedule_later(delay: Duration) -> TestParams {
+    TestParams {
+        on_startup: Some(Box::new(|ctx, actions| {
+            ctx.schedule(&mut actions.logical, after!(1 ms));
+        })),
+        on_logical: Some(Box::new(move |ctx, actions| {
+            ctx.schedule(&mut actions.logical, Offset::After(delay));
+            ctx.request_stop(after!(1 ms));
+        })),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn timeout_forever_does_not_shut_down() {
+    let params = schedule_later(delay!(forever));
+    let record = params.record.clone();
+    let options = SchedulerOptions {
+        timeout: Some(try_parse_duration("forever").unwrap()),
+        ..Default::default()
+    };
+    SyncScheduler::run_main::<TestReactor>(options, params);
+
+    assert_eq!(
+        vec![("logical", tag!(T0 + 1 ms), None), ("shutdown", tag!(T0 + 2 ms), None)],
+        recorded(&record)
+    );
+}
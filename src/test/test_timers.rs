@@ -0,0 +1,235 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+use std::time::Instant;
+
+use crate::assembly::*;
+use crate::test::testutil::{recorded, Record};
+use crate::*;
+
+fn timer(offset: Duration, period: Duration) -> Timer {
+    Timer::new(TriggerId::FIRST_REGULAR, offset, period)
+}
+
+fn is_present(timer: &Timer, tag: EventTag) -> bool {
+    timer.is_present(&tag, &Instant::now())
+}
+
+#[test]
+fn periodic_timer_schedules_next_firing() {
+    let timer = timer(delay!(1 ms), delay!(10 ms));
+    let firing = timer.start(tag!(T0 + 1 ms));
+    assert!(timer.is_running());
+
+    let next = firing.fire().unwrap().unwrap();
+    assert_eq!(next.tag(), tag!(T0 + 11 ms));
+    // the previous firing has been consumed
+    assert!(firing.fire().is_err());
+}
+
+#[test]
+fn timer_is_present_only_at_firing_tag() {
+    let timer = timer(delay!(1 ms), delay!(10 ms));
+    let firing = timer.start(tag!(T0 + 1 ms));
+    assert!(!is_present(&timer, tag!(T0 + 1 ms)));
+
+    let next = firing.fire().unwrap().unwrap();
+    assert!(is_present(&timer, tag!(T0 + 1 ms)));
+    assert!(!is_present(&timer, tag!(T0 + 1 ms, 1)));
+    assert!(!is_present(&timer, tag!(T0 + 11 ms)));
+
+    next.fire().unwrap();
+    assert!(!is_present(&timer, tag!(T0 + 1 ms)));
+    assert!(is_present(&timer, tag!(T0 + 11 ms)));
+}
+
+#[test]
+fn one_shot_timer_stops_after_firing() {
+    let timer = timer(delay!(1 ms), Duration::ZERO);
+    let firing = timer.start(tag!(T0 + 1 ms));
+    assert!(matches!(firing.fire(), Ok(None)));
+    assert!(!timer.is_running());
+}
+
+#[test]
+fn stop_cancels_scheduled_firing() {
+    let timer = timer(Duration::ZERO, delay!(10 ms));
+    let firing = timer.start(tag!(T0));
+    let next = firing.fire().unwrap().unwrap();
+
+    timer.stop();
+    // the firing at the current tag is not cancelled
+    assert!(is_present(&timer, tag!(T0)));
+    assert!(!timer.is_running());
+    assert!(next.is_cancelled());
+    assert!(next.fire().is_err());
+    assert!(!is_present(&timer, tag!(T0 + 10 ms)));
+}
+
+#[test]
+fn restart_cancels_previous_firing() {
+    let timer = timer(Duration::ZERO, delay!(10 ms));
+    let first = timer.start(tag!(T0 + 10 ms));
+    let second = timer.start(tag!(T0 + 3 ms));
+
+    assert!(first.is_cancelled());
+    assert!(!second.is_cancelled());
+    let next = second.fire().unwrap().unwrap();
+    assert_eq!(next.tag(), tag!(T0 + 13 ms));
+    assert!(first.fire().is_err());
+}
+
+#[test]
+fn set_period_reschedules_running_timer() {
+    let timer = timer(Duration::ZERO, delay!(10 ms));
+    let next = timer.start(tag!(T0)).fire().unwrap().unwrap();
+
+    let rescheduled = timer.set_period(tag!(T0 + 2 ms), delay!(5 ms)).unwrap();
+    assert_eq!(rescheduled.tag(), tag!(T0 + 7 ms));
+    assert_eq!(timer.current_period(), delay!(5 ms));
+    assert!(next.is_cancelled());
+
+    let after = rescheduled.fire().unwrap().unwrap();
+    assert_eq!(after.tag(), tag!(T0 + 12 ms));
+}
+
+#[test]
+fn zero_period_keeps_next_firing() {
+    let timer = timer(Duration::ZERO, delay!(10 ms));
+    let next = timer.start(tag!(T0)).fire().unwrap().unwrap();
+
+    assert!(timer.set_period(tag!(T0 + 2 ms), Duration::ZERO).is_none());
+    assert!(!timer.is_periodic());
+    assert!(!next.is_cancelled());
+    assert!(matches!(next.fire(), Ok(None)));
+    assert!(!timer.is_running());
+}
+
+#[test]
+fn set_period_of_stopped_timer_does_not_restart_it() {
+    let timer = timer(Duration::ZERO, delay!(10 ms));
+    timer.stop();
+    assert!(timer.set_period(tag!(T0 + 1 ms), delay!(5 ms)).is_none());
+    assert!(!timer.is_running());
+}
+
+/// A timer with an offset of 1 ms and a period of 10 ms,
+/// bootstrapped and rescheduled like in generated code. Its
+/// reaction stops it at the second tick, and a logical action
+/// restarts it 15 ms later. At the next tick, its period is
+/// set to 5 ms, and the program stops at the fifth tick.
+struct ControlledTimer {
+    id: ReactorId,
+    timer: Timer,
+    control: LogicalAction<()>,
+    ticks: u32,
+    record: Record,
+}
+
+impl ReactorInitializer for ControlledTimer {
+    type Wrapped = ();
+    type Params = Record;
+    const MAX_REACTION_ID: LocalReactionId = LocalReactionId::new(4);
+
+    fn assemble(record: Self::Params, ctx: AssemblyCtx<Self>) -> AssemblyResult<FinishedReactor<Self>> {
+        ctx.assemble(|ctx| {
+            ctx.assemble_self(
+                |cc, id| {
+                    Ok(Self {
+                        id,
+                        timer: cc.new_timer("timer", delay!(1 ms), delay!(10 ms)),
+                        control: cc.new_logical_action("control", None),
+                        ticks: 0,
+                        record,
+                    })
+                },
+                4,
+                [Some("bootstrap"), Some("reschedule"), Some("on_tick"), Some("on_control")],
+                |decl, me, [bootstrap, reschedule, on_tick, on_control]| {
+                    decl.declare_triggers(TriggerId::STARTUP, bootstrap)?;
+                    decl.effects_timer(bootstrap, &me.timer)?;
+                    decl.declare_triggers(me.timer.get_id(), reschedule)?;
+                    decl.declare_triggers(me.timer.get_id(), on_tick)?;
+                    decl.declare_triggers(me.control.get_id(), on_control)?;
+                    Ok(())
+                },
+            )
+        })
+    }
+}
+
+impl ReactorBehavior for ControlledTimer {
+    fn id(&self) -> ReactorId {
+        self.id
+    }
+
+    fn react(&mut self, ctx: &mut ReactionCtx, rid: LocalReactionId) {
+        match rid.raw() {
+            0 => ctx.bootstrap_timer(&mut self.timer),
+            1 => ctx.reschedule_timer(&mut self.timer),
+            2 => {
+                assert!(ctx.is_present(&self.timer));
+                self.ticks += 1;
+                self.record.lock().unwrap().push(("tick", ctx.get_tag(), Some(self.ticks)));
+                match self.ticks {
+                    2 => {
+                        ctx.stop_timer(&self.timer);
+                        ctx.schedule(&mut self.control, after!(15 ms));
+                    }
+                    3 => ctx.set_timer_period(&self.timer, delay!(5 ms)),
+                    5 => ctx.request_stop(Offset::Asap),
+                    _ => {}
+                }
+            }
+            3 => {
+                assert!(!self.timer.is_running());
+                self.record.lock().unwrap().push(("control", ctx.get_tag(), None));
+                ctx.start_timer(&self.timer, after!(2 ms));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn cleanup_tag(&mut self, ctx: &CleanupCtx) {
+        ctx.cleanup_logical_action(&mut self.control);
+    }
+}
+
+#[test]
+fn reactions_stop_restart_and_change_period_of_timer() {
+    let record = Record::default();
+    SyncScheduler::run_main::<ControlledTimer>(SchedulerOptions::default(), record.clone());
+
+    assert_eq!(
+        vec![
+            ("tick", tag!(T0 + 1 ms), Some(1)),
+            ("tick", tag!(T0 + 11 ms), Some(2)),
+            ("control", tag!(T0 + 26 ms), None),
+            ("tick", tag!(T0 + 28 ms), Some(3)),
+            ("tick", tag!(T0 + 33 ms), Some(4)),
+            ("tick", tag!(T0 + 38 ms), Some(5)),
+        ],
+        recorded(&record)
+    );
+}
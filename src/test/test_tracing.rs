@@ -0,0 +1,220 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+//! Checks the spans opened with feature `tracing`, with a
+//! minimal subscriber that records them.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use tracing_core::span::Current;
+
+use crate::assembly::*;
+use crate::*;
+
+#[derive(Debug)]
+struct RecordedSpan {
+    metadata: &'static Metadata<'static>,
+    parent: Option<u64>,
+    fields: HashMap<&'static str, String>,
+}
+
+/// Records all spans, with their parent and fields.
+#[derive(Clone, Default)]
+struct SpanRecorder {
+    next_id: Arc<AtomicU64>,
+    spans: Arc<Mutex<HashMap<u64, RecordedSpan>>>,
+}
+
+thread_local! {
+    /// Spans entered on this thread.
+    static ENTERED: RefCell<Vec<Id>> = const { RefCell::new(Vec::new()) };
+}
+
+struct FieldRecorder<'a>(&'a mut HashMap<&'static str, String>);
+
+impl Visit for FieldRecorder<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name(), format!("{:?}", value));
+    }
+}
+
+impl Subscriber for SpanRecorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let parent = match attrs.parent() {
+            Some(parent) => Some(parent.into_u64()),
+            None if attrs.is_contextual() => ENTERED.with(|entered| entered.borrow().last().map(Id::into_u64)),
+            None => None,
+        };
+        let mut fields = HashMap::new();
+        attrs.record(&mut FieldRecorder(&mut fields));
+        let span = RecordedSpan { metadata: attrs.metadata(), parent, fields };
+        self.spans.lock().unwrap().insert(id, span);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.clone()));
+    }
+
+    fn exit(&self, _: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().pop());
+    }
+
+    fn current_span(&self) -> Current {
+        match ENTERED.with(|entered| entered.borrow().last().cloned()) {
+            Some(id) => {
+                let metadata = self.spans.lock().unwrap()[&id.into_u64()].metadata;
+                Current::new(id, metadata)
+            }
+            None => Current::none(),
+        }
+    }
+}
+
+/// A reactor with a single reaction, triggered by startup.
+struct Traced {
+    id: ReactorId,
+}
+
+impl ReactorInitializer for Traced {
+    type Wrapped = ();
+    type Params = ();
+    const MAX_REACTION_ID: LocalReactionId = LocalReactionId::new(1);
+
+    fn assemble(_: Self::Params, ctx: AssemblyCtx<Self>) -> AssemblyResult<FinishedReactor<Self>> {
+        ctx.assemble(|ctx| {
+            ctx.assemble_self(
+                |_, id| Ok(Self { id }),
+                1,
+                [Some("traced")],
+                |decl, _, [traced]| decl.declare_triggers(TriggerId::STARTUP, traced),
+            )
+        })
+    }
+}
+
+impl ReactorBehavior for Traced {
+    fn id(&self) -> ReactorId {
+        self.id
+    }
+
+    fn react(&mut self, _: &mut ReactionCtx, _: LocalReactionId) {}
+
+    fn cleanup_tag(&mut self, _: &CleanupCtx) {}
+}
+
+/// Contains a bank of [Traced] reactors, whose reactions are
+/// in the same batch. With feature `parallel-runtime`, the
+/// batch is large enough to be executed on rayon workers.
+struct TracedBank {
+    id: ReactorId,
+}
+
+const BANK_WIDTH: usize = 4;
+
+impl ReactorInitializer for TracedBank {
+    type Wrapped = ();
+    type Params = ();
+    const MAX_REACTION_ID: LocalReactionId = LocalReactionId::new(0);
+
+    fn assemble(_: Self::Params, ctx: AssemblyCtx<Self>) -> AssemblyResult<FinishedReactor<Self>> {
+        ctx.assemble(|ctx| {
+            ctx.with_child_bank::<Traced, _, _>(
+                "traced_bank",
+                BANK_WIDTH,
+                |_| (),
+                |ctx, _| ctx.assemble_self(|_, id| Ok(Self { id }), 0, [], |_, _, []| Ok(())),
+            )
+        })
+    }
+}
+
+impl ReactorBehavior for TracedBank {
+    fn id(&self) -> ReactorId {
+        self.id
+    }
+
+    fn react(&mut self, _: &mut ReactionCtx, _: LocalReactionId) {
+        unreachable!()
+    }
+
+    fn cleanup_tag(&mut self, _: &CleanupCtx) {}
+}
+
+#[test]
+fn reaction_spans_are_children_of_tag_spans() {
+    let recorder = SpanRecorder::default();
+    // the global default is also used by the threads of the
+    // scheduler, but it also receives spans of other tests
+    tracing::subscriber::set_global_default(recorder.clone()).unwrap();
+
+    let options = SchedulerOptions { threads: 2, ..Default::default() };
+    SyncScheduler::run_main::<TracedBank>(options, ());
+
+    let spans = recorder.spans.lock().unwrap();
+    let reactions: Vec<_> = spans
+        .values()
+        .filter(|span| span.metadata.name() == "reaction")
+        .filter(|span| span.fields["reactor"].contains("traced_bank"))
+        .collect();
+    assert_eq!(BANK_WIDTH, reactions.len());
+
+    for reaction in reactions {
+        for field in ["tag", "level", "reactor", "reaction"] {
+            assert!(
+                reaction.fields.contains_key(field),
+                "missing field {} in {:?}",
+                field,
+                reaction
+            );
+        }
+        assert_eq!(Some("traced"), reaction.fields.get("label").map(String::as_str));
+
+        let tag = &spans[&reaction.parent.expect("reaction span has no parent")];
+        assert_eq!("tag", tag.metadata.name());
+        assert_eq!(tag.fields["tag"], reaction.fields["tag"]);
+        assert_eq!("(T0, 0)", tag.fields["tag"]);
+    }
+}
@@ -0,0 +1,84 @@
+/*
+ * Copyright (c) 2021, TU Dresden.
+ *
+ * Redistribution and use in source and binary forms, with or without modification,
+ * are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice,
+ *    this list of conditions and the following disclaimer.
+ *
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the documentation
+ *    and/or other materials provided with the distribution.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY
+ * EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL
+ * THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+ * PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+ * INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+ * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
+ * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+//! Checks the [ZenoPolicy] of the scheduler.
+
+use std::panic::AssertUnwindSafe;
+
+use crate::test::testutil::{recorded, Behavior, TestParams, TestReactor};
+use crate::*;
+
+/// Schedules the logical action at the next microstep forever.
+fn zeno_loop() -> TestParams {
+    fn repeat() -> Option<Behavior> {
+        Some(Box::new(|ctx, actions| {
+            ctx.schedule(&mut actions.logical, Offset::Asap);
+        }))
+    }
+    TestParams {
+        on_startup: repeat(),
+        on_logical: repeat(),
+        ..Default::default()
+    }
+}
+
+fn zeno_options(on_zeno: ZenoPolicy) -> SchedulerOptions {
+    SchedulerOptions {
+        max_microsteps: Some(10),
+        on_zeno,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn zeno_policy_stop_shuts_down_at_offending_tag() {
+    let params = zeno_loop();
+    let record = params.record.clone();
+    SyncScheduler::run_main::<TestReactor>(zeno_options(ZenoPolicy::Stop), params);
+
+    let record = recorded(&record);
+    let repeats: Vec<_> = record
+        .iter()
+        .filter(|(name, ..)| *name == "logical")
+        .map(|(_, tag, _)| *tag)
+        .collect();
+    assert_eq!((1..=10).map(|m| tag!(T0, m)).collect::<Vec<_>>(), repeats);
+    // the reactions at the offending tag are not executed
+    assert_eq!(Some(&("shutdown", tag!(T0, 11), None)), record.last());
+}
+
+#[test]
+fn zeno_policy_panic_names_reactions() {
+    let params = zeno_loop();
+    let record = params.record.clone();
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        SyncScheduler::run_main::<TestReactor>(zeno_options(ZenoPolicy::Panic), params);
+    }));
+
+    let error = result.unwrap_err().downcast::<ZenoError>().unwrap();
+    assert_eq!(tag!(T0, 11), error.tag);
+    assert_eq!(vec!["/1@on_logical".to_string()], error.reactions);
+    // shutdown reactions are not executed
+    assert!(recorded(&record).iter().all(|(name, ..)| *name == "logical"));
+}
@@ -24,9 +24,174 @@
 
 //! Test utilities.
 
+use std::future::Future;
+use std::pin::{pin, Pin};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use crate::assembly::*;
 use crate::*;
 
 /// Set a port to a value
 pub fn set_port<T: Sync>(port: &mut Port<T>, v: T) {
     port.set_impl(Some(v))
 }
+
+/// Run a future to completion on the current thread.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark()
+        }
+    }
+
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+/// An [AsyncTimer] for [block_on], whose futures are woken
+/// up by a new thread.
+pub fn thread_timer(deadline: Instant) -> ThreadSleep {
+    ThreadSleep { deadline, started: false }
+}
+
+/// Future returned by [thread_timer].
+pub struct ThreadSleep {
+    deadline: Instant,
+    started: bool,
+}
+
+impl Future for ThreadSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        if !self.started {
+            self.started = true;
+            let (deadline, waker) = (self.deadline, cx.waker().clone());
+            thread::spawn(move || {
+                if let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+                    thread::sleep(remaining)
+                }
+                waker.wake()
+            });
+        }
+        Poll::Pending
+    }
+}
+
+/// What a [TestReactor] recorded: the name of a trigger, the
+/// tag at which it triggered a reaction, and its value.
+pub type Record = Arc<Mutex<Vec<(&'static str, EventTag, Option<u32>)>>>;
+
+/// Returns a copy of the entries of the record.
+pub fn recorded(record: &Record) -> Vec<(&'static str, EventTag, Option<u32>)> {
+    record.lock().unwrap().clone()
+}
+
+/// Behavior of a reaction of a [TestReactor].
+pub type Behavior = Box<dyn FnMut(&mut ReactionCtx, &mut TestActions) + Send>;
+
+/// The actions of a [TestReactor], which its reactions can schedule.
+pub struct TestActions {
+    pub logical: LogicalAction<u32>,
+    pub physical: PhysicalActionRef<u32>,
+}
+
+/// Parameters of a [TestReactor].
+#[derive(Default)]
+pub struct TestParams {
+    pub logical_spacing: Option<MinSpacing>,
+    pub physical_spacing: Option<MinSpacing>,
+    /// Whether the startup reaction is declared to spawn threads.
+    pub spawns_threads: bool,
+    pub on_startup: Option<Behavior>,
+    pub on_logical: Option<Behavior>,
+    pub on_physical: Option<Behavior>,
+    pub on_shutdown: Option<Behavior>,
+    pub record: Record,
+}
+
+/// A reactor with a logical and a physical action, whose
+/// reactions run the behaviors given in its [TestParams].
+/// The reactions to the actions and to shutdown record their
+/// trigger before that, as `logical`, `physical` or `shutdown`.
+pub struct TestReactor {
+    id: ReactorId,
+    actions: TestActions,
+    params: TestParams,
+}
+
+impl ReactorInitializer for TestReactor {
+    type Wrapped = ();
+    type Params = TestParams;
+    const MAX_REACTION_ID: LocalReactionId = LocalReactionId::new(4);
+
+    fn assemble(mut params: Self::Params, ctx: AssemblyCtx<Self>) -> AssemblyResult<FinishedReactor<Self>> {
+        ctx.assemble(|ctx| {
+            ctx.assemble_self(
+                |cc, id| {
+                    let actions = TestActions {
+                        logical: cc.new_logical_action_with_spacing("logical", None, params.logical_spacing.take()),
+                        physical: cc.new_physical_action_with_spacing("physical", None, params.physical_spacing.take()),
+                    };
+                    Ok(Self { id, actions, params })
+                },
+                4,
+                [Some("start"), Some("on_logical"), Some("on_physical"), Some("shutdown")],
+                |decl, me, [start, on_logical, on_physical, shutdown]| {
+                    decl.declare_triggers(TriggerId::STARTUP, start)?;
+                    if me.params.spawns_threads {
+                        decl.declare_spawns_threads(start)?;
+                    }
+                    decl.declare_triggers(me.actions.logical.get_id(), on_logical)?;
+                    decl.declare_triggers(me.actions.physical.get_id(), on_physical)?;
+                    decl.declare_triggers(TriggerId::SHUTDOWN, shutdown)?;
+                    Ok(())
+                },
+            )
+        })
+    }
+}
+
+impl ReactorBehavior for TestReactor {
+    fn id(&self) -> ReactorId {
+        self.id
+    }
+
+    fn react(&mut self, ctx: &mut ReactionCtx, rid: LocalReactionId) {
+        let (trigger, behavior) = match rid.raw() {
+            0 => (None, &mut self.params.on_startup),
+            1 => (Some(("logical", ctx.get(&self.actions.logical))), &mut self.params.on_logical),
+            2 => (
+                Some(("physical", ctx.get(&self.actions.physical))),
+                &mut self.params.on_physical,
+            ),
+            3 => (Some(("shutdown", None)), &mut self.params.on_shutdown),
+            _ => unreachable!(),
+        };
+        if let Some((name, value)) = trigger {
+            self.params.record.lock().unwrap().push((name, ctx.get_tag(), value));
+        }
+        if let Some(behavior) = behavior {
+            behavior(ctx, &mut self.actions)
+        }
+    }
+
+    fn cleanup_tag(&mut self, ctx: &CleanupCtx) {
+        ctx.cleanup_logical_action(&mut self.actions.logical);
+        ctx.cleanup_physical_action(&mut self.actions.physical);
+    }
+}
@@ -23,13 +23,17 @@
  */
 
 use std::fmt::{Debug, Display, Formatter};
-use std::ops::Add;
+use std::ops::{Add, Sub};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 /// Private concrete type of a microstep.
 pub(crate) type MS = u32;
 
 /// Type of the microsteps of an [EventTag](crate::EventTag).
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct MicroStep(MS);
 
 impl MicroStep {
@@ -52,3 +56,206 @@ impl Add<MS> for MicroStep {
         Self(self.0 + rhs)
     }
 }
+
+/// An absolute point in time, as a number of nanoseconds since
+/// the Unix epoch. Unlike an [Instant], it can be serialized,
+/// compared across processes, and represent instants before the
+/// epoch. The values [NEVER](Self::NEVER) and [FOREVER](Self::FOREVER)
+/// are smaller and greater than any other timestamp, and
+/// arithmetic saturates to them.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Timestamp(i64);
+
+impl Timestamp {
+    /// A timestamp before any other.
+    pub const NEVER: Timestamp = Timestamp(i64::MIN);
+    /// A timestamp after any other.
+    pub const FOREVER: Timestamp = Timestamp(i64::MAX);
+    /// The Unix epoch.
+    pub const EPOCH: Timestamp = Timestamp(0);
+
+    /// Create a timestamp from a number of nanoseconds since
+    /// the Unix epoch.
+    #[inline]
+    pub const fn from_nanos(nanos: i64) -> Self {
+        Self(nanos)
+    }
+
+    /// Returns the number of nanoseconds since the Unix epoch.
+    #[inline]
+    pub const fn as_nanos(self) -> i64 {
+        self.0
+    }
+
+    /// Returns the current physical time.
+    pub fn now() -> Self {
+        Self::from(SystemTime::now())
+    }
+
+    /// Whether this is [NEVER](Self::NEVER) or [FOREVER](Self::FOREVER).
+    #[inline]
+    pub fn is_sentinel(self) -> bool {
+        self == Self::NEVER || self == Self::FOREVER
+    }
+
+    /// Convert an [Instant] to a timestamp. [Instant]s are
+    /// mapped to the system clock using a reference point
+    /// that is taken once per process, so conversions are
+    /// consistent within a process, even if the system clock
+    /// is adjusted in the meantime.
+    pub fn from_instant(instant: Instant) -> Self {
+        let (ref_instant, ref_timestamp) = reference_point();
+        if instant >= ref_instant {
+            ref_timestamp.saturating_add(instant - ref_instant)
+        } else {
+            ref_timestamp.saturating_sub(ref_instant - instant)
+        }
+    }
+
+    /// Convert this timestamp to an [Instant], using the same
+    /// reference point as [Self::from_instant]. Returns None for
+    /// [NEVER](Self::NEVER), [FOREVER](Self::FOREVER), and
+    /// timestamps that an [Instant] cannot represent.
+    pub fn to_instant(self) -> Option<Instant> {
+        if self.is_sentinel() {
+            return None;
+        }
+        let (ref_instant, ref_timestamp) = reference_point();
+        if self >= ref_timestamp {
+            ref_instant.checked_add(self.duration_since(ref_timestamp)?)
+        } else {
+            ref_instant.checked_sub(ref_timestamp.duration_since(self)?)
+        }
+    }
+
+    /// Convert this timestamp to a [SystemTime]. Returns None
+    /// for [NEVER](Self::NEVER), [FOREVER](Self::FOREVER), and
+    /// timestamps that a [SystemTime] cannot represent.
+    pub fn to_system_time(self) -> Option<SystemTime> {
+        if self.is_sentinel() {
+            return None;
+        }
+        let offset = Duration::from_nanos(self.0.unsigned_abs());
+        if self.0 >= 0 {
+            UNIX_EPOCH.checked_add(offset)
+        } else {
+            UNIX_EPOCH.checked_sub(offset)
+        }
+    }
+
+    /// Returns the duration elapsed from `earlier` to this
+    /// timestamp, or None if `earlier` is later than this
+    /// timestamp, or if either is a sentinel.
+    pub fn duration_since(self, earlier: Timestamp) -> Option<Duration> {
+        if self.is_sentinel() || earlier.is_sentinel() {
+            return None;
+        }
+        let nanos = self.0.checked_sub(earlier.0)?;
+        u64::try_from(nanos).ok().map(Duration::from_nanos)
+    }
+
+    /// Add a duration to this timestamp. The result saturates
+    /// to [FOREVER](Self::FOREVER), and the sentinels are left
+    /// unchanged.
+    pub fn saturating_add(self, duration: Duration) -> Self {
+        if self.is_sentinel() {
+            return self;
+        }
+        let nanos = i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX);
+        Self(self.0.saturating_add(nanos))
+    }
+
+    /// Subtract a duration from this timestamp. The result
+    /// saturates to [NEVER](Self::NEVER), and the sentinels are
+    /// left unchanged.
+    pub fn saturating_sub(self, duration: Duration) -> Self {
+        if self.is_sentinel() {
+            return self;
+        }
+        let nanos = i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX);
+        Self(self.0.saturating_sub(nanos))
+    }
+}
+
+/// A pair of an [Instant] and the [Timestamp] it corresponds to.
+fn reference_point() -> (Instant, Timestamp) {
+    static REFERENCE: OnceLock<(Instant, Timestamp)> = OnceLock::new();
+    *REFERENCE.get_or_init(|| (Instant::now(), Timestamp::now()))
+}
+
+impl From<SystemTime> for Timestamp {
+    /// Convert a [SystemTime] to a timestamp, saturating if it
+    /// is too far from the epoch.
+    fn from(time: SystemTime) -> Self {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(after) => Timestamp::EPOCH.saturating_add(after),
+            Err(before) => Timestamp::EPOCH.saturating_sub(before.duration()),
+        }
+    }
+}
+
+impl Add<Duration> for Timestamp {
+    type Output = Self;
+    #[inline]
+    fn add(self, rhs: Duration) -> Self::Output {
+        self.saturating_add(rhs)
+    }
+}
+
+impl Sub<Duration> for Timestamp {
+    type Output = Self;
+    #[inline]
+    fn sub(self, rhs: Duration) -> Self::Output {
+        self.saturating_sub(rhs)
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::NEVER => write!(f, "never"),
+            Self::FOREVER => write!(f, "forever"),
+            Self(nanos) => write!(f, "{} ns", nanos),
+        }
+    }
+}
+
+/// A logical time: a [Timestamp] and a microstep. This is the
+/// absolute counterpart of an [EventTag](crate::EventTag), which
+/// is relative to the start of the program. It is meant for
+/// traces, persistence, and communication between processes.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogicalTime {
+    /// The time at which the tag occurs.
+    pub timestamp: Timestamp,
+    /// The microstep of the tag.
+    pub microstep: MicroStep,
+}
+
+impl LogicalTime {
+    /// A logical time before any other.
+    pub const NEVER: LogicalTime = LogicalTime {
+        timestamp: Timestamp::NEVER,
+        microstep: MicroStep::ZERO,
+    };
+    /// A logical time after any other.
+    pub const FOREVER: LogicalTime = LogicalTime {
+        timestamp: Timestamp::FOREVER,
+        microstep: MicroStep(MS::MAX),
+    };
+
+    /// Create a new logical time.
+    #[inline]
+    pub fn new(timestamp: Timestamp, microstep: MicroStep) -> Self {
+        Self { timestamp, microstep }
+    }
+}
+
+impl Display for LogicalTime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.timestamp, self.microstep)
+    }
+}
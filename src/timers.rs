@@ -21,16 +21,20 @@
  * STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF
  * THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
  */
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
+use crossbeam_utils::atomic::AtomicCell;
+
 use super::*;
 use crate::assembly::{TriggerId, TriggerLike};
 
 /// A timer is conceptually a logical action that may re-schedule
 /// itself periodically.
 ///
-/// For periodic timers, a reaction is synthesized which reschedules
-/// the timer.
+/// Timers are scheduled by the runtime when they fire. Reactions
+/// can stop and restart them, or change their period, see eg
+/// [ReactionCtx::start_timer].
 pub struct Timer {
     id: TriggerId,
 
@@ -38,15 +42,41 @@ pub struct Timer {
     /// which the timer starts to trigger.
     pub offset: Duration,
 
-    /// Period between events emitted by this timer. A period
-    /// of zero means that the timer will trigger exactly once
-    /// after the specified offset.
+    /// Period between events emitted by this timer, as declared.
+    /// A period of zero means that the timer will trigger exactly
+    /// once after the specified offset. The period may be changed
+    /// at runtime, see [Self::current_period].
     pub period: Duration,
+
+    /// State that changes at runtime.
+    state: Arc<Mutex<TimerState>>,
+    /// Tag of the latest firing. The timer is present at this
+    /// tag only. This is read by every reaction that checks
+    /// whether the timer is present, so it is not part of the
+    /// locked state.
+    fired_at: Arc<AtomicCell<Option<EventTag>>>,
+}
+
+#[derive(Debug)]
+struct TimerState {
+    /// The current period.
+    period: Duration,
+    /// Tag of the next firing, None if the timer is stopped
+    /// or will not trigger anymore. Firings that were scheduled
+    /// for another tag have been cancelled.
+    next: Option<EventTag>,
 }
 
 impl Timer {
     pub(crate) fn new(id: TriggerId, offset: Duration, period: Duration) -> Self {
-        Self { offset, period, id }
+        let state = TimerState { period, next: None };
+        Self {
+            offset,
+            period,
+            id,
+            state: Arc::new(Mutex::new(state)),
+            fired_at: Default::default(),
+        }
     }
 
     /// Whether the timer should repeat itself. A period of zero
@@ -54,7 +84,108 @@ impl Timer {
     /// specified offset.
     #[inline]
     pub fn is_periodic(&self) -> bool {
-        !self.period.is_zero()
+        !self.current_period().is_zero()
+    }
+
+    /// The current period of the timer, which is the declared
+    /// [period](Self::period) unless it was changed with
+    /// [ReactionCtx::set_timer_period].
+    pub fn current_period(&self) -> Duration {
+        self.state.lock().unwrap().period
+    }
+
+    /// Whether the timer is scheduled to trigger again.
+    pub fn is_running(&self) -> bool {
+        self.state.lock().unwrap().next.is_some()
+    }
+
+    /// (Re)start the timer so that it next fires at the given
+    /// tag, cancelling its other scheduled firings.
+    pub(crate) fn start(&self, next: EventTag) -> TimerFiring {
+        let mut state = self.state.lock().unwrap();
+        state.next = Some(next);
+        self.firing(next)
+    }
+
+    /// Returns a timer that shares the state of this one, so
+    /// that the debugger can tell whether it is present.
+    pub(crate) fn probe(&self) -> Timer {
+        Timer {
+            id: self.id,
+            offset: self.offset,
+            period: self.period,
+            state: self.state.clone(),
+            fired_at: self.fired_at.clone(),
+        }
+    }
+
+    fn firing(&self, tag: EventTag) -> TimerFiring {
+        TimerFiring {
+            state: self.state.clone(),
+            fired_at: self.fired_at.clone(),
+            tag,
+        }
+    }
+
+    /// Stop the timer, cancelling its scheduled firings. If it
+    /// fired at the current tag, it stays present until the end
+    /// of the tag.
+    pub(crate) fn stop(&self) {
+        self.state.lock().unwrap().next = None;
+    }
+
+    /// Change the period of the timer at the given tag. If the
+    /// timer is running and the period is not zero, the timer is
+    /// rescheduled to fire one period after that tag, and the
+    /// new firing is returned.
+    pub(crate) fn set_period(&self, now: EventTag, period: Duration) -> Option<TimerFiring> {
+        let rescheduled = {
+            let mut state = self.state.lock().unwrap();
+            state.period = period;
+            state.next.is_some() && !period.is_zero()
+        };
+        rescheduled.then(|| self.start(now.successor(period)))
+    }
+}
+
+/// A scheduled firing of a timer. It is cancelled if the timer
+/// is stopped or rescheduled before the firing happens.
+#[derive(Debug)]
+pub(crate) struct TimerFiring {
+    state: Arc<Mutex<TimerState>>,
+    fired_at: Arc<AtomicCell<Option<EventTag>>>,
+    tag: EventTag,
+}
+
+impl TimerFiring {
+    pub(crate) fn tag(&self) -> EventTag {
+        self.tag
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.state.lock().unwrap().next != Some(self.tag)
+    }
+
+    /// Fire the timer, unless this firing has been cancelled, in
+    /// which case this returns an error. If the timer is periodic,
+    /// its next firing is scheduled and returned.
+    pub(crate) fn fire(&self) -> Result<Option<TimerFiring>, ()> {
+        let mut state = self.state.lock().unwrap();
+        if state.next != Some(self.tag) {
+            return Err(());
+        }
+        self.fired_at.store(Some(self.tag));
+        if state.period.is_zero() {
+            state.next = None;
+            return Ok(None);
+        }
+        let next = self.tag.successor(state.period);
+        state.next = Some(next);
+        Ok(Some(TimerFiring {
+            state: self.state.clone(),
+            fired_at: self.fired_at.clone(),
+            tag: next,
+        }))
     }
 }
 
@@ -66,16 +197,8 @@ impl TriggerLike for Timer {
 
 impl ReactionTrigger<()> for Timer {
     fn is_present(&self, now: &EventTag, _start: &Instant) -> bool {
-        let elapsed = now.duration_since_start();
-        if elapsed == self.offset {
-            true
-        } else if elapsed < self.offset || !self.is_periodic() {
-            false
-        } else {
-            ((elapsed - self.offset).as_nanos() % self.period.as_nanos()) == 0
-        }
+        self.fired_at.load() == Some(*now)
     }
-
     #[inline]
     fn get_value(&self, now: &EventTag, start: &Instant) -> Option<()> {
         if self.is_present(now, start) {
@@ -100,39 +100,93 @@ macro_rules! after {
 /// assert_eq!(delay!(2 h), delay!(2 hours));
 /// assert_eq!(delay!(1 week), delay!(7 days));
 ///
+/// // fractional amounts, and the largest duration
+/// assert_eq!(delay!(1.5 s), delay!(1500 ms));
+/// assert_eq!(delay!(forever), Duration::MAX);
+///
+/// // integer amounts can be used in constants
+/// const PERIOD: Duration = delay!(3 s);
 /// ```
+///
+/// The units are those of [TimeUnit], which is also used to
+/// parse durations at runtime, see [try_parse_duration].
+/// Integer amounts are [u64], float amounts are [f64] and
+/// go through [TimeUnit::of].
 #[macro_export]
 macro_rules! delay {
     (0)                   => { $crate::Duration::from_nanos(0) };
-    ($amount:tt ns)       => { $crate::Duration::from_nanos($amount) };
+    (forever)             => { $crate::Duration::MAX };
+    ($amount:tt ns)       => { $crate::DelayAmount($amount).of($crate::TimeUnit::NANO) };
     ($amount:tt nsec)     => { delay!($amount ns) };
     ($amount:tt nsecs)    => { delay!($amount ns) };
-    ($amount:tt us)       => { $crate::Duration::from_micros($amount) };
+    ($amount:tt us)       => { $crate::DelayAmount($amount).of($crate::TimeUnit::MICRO) };
     ($amount:tt usec)     => { delay!($amount us) };
     ($amount:tt usecs)    => { delay!($amount us) };
-    ($amount:tt ms)       => { $crate::Duration::from_millis($amount) };
+    ($amount:tt ms)       => { $crate::DelayAmount($amount).of($crate::TimeUnit::MILLI) };
     ($amount:tt msec)     => { delay!($amount ms) };
     ($amount:tt msecs)    => { delay!($amount ms) };
-    ($amount:tt s)        => { $crate::Duration::from_secs($amount) };
+    ($amount:tt s)        => { $crate::DelayAmount($amount).of($crate::TimeUnit::SEC) };
     ($amount:tt sec)      => { delay!($amount s) };
     ($amount:tt secs)     => { delay!($amount s) };
     ($amount:tt second)   => { delay!($amount s) };
     ($amount:tt seconds)  => { delay!($amount s) };
-    ($amount:tt min)      => { $crate::Duration::from_secs(60 * $amount) };
+    ($amount:tt min)      => { $crate::DelayAmount($amount).of($crate::TimeUnit::MIN) };
     ($amount:tt mins)     => { delay!($amount min) };
     ($amount:tt minute)   => { delay!($amount min) };
     ($amount:tt minutes)  => { delay!($amount min) };
-    ($amount:tt h)        => { delay!((3600 * $amount) s) };
+    ($amount:tt h)        => { $crate::DelayAmount($amount).of($crate::TimeUnit::HOUR) };
     ($amount:tt hour)     => { delay!($amount h) };
     ($amount:tt hours)    => { delay!($amount h) };
-    ($amount:tt d)        => { delay!((24*$amount) h) };
+    ($amount:tt d)        => { $crate::DelayAmount($amount).of($crate::TimeUnit::DAY) };
     ($amount:tt day)      => { delay!($amount d) };
     ($amount:tt days)     => { delay!($amount d) };
-    ($amount:tt week)     => { delay!((7*$amount) d) };
+    ($amount:tt week)     => { $crate::DelayAmount($amount).of($crate::TimeUnit::WEEK) };
     ($amount:tt weeks)    => { delay!($amount week) };
     ($amount:tt $i:ident) => { compile_error!(concat!("Unknown time unit `", stringify!($i), "`")) };
 }
 
+/// Log a message from a reaction at level error, see [ReactionCtx::log](crate::ReactionCtx::log).
+/// The first argument is the [ReactionCtx](crate::ReactionCtx),
+/// the others are like those of [format!].
+///
+/// ```no_run
+/// # use reactor_rt::prelude::*;
+/// # let ctx: &mut ReactionCtx = panic!();
+/// ctx_error!(ctx, "unexpected value {}", 4);
+/// ctx_warn!(ctx, "late by {:?}", delay!(3 ms));
+/// ctx_info!(ctx, "started");
+/// ctx_debug!(ctx, "state: {:?}", (1, 2));
+/// ctx_trace!(ctx, "here");
+/// ```
+#[macro_export]
+macro_rules! ctx_error {
+    ($ctx:expr, $($arg:tt)+) => { $ctx.log($crate::LogLevel::Error, format_args!($($arg)+)) };
+}
+
+/// Log a message from a reaction at level warn, see [ctx_error!](crate::ctx_error).
+#[macro_export]
+macro_rules! ctx_warn {
+    ($ctx:expr, $($arg:tt)+) => { $ctx.log($crate::LogLevel::Warn, format_args!($($arg)+)) };
+}
+
+/// Log a message from a reaction at level info, see [ctx_error!](crate::ctx_error).
+#[macro_export]
+macro_rules! ctx_info {
+    ($ctx:expr, $($arg:tt)+) => { $ctx.log($crate::LogLevel::Info, format_args!($($arg)+)) };
+}
+
+/// Log a message from a reaction at level debug, see [ctx_error!](crate::ctx_error).
+#[macro_export]
+macro_rules! ctx_debug {
+    ($ctx:expr, $($arg:tt)+) => { $ctx.log($crate::LogLevel::Debug, format_args!($($arg)+)) };
+}
+
+/// Log a message from a reaction at level trace, see [ctx_error!](crate::ctx_error).
+#[macro_export]
+macro_rules! ctx_trace {
+    ($ctx:expr, $($arg:tt)+) => { $ctx.log($crate::LogLevel::Trace, format_args!($($arg)+)) };
+}
+
 /// Convenient macro to assert equality of the current tag.
 /// This is just shorthand for using `assert_eq!` with the
 /// syntax of [tag].
@@ -189,7 +243,8 @@ macro_rules! tag {
 }
 
 /// A unit of time, used in LF.
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TimeUnit {
     NANO,
     MICRO,
@@ -198,18 +253,20 @@ pub enum TimeUnit {
     MIN,
     HOUR,
     DAY,
+    WEEK,
 }
 
 impl TryFrom<&str> for TimeUnit {
     type Error = ();
 
-    /// This recognizes the same strings as LF
+    /// This recognizes the same strings as LF, and as the [delay!](crate::delay) macro.
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         let u = match value {
-            "day" | "days" => Self::DAY,
+            "week" | "weeks" => Self::WEEK,
+            "d" | "day" | "days" => Self::DAY,
             "h" | "hour" | "hours" => Self::HOUR,
-            "min" | "minute" | "minutes" => Self::MIN,
-            "s" | "sec" | "secs" => Self::SEC,
+            "min" | "mins" | "minute" | "minutes" => Self::MIN,
+            "s" | "sec" | "secs" | "second" | "seconds" => Self::SEC,
             "ms" | "msec" | "msecs" => Self::MILLI,
             "us" | "usec" | "usecs" => Self::MICRO,
             "ns" | "nsec" | "nsecs" => Self::NANO,
@@ -220,23 +277,192 @@ impl TryFrom<&str> for TimeUnit {
 }
 
 impl TimeUnit {
-    pub fn to_duration(&self, magnitude: u64) -> Duration {
+    /// Number of nanoseconds in one unit.
+    pub const fn nanos(&self) -> u64 {
         match *self {
-            TimeUnit::NANO => Duration::from_nanos(magnitude),
-            TimeUnit::MICRO => Duration::from_micros(magnitude),
-            TimeUnit::MILLI => Duration::from_millis(magnitude),
-            TimeUnit::SEC => Duration::from_secs(magnitude),
-            TimeUnit::MIN => Duration::from_secs(60 * magnitude),
-            TimeUnit::HOUR => Duration::from_secs(60 * 60 * magnitude),
-            TimeUnit::DAY => Duration::from_secs(60 * 60 * 24 * magnitude),
+            TimeUnit::NANO => 1,
+            TimeUnit::MICRO => 1_000,
+            TimeUnit::MILLI => 1_000_000,
+            TimeUnit::SEC => 1_000_000_000,
+            TimeUnit::MIN => 60 * 1_000_000_000,
+            TimeUnit::HOUR => 60 * 60 * 1_000_000_000,
+            TimeUnit::DAY => 24 * 60 * 60 * 1_000_000_000,
+            TimeUnit::WEEK => 7 * 24 * 60 * 60 * 1_000_000_000,
+        }
+    }
+
+    pub fn to_duration(&self, magnitude: u64) -> Duration {
+        self.of(magnitude)
+    }
+
+    /// Returns the duration of the given number of units. The
+    /// magnitude may be an integer or a float, but not negative.
+    /// This is what the [delay!](crate::delay) macro expands to.
+    ///
+    /// ### Panics
+    ///
+    /// If the magnitude is negative or the duration overflows.
+    pub fn of(&self, magnitude: impl Magnitude) -> Duration {
+        magnitude.times(*self)
+    }
+}
+
+/// The amount of a [delay!](crate::delay). Integer amounts
+/// use the const constructors of [Duration], so that the macro
+/// can be used in constants.
+#[doc(hidden)]
+pub struct DelayAmount<T>(pub T);
+
+impl DelayAmount<u64> {
+    pub const fn of(self, unit: TimeUnit) -> Duration {
+        match unit {
+            TimeUnit::NANO => Duration::from_nanos(self.0),
+            TimeUnit::MICRO => Duration::from_micros(self.0),
+            TimeUnit::MILLI => Duration::from_millis(self.0),
+            TimeUnit::SEC => Duration::from_secs(self.0),
+            TimeUnit::MIN => Duration::from_secs(60 * self.0),
+            TimeUnit::HOUR => Duration::from_secs(3600 * self.0),
+            TimeUnit::DAY => Duration::from_secs(24 * 3600 * self.0),
+            TimeUnit::WEEK => Duration::from_secs(7 * 24 * 3600 * self.0),
         }
     }
 }
 
+impl DelayAmount<f64> {
+    pub fn of(self, unit: TimeUnit) -> Duration {
+        unit.of(self.0)
+    }
+}
+
+/// A number of [TimeUnit]s, see [TimeUnit::of].
+pub trait Magnitude {
+    /// Returns the duration of this number of units.
+    fn times(self, unit: TimeUnit) -> Duration;
+}
+
+macro_rules! impl_magnitude_for_int {
+    ($($t:ty),*) => {$(
+        impl Magnitude for $t {
+            #[allow(unused_comparisons)]
+            fn times(self, unit: TimeUnit) -> Duration {
+                assert!(self >= 0, "negative duration: {} {:?}", self, unit);
+                let nanos = (self as u128) * (unit.nanos() as u128);
+                duration_from_nanos(nanos).expect("duration overflow")
+            }
+        }
+    )*};
+}
+
+impl_magnitude_for_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl Magnitude for f64 {
+    fn times(self, unit: TimeUnit) -> Duration {
+        assert!(self >= 0.0, "negative or NaN duration: {} {:?}", self, unit);
+        let nanos = (self * unit.nanos() as f64).round();
+        assert!(nanos <= u128::MAX as f64, "duration overflow");
+        duration_from_nanos(nanos as u128).expect("duration overflow")
+    }
+}
+
+impl Magnitude for f32 {
+    fn times(self, unit: TimeUnit) -> Duration {
+        (self as f64).times(unit)
+    }
+}
+
+fn duration_from_nanos(nanos: u128) -> Option<Duration> {
+    let secs = u64::try_from(nanos / 1_000_000_000).ok()?;
+    Some(Duration::new(secs, (nanos % 1_000_000_000) as u32))
+}
+
+/// A parsed amount of time, which may be negative.
+#[derive(Debug, Eq, PartialEq)]
+enum ParsedTime {
+    Forever { negative: bool },
+    Nanos { negative: bool, nanos: u64 },
+}
+
+/// Parse an amount of time. The grammar is:
+/// - an optional sign `-` or `+`,
+/// - then either `forever`, `0`, or a sequence of components,
+///   each being a decimal number followed by a [TimeUnit],
+///   possibly separated by whitespace, eg `1h 30min` or `1.5 s`.
+fn parse_time(t: &str) -> Result<ParsedTime, String> {
+    let t = t.trim();
+    if t.is_empty() {
+        return Err("cannot parse empty string".into());
+    }
+    let (negative, rest) = match t.as_bytes()[0] {
+        b'-' => (true, t[1..].trim_start()),
+        b'+' => (false, t[1..].trim_start()),
+        _ => (false, t),
+    };
+    if rest == "forever" {
+        return Ok(ParsedTime::Forever { negative });
+    } else if rest == "0" {
+        return Ok(ParsedTime::Nanos { negative, nanos: 0 });
+    }
+
+    let mut rest = rest;
+    let mut total: u64 = 0;
+    loop {
+        let num_end = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        let number = &rest[..num_end];
+        if number.is_empty() {
+            return Err(format!("expected a number, got '{}'", rest));
+        }
+        rest = rest[num_end..].trim_start();
+        let unit_end = rest.find(|c: char| !c.is_alphabetic()).unwrap_or(rest.len());
+        let unit = &rest[..unit_end];
+        if unit.is_empty() {
+            return Err("time unit required".into());
+        }
+        let unit = TimeUnit::try_from(unit).map_err(|_| format!("unknown time unit '{}'", unit))?;
+        let nanos = parse_component(number, unit)?;
+        total = total.checked_add(nanos).ok_or("duration too large")?;
+
+        rest = rest[unit_end..].trim_start();
+        if rest.is_empty() {
+            return Ok(ParsedTime::Nanos { negative, nanos: total });
+        }
+    }
+}
+
+/// Parse a decimal number of the given unit, rounded to the
+/// nearest nanosecond.
+fn parse_component(number: &str, unit: TimeUnit) -> Result<u64, String> {
+    let (int, frac) = number.split_once('.').unwrap_or((number, ""));
+    if frac.contains('.') || (int.is_empty() && frac.is_empty()) {
+        return Err(format!("invalid number '{}'", number));
+    }
+    let int: u64 = if int.is_empty() {
+        0
+    } else {
+        int.parse::<u64>().map_err(|e| format!("{}", e))?
+    };
+    let nanos = (int as u128) * (unit.nanos() as u128);
+
+    // digits beyond the 18th cannot matter after rounding to a nanosecond
+    let frac = &frac[..frac.len().min(18)];
+    let frac_nanos = if frac.is_empty() {
+        0
+    } else {
+        let scale = 10u128.pow(frac.len() as u32);
+        let frac: u128 = frac.parse().map_err(|e| format!("{}", e))?;
+        (frac * unit.nanos() as u128 + scale / 2) / scale
+    };
+    u64::try_from(nanos + frac_nanos).map_err(|_| "duration too large".into())
+}
+
 /// Parse a duration from a string. This is used for CLI
 /// parameter parsing in programs generated by LFC, specifically,
 /// to parse main parameters with `time` type, and scheduler
-/// options with time type.
+/// options with time type. Config files use it too.
+///
+/// Durations are written as a sequence of numbers with a [TimeUnit],
+/// which may be decimal, or as `forever`, which is [Duration::MAX].
+/// Durations must fit in 64 bits of nanoseconds. See
+/// [try_parse_signed_nanos] to parse negative amounts of time.
 ///
 /// ### Tests
 ///
@@ -250,37 +476,109 @@ impl TimeUnit {
 /// assert_eq!(try_parse_duration("30ns"),   Ok(Duration::from_nanos(30)));
 /// assert_eq!(try_parse_duration("30nsec"), Ok(Duration::from_nanos(30)));
 /// assert_eq!(try_parse_duration("30secs"), Ok(Duration::from_secs(30)));
+/// assert_eq!(try_parse_duration("1.5 s"),  Ok(Duration::from_millis(1500)));
+/// assert_eq!(try_parse_duration("1h30min"), Ok(Duration::from_secs(5400)));
+/// assert_eq!(try_parse_duration("1 h 30 min"), Ok(Duration::from_secs(5400)));
+/// assert_eq!(try_parse_duration("2 weeks"), Ok(Duration::from_secs(14 * 24 * 3600)));
+/// assert_eq!(try_parse_duration("forever"), Ok(Duration::MAX));
 /// // unit is not required for zero
 /// assert_eq!(try_parse_duration("0"), Ok(Duration::from_secs(0)));
 ///
 /// assert_eq!(try_parse_duration(""), Err("cannot parse empty string".into()));
 /// assert_eq!(try_parse_duration("30"), Err("time unit required".into()));
+/// assert_eq!(try_parse_duration("30 parsecs"), Err("unknown time unit 'parsecs'".into()));
+/// assert_eq!(try_parse_duration("-3 ms"), Err("negative duration '-3 ms'".into()));
 /// assert_eq!(try_parse_duration("30000000000000000000000ns"), Err("number too large to fit in target type".into()));
 ///
 /// ```
 ///
 pub fn try_parse_duration(t: &str) -> Result<Duration, String> {
-    // note: we parse this manually to avoid depending on regex
-    let mut chars = t.char_indices().skip_while(|(_, c)| c.is_numeric());
+    match parse_time(t)? {
+        ParsedTime::Nanos { nanos: 0, .. } => Ok(Duration::ZERO),
+        ParsedTime::Forever { negative: false } => Ok(Duration::MAX),
+        ParsedTime::Nanos { negative: false, nanos } => Ok(Duration::from_nanos(nanos)),
+        ParsedTime::Forever { negative: true } | ParsedTime::Nanos { negative: true, .. } => {
+            Err(format!("negative duration '{}'", t))
+        }
+    }
+}
+
+/// Parse a signed amount of time, as a number of nanoseconds.
+/// This accepts the same syntax as [try_parse_duration], plus
+/// a leading minus sign. `forever` and `-forever` are parsed
+/// as [i64::MAX] and [i64::MIN], which are the nanoseconds of
+/// [Timestamp::FOREVER](crate::Timestamp::FOREVER) and
+/// [Timestamp::NEVER](crate::Timestamp::NEVER).
+///
+/// ```
+/// use reactor_rt::try_parse_signed_nanos;
+///
+/// assert_eq!(try_parse_signed_nanos("-1.5 ms"), Ok(-1_500_000));
+/// assert_eq!(try_parse_signed_nanos("2 us"), Ok(2_000));
+/// assert_eq!(try_parse_signed_nanos("-forever"), Ok(i64::MIN));
+/// assert_eq!(try_parse_signed_nanos("20000 weeks"), Err("duration too large".into()));
+/// ```
+pub fn try_parse_signed_nanos(t: &str) -> Result<i64, String> {
+    match parse_time(t)? {
+        ParsedTime::Forever { negative } => Ok(if negative { i64::MIN } else { i64::MAX }),
+        ParsedTime::Nanos { negative, nanos } => {
+            let nanos = i64::try_from(nanos).map_err(|_| "duration too large".to_owned())?;
+            Ok(if negative { -nanos } else { nanos })
+        }
+    }
+}
 
-    if let Some((num_end, _)) = &chars.next() {
-        let magnitude: u64 = t[0..*num_end].parse::<u64>().map_err(|e| format!("{}", e))?;
+/// Format a duration for humans, so that [try_parse_duration]
+/// can read it back. Whole minutes, hours and days are written
+/// with these units, and the rest with the largest unit among
+/// seconds, milliseconds, microseconds and nanoseconds that is
+/// not greater than it, possibly with decimals.
+///
+/// ```
+/// use reactor_rt::{delay, format_duration};
+/// use std::time::Duration;
+///
+/// assert_eq!(format_duration(delay!(1500 ms)), "1.5 s");
+/// assert_eq!(format_duration(delay!(20 ms)), "20 ms");
+/// assert_eq!(format_duration(delay!(1 us) + delay!(1 ns)), "1.001 us");
+/// assert_eq!(format_duration(delay!(90 min)), "1 h 30 min");
+/// assert_eq!(format_duration(delay!(2 d) + delay!(30 s)), "2 d 30 s");
+/// assert_eq!(format_duration(Duration::ZERO), "0");
+/// assert_eq!(format_duration(Duration::MAX), "forever");
+/// ```
+pub fn format_duration(d: Duration) -> String {
+    use std::fmt::Write;
 
-        let unit = t[*num_end..].trim();
+    if d == Duration::MAX {
+        return "forever".into();
+    } else if d.is_zero() {
+        return "0".into();
+    }
+    let mut parts = Vec::new();
+    let mut secs = d.as_secs();
+    for (unit_secs, name) in [(24 * 3600, "d"), (3600, "h"), (60, "min")] {
+        if secs >= unit_secs {
+            parts.push(format!("{} {}", secs / unit_secs, name));
+            secs %= unit_secs;
+        }
+    }
 
-        let duration = match TimeUnit::try_from(unit) {
-            Ok(unit) => unit.to_duration(magnitude),
-            Err(_) => return Err(format!("unknown time unit '{}'", unit)),
+    let rest = secs as u128 * 1_000_000_000 + d.subsec_nanos() as u128;
+    if rest > 0 {
+        let (unit_nanos, digits, name) = match rest {
+            1_000_000_000.. => (1_000_000_000, 9, "s"),
+            1_000_000.. => (1_000_000, 6, "ms"),
+            1_000.. => (1_000, 3, "us"),
+            _ => (1, 0, "ns"),
         };
-        Ok(duration)
-    } else if t != "0" {
-        // no unit
-        if !t.is_empty() {
-            Err("time unit required".into())
-        } else {
-            Err("cannot parse empty string".into())
+        let mut part = (rest / unit_nanos).to_string();
+        let frac = rest % unit_nanos;
+        if frac > 0 {
+            let frac = format!("{:0width$}", frac, width = digits);
+            write!(part, ".{}", frac.trim_end_matches('0')).unwrap();
         }
-    } else {
-        Ok(Duration::from_secs(0))
+        write!(part, " {}", name).unwrap();
+        parts.push(part);
     }
+    parts.join(" ")
 }